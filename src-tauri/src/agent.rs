@@ -0,0 +1,663 @@
+// Rust-side command agent.
+//
+// Owns the device control plane (heartbeat, /command/pull, /command/ack) so the
+// PC keeps obeying admin commands even if the webview hangs or the React tree
+// crashes. The UI only receives Tauri events from here.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::catalog::CatalogStore;
+use crate::device_client::DeviceClient;
+use crate::kiosk::KioskState;
+use crate::power::{PowerKind, PowerRequest, PowerScheduler};
+use crate::supervisor::Supervisor;
+use crate::credentials::DeviceCredentials;
+
+/// Event carrying a command/event for the UI (`{ event, payload }`)
+pub const EVENT_AGENT_COMMAND: &str = "agent-command";
+/// Event carrying the backend connection state (`bool`)
+pub const EVENT_AGENT_CONNECTION: &str = "agent-connection";
+/// Event carrying each heartbeat response (e.g. `{ "status": "locked" }`)
+pub const EVENT_AGENT_HEARTBEAT: &str = "agent-heartbeat";
+
+const PULL_TIMEOUT_SECS: u64 = 25;
+const PULL_RETRY_DELAY: Duration = Duration::from_secs(5);
+const PULL_IDLE_DELAY: Duration = Duration::from_millis(500);
+const KIOSK_STATUS_INTERVAL: Duration = Duration::from_secs(300);
+
+// Commands that are forwarded to the UI as-is instead of being executed
+const UI_EVENT_COMMANDS: &[&str] = &["chat.message", "pc.time.update", "shop.purchase", "notification", "message"];
+
+/// Where the agent reports what it is doing. Implemented by `tauri::AppHandle`
+/// in the app, and by a recorder when driving the agent against a mock backend.
+pub trait AgentEvents: Send + Sync + 'static {
+    fn emit(&self, event: &str, payload: Value);
+}
+
+impl AgentEvents for tauri::AppHandle {
+    fn emit(&self, event: &str, payload: Value) {
+        use tauri::Manager;
+        let _ = self.emit_all(event, payload);
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct PendingCommand {
+    id: i64,
+    command: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Managed state tracking the running agent. Each start gets a fresh flag so
+/// loops from a previous run can never be revived by a restart.
+#[derive(Default)]
+pub struct AgentState {
+    running: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+impl AgentState {
+    pub fn is_running(&self) -> bool {
+        self.running.lock().unwrap()
+            .as_ref()
+            .map(|flag| flag.load(Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+
+    pub fn stop(&self) {
+        if let Some(flag) = self.running.lock().unwrap().take() {
+            flag.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// App-wide state the agent acts on
+pub struct AgentServices {
+    pub power: Arc<PowerScheduler>,
+    pub catalog: Arc<CatalogStore>,
+    pub supervisor: Arc<Supervisor>,
+    pub kiosk: Arc<KioskState>,
+}
+
+pub struct CommandAgent<E: AgentEvents> {
+    events: Arc<E>,
+    client: DeviceClient,
+    power: Arc<PowerScheduler>,
+    catalog: Arc<CatalogStore>,
+    supervisor: Arc<Supervisor>,
+    kiosk: Arc<KioskState>,
+    config_dir: PathBuf,
+    running: Arc<AtomicBool>,
+}
+
+impl<E: AgentEvents> CommandAgent<E> {
+    pub fn new(
+        events: Arc<E>,
+        services: AgentServices,
+        creds: DeviceCredentials,
+        config_dir: PathBuf,
+        running: Arc<AtomicBool>,
+    ) -> Result<Self, String> {
+        // Long-poll must outlive the server-side pull timeout
+        let client = DeviceClient::with_timeout(creds, Duration::from_secs(PULL_TIMEOUT_SECS + 10))?;
+        let AgentServices { power, catalog, supervisor, kiosk } = services;
+        Ok(Self { events, client, power, catalog, supervisor, kiosk, config_dir, running })
+    }
+
+    /// Spawn the heartbeat, command pull and kiosk status loops on the async runtime.
+    pub fn spawn(self) {
+        let agent = Arc::new(self);
+        log_info!("[Agent] Starting command agent for PC #{}", agent.client.pc_id());
+
+        let heartbeat = agent.clone();
+        tauri::async_runtime::spawn(async move {
+            heartbeat.report_fingerprint().await;
+            heartbeat.sync_exec_policy().await;
+            if crate::config::current().catalog_sync {
+                if let Err(e) = heartbeat.sync_catalog().await {
+                    log_warn!("[Agent] Catalog sync failed: {}", e);
+                }
+            }
+            heartbeat.heartbeat_loop().await
+        });
+        let status = agent.clone();
+        tauri::async_runtime::spawn(async move { status.kiosk_status_loop().await });
+        tauri::async_runtime::spawn(async move { agent.command_pull_loop().await });
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    fn set_connected(&self, connected: bool) {
+        self.events.emit(EVENT_AGENT_CONNECTION, json!(connected));
+    }
+
+    fn notify(&self, event: &str, payload: Value) {
+        self.events.emit(EVENT_AGENT_COMMAND, json!({ "event": event, "payload": payload }));
+    }
+
+    /// Tell the backend which hardware components changed since the last run
+    async fn report_fingerprint(&self) {
+        let config_dir = self.config_dir.clone();
+        let report = match tauri::async_runtime::spawn_blocking(move || crate::fingerprint::compare(&config_dir)).await {
+            Ok(Ok(report)) => report,
+            Ok(Err(e)) => {
+                log_warn!("[Agent] Failed to compute fingerprint report: {}", e);
+                return;
+            }
+            Err(e) => {
+                log_warn!("[Agent] Fingerprint task failed: {}", e);
+                return;
+            }
+        };
+        let changed = match report.report.as_ref() {
+            Some(r) => !r.changed.is_empty() || !r.missing.is_empty() || !r.added.is_empty(),
+            None => {
+                // First run: nothing to compare against yet
+                self.commit_fingerprint(&report.current);
+                return;
+            }
+        };
+        if !changed {
+            return;
+        }
+        let body = json!({
+            "fingerprint": report.current.fingerprint,
+            "version": report.current.version,
+            "components": report.current.components,
+            "match": report.report,
+        });
+        // Only move the baseline once the backend has accepted the change,
+        // otherwise a failed report would be lost for good
+        match self.client.post("/clientpc/fingerprint", &body).await {
+            Ok(_) => self.commit_fingerprint(&report.current),
+            Err(e) => log_warn!("[Agent] Failed to report fingerprint change: {}", e),
+        }
+    }
+
+    fn commit_fingerprint(&self, fingerprint: &crate::fingerprint::Fingerprint) {
+        if let Err(e) = crate::fingerprint::commit(&self.config_dir, fingerprint) {
+            log_warn!("[Agent] Failed to store fingerprint baseline: {}", e);
+        }
+    }
+
+    /// Pull the executable allow-list for this PC. Missing endpoint or
+    /// network errors keep the last stored policy.
+    async fn sync_exec_policy(&self) {
+        match self.client.get("/clientpc/exec-policy").await {
+            Ok(Value::Null) => {}
+            Ok(doc) => {
+                if let Err(e) = self.store_exec_policy(doc) {
+                    log_warn!("[Agent] Rejected executable policy from backend: {}", e);
+                }
+            }
+            Err(e) => log_warn!("[Agent] Failed to fetch executable policy: {}", e),
+        }
+    }
+
+    fn store_exec_policy(&self, doc: Value) -> Result<usize, String> {
+        let policy: crate::policy::ExecPolicy = serde_json::from_value(doc).map_err(|e| format!("Invalid policy: {}", e))?;
+        crate::policy::save(&self.config_dir, &policy)?;
+        log_info!("[Agent] Stored executable policy ({:?}, {} rules)", policy.mode, policy.rules.len());
+        Ok(policy.rules.len())
+    }
+
+    async fn close_apps(&self, params: &Value) -> Result<Vec<crate::apps::CloseResult>, String> {
+        let timeout = crate::apps::graceful_timeout(params.get("graceful_timeout").and_then(|v| v.as_u64()))?;
+        let pids: Vec<u32> = match params.get("pid").and_then(|v| v.as_u64()) {
+            Some(pid) => vec![u32::try_from(pid).map_err(|_| format!("Invalid PID {}", pid))?],
+            None => self.supervisor.sessions().iter().flat_map(|s| s.pids.iter().copied()).collect(),
+        };
+        tauri::async_runtime::spawn_blocking(move || {
+            let mut results = Vec::new();
+            for pid in pids {
+                // Children of an earlier PID may already be gone
+                match crate::apps::close(pid, timeout) {
+                    Ok(result) => results.push(result),
+                    Err(e) => log_warn!("[Agent] {}", e),
+                }
+            }
+            results
+        })
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+    }
+
+    async fn sync_catalog(&self) -> Result<usize, String> {
+        let count = crate::catalog::sync_from_backend(&self.catalog, &self.client).await?;
+        self.events.emit(crate::catalog::EVENT_CATALOG_CHANGED, json!(self.catalog.list()));
+        crate::catalog::spawn_icon_refresh(self.catalog.clone(), self.events.clone());
+        Ok(count)
+    }
+
+    async fn heartbeat_loop(&self) {
+        while self.is_running() {
+            match self.client.post("/clientpc/heartbeat", &json!({})).await {
+                Ok(response) => {
+                    self.set_connected(true);
+                    self.events.emit(EVENT_AGENT_HEARTBEAT, response);
+                }
+                Err(e) => {
+                    log_warn!("[Agent] Heartbeat failed: {}", e);
+                    self.set_connected(false);
+                }
+            }
+            // Re-read every round so config changes apply live
+            tokio::time::sleep(crate::config::current().heartbeat_interval()).await;
+        }
+        log_info!("[Agent] Heartbeat loop stopped");
+    }
+
+    /// Report the kiosk settings of this PC so admins see drift across the café
+    async fn kiosk_status_loop(&self) {
+        while self.is_running() {
+            let config_dir = self.config_dir.clone();
+            let hook_installed = self.kiosk.shortcuts_installed();
+            let status = tauri::async_runtime::spawn_blocking(move || {
+                let exe = crate::kiosk_setup::current_exe()?;
+                crate::kiosk_setup::status(crate::settings::platform().as_ref(), &config_dir, &exe, hook_installed)
+            })
+            .await
+            .map_err(|e| format!("Task join error: {}", e));
+            match status {
+                Ok(Ok(status)) => {
+                    if let Err(e) = self.client.post("/clientpc/kiosk-status", &json!(status)).await {
+                        log_warn!("[Agent] Failed to report kiosk status: {}", e);
+                    }
+                }
+                Ok(Err(e)) | Err(e) => log_warn!("[Agent] Failed to read kiosk status: {}", e),
+            }
+            tokio::time::sleep(KIOSK_STATUS_INTERVAL).await;
+        }
+        log_info!("[Agent] Kiosk status loop stopped");
+    }
+
+    async fn command_pull_loop(&self) {
+        while self.is_running() {
+            match self.client.post("/command/pull", &json!({ "timeout": PULL_TIMEOUT_SECS })).await {
+                Ok(response) => {
+                    self.set_connected(true);
+                    let commands: Vec<PendingCommand> = serde_json::from_value(response).unwrap_or_default();
+                    for cmd in commands {
+                        if !self.is_running() {
+                            break;
+                        }
+                        self.handle_command(cmd).await;
+                    }
+                }
+                Err(e) => {
+                    log_warn!("[Agent] Command pull failed, retrying in 5s: {}", e);
+                    self.set_connected(false);
+                    tokio::time::sleep(PULL_RETRY_DELAY).await;
+                }
+            }
+            tokio::time::sleep(PULL_IDLE_DELAY).await;
+        }
+        log_info!("[Agent] Command pull loop stopped");
+    }
+
+    async fn handle_command(&self, cmd: PendingCommand) {
+        // Params may arrive JSON-encoded as a string
+        let params = match &cmd.params {
+            Value::String(s) => serde_json::from_str(s).unwrap_or_else(|_| cmd.params.clone()),
+            other => other.clone(),
+        };
+
+        if UI_EVENT_COMMANDS.contains(&cmd.command.as_str()) {
+            self.notify(&cmd.command, params);
+            self.ack(cmd.id, "SUCCEEDED", json!({ "ok": true })).await;
+            return;
+        }
+
+        log_info!("[Agent] Executing command: {} {}", cmd.command, params);
+        self.ack(cmd.id, "RUNNING", Value::Null).await;
+
+        match self.execute(cmd.id, &cmd.command, params).await {
+            Ok(result) => self.ack(cmd.id, "SUCCEEDED", result).await,
+            Err(e) => {
+                log_warn!("[Agent] Command {} failed: {}", cmd.command, e);
+                self.ack(cmd.id, "FAILED", json!({ "error": e })).await;
+            }
+        }
+    }
+
+    async fn execute(&self, command_id: i64, command: &str, params: Value) -> Result<Value, String> {
+        let result = match command {
+            "lock" => {
+                self.notify("lock", params);
+                if let Err(e) = self.power.controller().lock() {
+                    log_warn!("[Agent] System lock failed, using event only: {}", e);
+                }
+                json!({ "status": "locked" })
+            }
+            "unlock" => {
+                self.notify("unlock", Value::Null);
+                json!({ "status": "unlocked" })
+            }
+            "shutdown" => {
+                self.notify("shutdown", Value::Null);
+                match self.schedule_power(PowerKind::Shutdown, &params, "Primus: System shutdown initiated") {
+                    Ok(op) => json!({ "status": "shutting_down", "operation": op }),
+                    Err(e) => json!({ "status": "shutdown_failed", "error": e }),
+                }
+            }
+            "restart" | "reboot" => {
+                self.notify("restart", Value::Null);
+                match self.schedule_power(PowerKind::Restart, &params, "Primus: System restart initiated") {
+                    Ok(op) => json!({ "status": "restarting", "operation": op }),
+                    Err(e) => json!({ "status": "restart_failed", "error": e }),
+                }
+            }
+            // Backend uses 'logout', the client historically used 'logoff'
+            "logoff" | "logout" => {
+                self.notify(command, Value::Null);
+                let (ok, failed) = if command == "logout" {
+                    ("logged_out", "logout_failed")
+                } else {
+                    ("logging_off", "logoff_failed")
+                };
+                match self.power.controller().logoff() {
+                    Ok(_) => json!({ "status": ok }),
+                    Err(e) => json!({ "status": failed, "error": e }),
+                }
+            }
+            "cancel_shutdown" => match self.power.cancel(self.events.as_ref()) {
+                Ok(result) if result.cancelled => json!({ "status": "shutdown_cancelled", "operation": result.operation }),
+                Ok(_) => json!({ "status": "nothing_to_cancel" }),
+                Err(e) => json!({ "status": "cancel_failed", "error": e }),
+            },
+            "login" => {
+                self.notify("login", params);
+                json!({ "status": "login_prompt_shown" })
+            }
+            // Params: `{ "format": "png"|"jpeg", "quality": 1-100, "max_width": px }`
+            "screenshot" => {
+                let settings = crate::config::current().screenshot.with_overrides(&params)?;
+                let info = crate::screenshot::capture_and_upload(Some(&self.client), settings, Some(command_id)).await?;
+                json!({ "status": "screenshot_uploaded", "screenshot": info })
+            }
+            "catalog.sync" => {
+                let games = self.sync_catalog().await?;
+                json!({ "status": "catalog_synced", "games": games })
+            }
+            "policy.update" => {
+                let rules = self.store_exec_policy(params)?;
+                json!({ "status": "policy_updated", "rules": rules })
+            }
+            // Params: a (partial) `ShortcutPolicy`, e.g. `{ "in_game": ["Win", "Alt+Tab"] }`
+            "kiosk.shortcuts" => {
+                let cfg = crate::config::apply_patch(json!({ "kiosk": { "shortcuts": params } }))?;
+                log_info!("[Agent] Updated shortcut policy");
+                self.events.emit(crate::config::EVENT_CONFIG_CHANGED, serde_json::to_value(&cfg).unwrap_or_default());
+                json!({ "status": "shortcuts_updated", "shortcuts": cfg.kiosk.shortcuts })
+            }
+            // Params: `{ "pid": n, "graceful_timeout": secs }`; without a pid
+            // every game launched through Primus is closed (session end)
+            "app.close" => {
+                let results = self.close_apps(&params).await?;
+                json!({ "status": "app_closed", "results": results })
+            }
+            other => return Err(format!("Unknown command: {}", other)),
+        };
+        Ok(result)
+    }
+
+    /// Params: `{ "delay": secs, "reason": "...", "force": bool }`, all optional
+    fn schedule_power(&self, kind: PowerKind, params: &Value, default_reason: &str) -> Result<crate::power::PendingOperation, String> {
+        let req = PowerRequest {
+            delay_secs: params.get("delay").and_then(|v| v.as_u64())
+                .map(|d| d.min(u32::MAX as u64) as u32)
+                .unwrap_or(crate::power::DEFAULT_DELAY_SECS),
+            reason: params.get("reason").and_then(|v| v.as_str())
+                .filter(|r| !r.trim().is_empty())
+                .unwrap_or(default_reason)
+                .to_string(),
+            force: params.get("force").and_then(|v| v.as_bool()).unwrap_or(false),
+        };
+        self.power.schedule(self.events.clone(), kind, req)
+    }
+
+    async fn ack(&self, command_id: i64, state: &str, result: Value) {
+        let body = json!({ "command_id": command_id, "state": state, "result": result });
+        if let Err(e) = self.client.post("/command/ack", &body).await {
+            log_warn!("[Agent] Failed to send ACK for command {}: {}", command_id, e);
+        }
+    }
+}
+
+/// Start the agent if device credentials exist. Returns false when the
+/// device has not been registered yet.
+pub fn start(app_handle: &tauri::AppHandle, state: &AgentState, config_dir: PathBuf) -> Result<bool, String> {
+    // Held until the agent is spawned, so concurrent starts can't both get past the check
+    let mut slot = state.running.lock().unwrap();
+    if slot.as_ref().map(|flag| flag.load(Ordering::SeqCst)).unwrap_or(false) {
+        return Ok(true);
+    }
+    let creds = match crate::load_device_credentials(&config_dir)? {
+        Some(creds) if !creds.device_secret.is_empty() => creds,
+        _ => {
+            log_info!("[Agent] No valid device credentials found - handshake required");
+            return Ok(false);
+        }
+    };
+    let running = Arc::new(AtomicBool::new(true));
+    let services = {
+        use tauri::Manager;
+        AgentServices {
+            power: app_handle.state::<Arc<PowerScheduler>>().inner().clone(),
+            catalog: app_handle.state::<Arc<CatalogStore>>().inner().clone(),
+            supervisor: app_handle.state::<Arc<Supervisor>>().inner().clone(),
+            kiosk: app_handle.state::<Arc<KioskState>>().inner().clone(),
+        }
+    };
+    let agent = CommandAgent::new(Arc::new(app_handle.clone()), services, creds, config_dir, running.clone())?;
+    *slot = Some(running);
+    agent.spawn();
+    Ok(true)
+}
+
+/// Start the command agent (e.g. right after the handshake saved credentials)
+#[tauri::command]
+pub async fn start_command_agent(app_handle: tauri::AppHandle, state: tauri::State<'_, AgentState>) -> Result<bool, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+    start(&app_handle, &state, config_dir)
+}
+
+#[tauri::command]
+pub async fn stop_command_agent(state: tauri::State<'_, AgentState>) -> Result<(), String> {
+    state.stop();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn command_agent_status(state: tauri::State<'_, AgentState>) -> Result<bool, String> {
+    Ok(state.is_running())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::power::{DryRunPower, PowerCall};
+
+    /// Records every event instead of emitting it to a webview
+    #[derive(Default)]
+    struct RecordingEvents(Mutex<Vec<(String, Value)>>);
+
+    impl AgentEvents for RecordingEvents {
+        fn emit(&self, event: &str, payload: Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    impl RecordingEvents {
+        fn named(&self, event: &str) -> Vec<Value> {
+            self.0.lock().unwrap().iter().filter(|(e, _)| e == event).map(|(_, p)| p.clone()).collect()
+        }
+    }
+
+    type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// Local HTTP backend that answers `{}` to everything and records
+    /// `(path, json body)` of each request
+    fn stub_backend() -> (String, Requests) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                recorded.lock().unwrap().push((path, serde_json::from_slice(&body).unwrap_or(Value::Null)));
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}",
+                );
+            }
+        });
+        (url, requests)
+    }
+
+    fn test_agent(backend: &str) -> (CommandAgent<RecordingEvents>, Arc<RecordingEvents>, Arc<DryRunPower>) {
+        let dir = std::env::temp_dir().join(format!("primus-agent-test-{}", uuid::Uuid::new_v4().simple()));
+        let events = Arc::new(RecordingEvents::default());
+        let power = Arc::new(DryRunPower::default());
+        let creds = DeviceCredentials { pc_id: 42, license_key: "license".to_string(), device_secret: "secret".to_string() };
+        let agent = CommandAgent {
+            events: events.clone(),
+            client: DeviceClient::new(creds).unwrap().with_backend(backend),
+            power: Arc::new(PowerScheduler::new(power.clone())),
+            catalog: Arc::new(CatalogStore::open(dir.clone(), dir.join("icons"))),
+            supervisor: Arc::new(Supervisor::new(events.clone(), Box::new(|_| {}))),
+            kiosk: Arc::new(KioskState::default()),
+            config_dir: dir,
+            running: Arc::new(AtomicBool::new(true)),
+        };
+        (agent, events, power)
+    }
+
+    fn command(id: i64, name: &str, params: Value) -> PendingCommand {
+        PendingCommand { id, command: name.to_string(), params }
+    }
+
+    fn acks(requests: &Requests) -> Vec<Value> {
+        requests.lock().unwrap().iter().filter(|(path, _)| path == "/api/command/ack").map(|(_, body)| body.clone()).collect()
+    }
+
+    #[test]
+    fn executes_command_and_acks_running_then_succeeded() {
+        let (url, requests) = stub_backend();
+        let (agent, events, _) = test_agent(&url);
+        tauri::async_runtime::block_on(agent.handle_command(command(7, "unlock", Value::Null)));
+
+        let acks = acks(&requests);
+        assert_eq!(acks.len(), 2);
+        assert_eq!(acks[0], json!({ "command_id": 7, "state": "RUNNING", "result": null }));
+        assert_eq!(acks[1], json!({ "command_id": 7, "state": "SUCCEEDED", "result": { "status": "unlocked" } }));
+        assert_eq!(events.named(EVENT_AGENT_COMMAND), vec![json!({ "event": "unlock", "payload": null })]);
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+
+    #[test]
+    fn forwards_ui_commands_with_string_params() {
+        let (url, requests) = stub_backend();
+        let (agent, events, _) = test_agent(&url);
+        tauri::async_runtime::block_on(agent.handle_command(command(8, "message", json!("{\"text\":\"hi\"}"))));
+
+        assert_eq!(acks(&requests), vec![json!({ "command_id": 8, "state": "SUCCEEDED", "result": { "ok": true } })]);
+        assert_eq!(events.named(EVENT_AGENT_COMMAND), vec![json!({ "event": "message", "payload": { "text": "hi" } })]);
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+
+    #[test]
+    fn unknown_command_is_acked_as_failed() {
+        let (url, requests) = stub_backend();
+        let (agent, _, _) = test_agent(&url);
+        tauri::async_runtime::block_on(agent.handle_command(command(9, "format_disk", Value::Null)));
+
+        let acks = acks(&requests);
+        assert_eq!(acks.len(), 2);
+        assert_eq!(acks[1], json!({ "command_id": 9, "state": "FAILED", "result": { "error": "Unknown command: format_disk" } }));
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+
+    #[test]
+    fn shutdown_is_scheduled_with_the_requested_options() {
+        let (url, _) = stub_backend();
+        let (agent, _, power) = test_agent(&url);
+        let params = json!({ "delay": 60, "reason": "Closing time", "force": true });
+        let result = tauri::async_runtime::block_on(agent.execute(1, "shutdown", params)).unwrap();
+
+        assert_eq!(result["status"], "shutting_down");
+        assert_eq!(power.calls(), vec![PowerCall::Shutdown { delay_secs: 60, reason: "Closing time".to_string(), force: true }]);
+        assert_eq!(agent.power.pending().map(|op| op.kind), Some(PowerKind::Shutdown));
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+
+    #[test]
+    fn restart_uses_defaults_without_params() {
+        let (url, _) = stub_backend();
+        let (agent, _, power) = test_agent(&url);
+        let result = tauri::async_runtime::block_on(agent.execute(2, "reboot", Value::Null)).unwrap();
+
+        assert_eq!(result["status"], "restarting");
+        assert_eq!(power.calls(), vec![PowerCall::Restart {
+            delay_secs: crate::power::DEFAULT_DELAY_SECS,
+            reason: "Primus: System restart initiated".to_string(),
+            force: false,
+        }]);
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+
+    #[test]
+    fn cancel_shutdown_aborts_the_pending_operation() {
+        let (url, _) = stub_backend();
+        let (agent, events, power) = test_agent(&url);
+        tauri::async_runtime::block_on(agent.execute(3, "shutdown", json!({ "delay": 60 }))).unwrap();
+        let result = tauri::async_runtime::block_on(agent.execute(4, "cancel_shutdown", Value::Null)).unwrap();
+
+        assert_eq!(result["status"], "shutdown_cancelled");
+        assert_eq!(power.calls().last(), Some(&PowerCall::CancelShutdown));
+        assert!(agent.power.pending().is_none());
+        assert_eq!(events.named(crate::power::EVENT_POWER_CANCELLED).len(), 1);
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+
+    #[test]
+    fn cancel_shutdown_without_pending_operation() {
+        let (url, _) = stub_backend();
+        let (agent, _, power) = test_agent(&url);
+        let result = tauri::async_runtime::block_on(agent.execute(5, "cancel_shutdown", Value::Null)).unwrap();
+
+        assert_eq!(result["status"], "nothing_to_cancel");
+        assert_eq!(power.calls(), vec![PowerCall::CancelShutdown]);
+        let _ = std::fs::remove_dir_all(&agent.config_dir);
+    }
+}
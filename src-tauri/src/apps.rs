@@ -0,0 +1,573 @@
+// Running applications, as the user sees them: one entry per top-level window.
+//
+// - Windows: EnumWindows, keeping visible, unowned windows that aren't tool
+//   windows, cloaked (suspended UWP / other virtual desktop) or the shell
+// - Linux: the EWMH client list of the window manager (X11/XWayland), read with
+//   xprop, keeping normal and dialog windows that appear in the taskbar
+//
+// Both lists are returned topmost first. Primus's own windows are left out.
+// The app switcher focuses one of these windows by PID (EWMH activation
+// through wmctrl or xdotool on Linux).
+//
+// `close_app` asks a process to close (WM_CLOSE to its windows on Windows,
+// SIGTERM on Linux), then kills whatever is left of its process tree. From the
+// UI it only works on games Primus launched, unless a staff token is given.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
+
+use crate::supervisor::Supervisor;
+
+pub const DEFAULT_GRACEFUL_TIMEOUT_SECS: u64 = 10;
+const MAX_GRACEFUL_TIMEOUT_SECS: u64 = 120;
+// Roles allowed to close arbitrary processes (matches the admin UI)
+const STAFF_ROLES: &[&str] = &["admin", "owner", "superadmin", "staff"];
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AppInfo {
+    pub name: String,
+    /// Full path of the executable, empty if it can't be read
+    pub exe_path: String,
+    pub window_title: String,
+    pub pid: u32,
+    /// HWND on Windows, X11 window id on Linux
+    pub window_id: u64,
+    /// False when minimized (or hidden by the window manager)
+    pub visible: bool,
+    /// Cached PNG, for `convertFileSrc`
+    pub icon_path: Option<String>,
+}
+
+/// A top-level window that passed the platform's "user-facing" filter
+struct Window {
+    id: u64,
+    pid: u32,
+    title: String,
+    visible: bool,
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::Window;
+    use winapi::shared::minwindef::{BOOL, DWORD, LPARAM, TRUE};
+    use winapi::shared::windef::HWND;
+    use winapi::shared::winerror::S_OK;
+    use winapi::um::dwmapi::{DwmGetWindowAttribute, DWMWA_CLOAKED};
+    use winapi::um::winuser::{
+        EnumWindows, GetClassNameW, GetWindow, GetWindowLongW, GetWindowTextLengthW, GetWindowTextW,
+        GetWindowThreadProcessId, IsIconic, IsWindowVisible, GWL_EXSTYLE, GW_OWNER, WS_EX_APPWINDOW, WS_EX_TOOLWINDOW,
+    };
+
+    // Desktop and taskbar windows
+    const SHELL_WINDOW_CLASSES: &[&str] = &["Progman", "WorkerW", "Shell_TrayWnd", "Shell_SecondaryTrayWnd"];
+
+    unsafe fn class_name(hwnd: HWND) -> String {
+        let mut buf = [0u16; 256];
+        let len = GetClassNameW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+        String::from_utf16_lossy(&buf[..len.max(0) as usize])
+    }
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam as *mut Vec<Window>);
+        if IsWindowVisible(hwnd) == 0 || !GetWindow(hwnd, GW_OWNER).is_null() {
+            return TRUE;
+        }
+        let ex_style = GetWindowLongW(hwnd, GWL_EXSTYLE) as DWORD;
+        if ex_style & WS_EX_TOOLWINDOW != 0 && ex_style & WS_EX_APPWINDOW == 0 {
+            return TRUE;
+        }
+        let mut cloaked: DWORD = 0;
+        let hr = DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_CLOAKED,
+            &mut cloaked as *mut DWORD as *mut _,
+            std::mem::size_of::<DWORD>() as DWORD,
+        );
+        if hr == S_OK && cloaked != 0 {
+            return TRUE;
+        }
+        if SHELL_WINDOW_CLASSES.contains(&class_name(hwnd).as_str()) {
+            return TRUE;
+        }
+
+        let len = GetWindowTextLengthW(hwnd);
+        if len <= 0 {
+            return TRUE;
+        }
+        let mut buf = vec![0u16; len as usize + 1];
+        let copied = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+        let mut pid: DWORD = 0;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        windows.push(Window {
+            id: hwnd as usize as u64,
+            pid,
+            title: String::from_utf16_lossy(&buf[..copied.max(0) as usize]),
+            visible: IsIconic(hwnd) == 0,
+        });
+        TRUE
+    }
+
+    /// Top-level windows in z-order, topmost first
+    pub(super) fn windows() -> Vec<Window> {
+        let mut windows: Vec<Window> = Vec::new();
+        unsafe {
+            EnumWindows(Some(collect), &mut windows as *mut Vec<Window> as LPARAM);
+        }
+        windows
+    }
+
+    /// Restore if minimized and bring to the foreground
+    pub(super) fn activate(id: u64) -> Result<(), String> {
+        use winapi::shared::minwindef::FALSE;
+        use winapi::um::processthreadsapi::GetCurrentThreadId;
+        use winapi::um::winuser::{AttachThreadInput, BringWindowToTop, GetForegroundWindow, SetForegroundWindow, ShowWindow, SW_RESTORE};
+
+        let hwnd = id as usize as HWND;
+        unsafe {
+            if IsIconic(hwnd) != 0 {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            // Only the foreground thread may hand out focus, so share its
+            // input state for the switch
+            let foreground_thread = GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut());
+            let own_thread = GetCurrentThreadId();
+            let attached = foreground_thread != 0
+                && foreground_thread != own_thread
+                && AttachThreadInput(own_thread, foreground_thread, TRUE) != 0;
+            BringWindowToTop(hwnd);
+            let ok = SetForegroundWindow(hwnd);
+            if attached {
+                AttachThreadInput(own_thread, foreground_thread, FALSE);
+            }
+            if ok == 0 {
+                return Err("Windows refused to bring the window to the foreground".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    unsafe extern "system" fn post_close(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        use winapi::um::winuser::{PostMessageW, WM_CLOSE};
+        let (pids, posted) = &mut *(lparam as *mut (&[u32], usize));
+        let mut pid: DWORD = 0;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if pids.contains(&pid) && PostMessageW(hwnd, WM_CLOSE, 0, 0) != 0 {
+            *posted += 1;
+        }
+        TRUE
+    }
+
+    /// Post WM_CLOSE to every top-level window of `pids`; false if there were none
+    pub(super) fn request_close(pids: &[u32]) -> bool {
+        let mut state: (&[u32], usize) = (pids, 0);
+        unsafe {
+            EnumWindows(Some(post_close), &mut state as *mut (&[u32], usize) as LPARAM);
+        }
+        state.1 > 0
+    }
+
+    pub(super) fn exe_path(pid: u32) -> Option<String> {
+        use winapi::um::handleapi::CloseHandle;
+        use winapi::um::processthreadsapi::OpenProcess;
+        use winapi::um::winbase::QueryFullProcessImageNameW;
+        use winapi::um::winnt::PROCESS_QUERY_LIMITED_INFORMATION;
+
+        unsafe {
+            let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if handle.is_null() {
+                return None;
+            }
+            let mut buf = [0u16; 32768];
+            let mut len = buf.len() as DWORD;
+            let ok = QueryFullProcessImageNameW(handle, 0, buf.as_mut_ptr(), &mut len);
+            CloseHandle(handle);
+            (ok != 0).then(|| String::from_utf16_lossy(&buf[..len as usize]))
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+mod platform {
+    use super::Window;
+    use std::process::Command;
+
+    fn xprop(args: &[&str]) -> Option<String> {
+        let output = Command::new("xprop").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Value of a string property line: `_NET_WM_NAME(UTF8_STRING) = "Title"`
+    fn unquote(value: &str) -> String {
+        let value = value.trim();
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+        value.replace("\\\"", "\"").replace("\\\\", "\\")
+    }
+
+    fn window(id: &str) -> Option<Window> {
+        let props = xprop(&["-id", id, "_NET_WM_PID", "_NET_WM_NAME", "WM_NAME", "_NET_WM_STATE", "_NET_WM_WINDOW_TYPE"])?;
+        parse_window(id, &props)
+    }
+
+    /// Window from `xprop -id` output, `None` if it isn't user-facing
+    fn parse_window(id: &str, props: &str) -> Option<Window> {
+        let mut pid = None;
+        let (mut net_name, mut name) = (None, None);
+        let (mut state, mut kind) = (String::new(), String::new());
+        for line in props.lines() {
+            // Missing properties print "... not found." / "no such atom"
+            let Some((key, value)) = line.split_once(" = ") else { continue };
+            match key.split('(').next().unwrap_or("") {
+                "_NET_WM_PID" => pid = value.trim().parse::<u32>().ok(),
+                "_NET_WM_NAME" => net_name = Some(unquote(value)),
+                "WM_NAME" => name = Some(unquote(value)),
+                "_NET_WM_STATE" => state = value.to_string(),
+                "_NET_WM_WINDOW_TYPE" => kind = value.to_string(),
+                _ => {}
+            }
+        }
+
+        if !kind.is_empty() && !kind.contains("_NET_WM_WINDOW_TYPE_NORMAL") && !kind.contains("_NET_WM_WINDOW_TYPE_DIALOG") {
+            return None;
+        }
+        if state.contains("_NET_WM_STATE_SKIP_TASKBAR") {
+            return None;
+        }
+        Some(Window {
+            id: u64::from_str_radix(id.trim_start_matches("0x"), 16).ok()?,
+            pid: pid?,
+            title: net_name.or(name).unwrap_or_default(),
+            visible: !state.contains("_NET_WM_STATE_HIDDEN"),
+        })
+    }
+
+    /// Managed windows in stacking order, topmost first
+    pub(super) fn windows() -> Vec<Window> {
+        // `_NET_CLIENT_LIST_STACKING(WINDOW): window id # 0x1e00003, 0x2200007`
+        let Some(list) = xprop(&["-root", "_NET_CLIENT_LIST_STACKING"]) else {
+            return Vec::new();
+        };
+        let Some((_, ids)) = list.split_once('#') else {
+            return Vec::new();
+        };
+        ids.split(',').map(str::trim).filter(|id| id.starts_with("0x")).rev().filter_map(window).collect()
+    }
+
+    /// `_NET_ACTIVE_WINDOW` request: switches desktop, unminimizes and raises
+    pub(super) fn activate(id: u64) -> Result<(), String> {
+        let id = format!("0x{:x}", id);
+        let tools: [(&str, Vec<&str>); 2] = [("wmctrl", vec!["-i", "-a", &id]), ("xdotool", vec!["windowactivate", &id])];
+        let mut last_error = String::new();
+        for (tool, args) in tools {
+            match Command::new(tool).args(&args).output() {
+                Ok(output) if output.status.success() => return Ok(()),
+                Ok(output) => last_error = format!("{} failed: {}", tool, String::from_utf8_lossy(&output.stderr).trim()),
+                Err(_) => continue,
+            }
+        }
+        if last_error.is_empty() {
+            last_error = "wmctrl or xdotool is required to switch windows".to_string();
+        }
+        Err(last_error)
+    }
+
+    pub(super) fn exe_path(pid: u32) -> Option<String> {
+        std::fs::read_link(format!("/proc/{}/exe", pid)).ok().map(|p| p.to_string_lossy().to_string())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn unquotes_string_properties() {
+            assert_eq!(unquote(r#" "Counter-Strike 2" "#), "Counter-Strike 2");
+            assert_eq!(unquote(r#""Say \"hi\" C:\\Games""#), r#"Say "hi" C:\Games"#);
+            assert_eq!(unquote("unquoted"), "unquoted");
+        }
+
+        #[test]
+        fn parses_xprop_output() {
+            let props = "_NET_WM_PID(CARDINAL) = 4242\n\
+                _NET_WM_NAME(UTF8_STRING) = \"Dota 2\"\n\
+                WM_NAME(STRING) = \"dota2\"\n\
+                _NET_WM_STATE(ATOM) = _NET_WM_STATE_HIDDEN\n\
+                _NET_WM_WINDOW_TYPE(ATOM) = _NET_WM_WINDOW_TYPE_NORMAL\n";
+            let window = parse_window("0x2200007", props).unwrap();
+            assert_eq!(window.id, 0x2200007);
+            assert_eq!(window.pid, 4242);
+            assert_eq!(window.title, "Dota 2");
+            assert!(!window.visible);
+        }
+
+        #[test]
+        fn falls_back_to_wm_name_and_tolerates_missing_properties() {
+            let props = "_NET_WM_PID(CARDINAL) = 17\n\
+                _NET_WM_NAME:  not found.\n\
+                WM_NAME(STRING) = \"xterm\"\n\
+                _NET_WM_STATE:  not found.\n\
+                _NET_WM_WINDOW_TYPE:  not found.\n";
+            let window = parse_window("0x1e00003", props).unwrap();
+            assert_eq!(window.pid, 17);
+            assert_eq!(window.title, "xterm");
+            assert!(window.visible);
+        }
+
+        #[test]
+        fn skips_windows_that_are_not_user_facing() {
+            let with = |extra: &str| format!("_NET_WM_PID(CARDINAL) = 17\nWM_NAME(STRING) = \"x\"\n{}\n", extra);
+            assert!(parse_window("0x1", &with("_NET_WM_WINDOW_TYPE(ATOM) = _NET_WM_WINDOW_TYPE_DOCK")).is_none());
+            assert!(parse_window("0x1", &with("_NET_WM_STATE(ATOM) = _NET_WM_STATE_SKIP_TASKBAR")).is_none());
+            assert!(parse_window("0x1", &with("_NET_WM_WINDOW_TYPE(ATOM) = _NET_WM_WINDOW_TYPE_DIALOG")).is_some());
+            // No PID, or an id that isn't hex
+            assert!(parse_window("0x1", "WM_NAME(STRING) = \"x\"\n").is_none());
+            assert!(parse_window("0xzz", &with("")).is_none());
+        }
+    }
+}
+
+/// User-facing application windows, topmost first. Icons are looked up (and
+/// cached) when `icon_dir` is given.
+pub fn running_apps(icon_dir: Option<&Path>) -> Vec<AppInfo> {
+    let own_pid = std::process::id();
+    platform::windows()
+        .into_iter()
+        .filter(|w| w.pid != 0 && w.pid != own_pid && !w.title.trim().is_empty())
+        .map(|w| {
+            let exe_path = platform::exe_path(w.pid).unwrap_or_default();
+            let name = Path::new(&exe_path)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| w.title.clone());
+            let icon_path = icon_dir
+                .filter(|_| !exe_path.is_empty())
+                .and_then(|dir| crate::icons::resolve(dir, &exe_path, ""))
+                .map(|p| p.to_string_lossy().to_string());
+            AppInfo {
+                name,
+                exe_path,
+                window_title: w.title,
+                pid: w.pid,
+                window_id: w.id,
+                visible: w.visible,
+                icon_path,
+            }
+        })
+        .collect()
+}
+
+/// Focus the topmost window of `pid`, returning its title
+pub fn focus(pid: u32) -> Result<String, String> {
+    let window = platform::windows()
+        .into_iter()
+        .find(|w| w.pid == pid)
+        .ok_or_else(|| format!("Process {} has no window to switch to", pid))?;
+    platform::activate(window.id)?;
+    log_info!("[Apps] Switched to {} (PID {})", window.title, pid);
+    Ok(window.title)
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CloseResult {
+    pub pid: u32,
+    /// The whole tree exited before the timeout
+    pub graceful: bool,
+    /// Processes that had to be killed
+    pub killed: Vec<u32>,
+}
+
+pub fn graceful_timeout(secs: Option<u64>) -> Result<Duration, String> {
+    let secs = secs.unwrap_or(DEFAULT_GRACEFUL_TIMEOUT_SECS);
+    if secs > MAX_GRACEFUL_TIMEOUT_SECS {
+        return Err(format!("Graceful timeout must be at most {} seconds", MAX_GRACEFUL_TIMEOUT_SECS));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// `pid` and its descendants, with start times to survive PID reuse
+fn process_tree(sys: &System, pid: u32) -> HashMap<u32, u64> {
+    let processes = sys.processes();
+    let mut tree = HashMap::new();
+    if let Some(p) = processes.get(&Pid::from_u32(pid)) {
+        tree.insert(pid, p.start_time());
+    }
+    loop {
+        let before = tree.len();
+        for (child, p) in processes {
+            if p.parent().map(|parent| tree.contains_key(&parent.as_u32())).unwrap_or(false) {
+                tree.entry(child.as_u32()).or_insert(p.start_time());
+            }
+        }
+        if tree.len() == before {
+            return tree;
+        }
+    }
+}
+
+/// Ask `pid` to close, wait up to `timeout`, then kill its remaining tree.
+/// Blocking.
+pub fn close(pid: u32, timeout: Duration) -> Result<CloseResult, String> {
+    if pid == 0 || pid == std::process::id() {
+        return Err(format!("Refusing to close PID {}", pid));
+    }
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new());
+    // Taken up front: children are re-parented once the root exits
+    let mut tree = process_tree(&sys, pid);
+    if tree.is_empty() {
+        return Err(format!("No process with PID {}", pid));
+    }
+
+    #[cfg(target_os = "windows")]
+    let requested = platform::request_close(&tree.keys().copied().collect::<Vec<_>>());
+    #[cfg(not(target_os = "windows"))]
+    let requested = sys
+        .process(Pid::from_u32(pid))
+        .and_then(|p| p.kill_with(sysinfo::Signal::Term))
+        .unwrap_or(false);
+
+    let deadline = Instant::now() + if requested { timeout } else { Duration::ZERO };
+    loop {
+        sys.refresh_processes_specifics(ProcessRefreshKind::new());
+        let processes = sys.processes();
+        tree.retain(|pid, start| processes.get(&Pid::from_u32(*pid)).map(|p| p.start_time() == *start).unwrap_or(false));
+        if tree.is_empty() {
+            log_info!("[Apps] PID {} closed gracefully", pid);
+            return Ok(CloseResult { pid, graceful: true, killed: Vec::new() });
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+
+    let mut killed = Vec::new();
+    for child in tree.keys() {
+        if let Some(p) = sys.process(Pid::from_u32(*child)) {
+            if p.kill() {
+                killed.push(*child);
+            } else {
+                log_warn!("[Apps] Failed to kill PID {}", child);
+            }
+        }
+    }
+    killed.sort_unstable();
+    log_info!("[Apps] Killed PID {} tree: {:?}", pid, killed);
+    Ok(CloseResult { pid, graceful: false, killed })
+}
+
+/// Check a user token against the backend and require a staff role
+pub(crate) async fn verify_staff_token(token: &str) -> Result<(), String> {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+    let response = http
+        .get(format!("{}/api/auth/me", crate::backend_url()))
+        .bearer_auth(token)
+        .send()
+        .await
+        .map_err(|e| format!("Could not verify admin token: {}", e))?;
+    if !response.status().is_success() {
+        return Err("Admin token was rejected".to_string());
+    }
+    let me: serde_json::Value = response.json().await.map_err(|e| format!("Unexpected auth response: {}", e))?;
+    let role = me.get("role").and_then(|r| r.as_str()).unwrap_or("");
+    if !STAFF_ROLES.contains(&role) {
+        return Err(format!("Role '{}' may not close this application", role));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_running_apps(app_handle: tauri::AppHandle) -> Result<Vec<AppInfo>, String> {
+    let icon_dir = crate::icons::cache_dir(&app_handle).ok();
+    tauri::async_runtime::spawn_blocking(move || running_apps(icon_dir.as_deref()))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+}
+
+#[tauri::command]
+pub async fn switch_to_app(window: tauri::Window, pid: u32) -> Result<String, String> {
+    let title = tauri::async_runtime::spawn_blocking(move || focus(pid))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))??;
+    // Get the kiosk window out of the way, as when launching a game
+    #[cfg(target_os = "windows")]
+    let _ = window.set_always_on_top(false);
+    let _ = window.minimize();
+    Ok(format!("Switched to {}", title))
+}
+
+/// Close an application: graceful first, then kill its process tree. Apps not
+/// launched through Primus need a staff `admin_token`.
+#[tauri::command]
+pub async fn close_app(
+    supervisor: tauri::State<'_, Arc<Supervisor>>,
+    pid: u32,
+    graceful_timeout: Option<u64>,
+    admin_token: Option<String>,
+) -> Result<CloseResult, String> {
+    let timeout = self::graceful_timeout(graceful_timeout)?;
+    if !supervisor.owns(pid) {
+        match admin_token.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+            Some(token) => verify_staff_token(token).await?,
+            None => return Err(format!("PID {} was not launched by Primus; an admin token is required", pid)),
+        }
+    }
+    tauri::async_runtime::spawn_blocking(move || close(pid, timeout))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graceful_timeout_defaults_and_bounds() {
+        assert_eq!(graceful_timeout(None), Ok(Duration::from_secs(DEFAULT_GRACEFUL_TIMEOUT_SECS)));
+        assert_eq!(graceful_timeout(Some(0)), Ok(Duration::ZERO));
+        assert_eq!(graceful_timeout(Some(MAX_GRACEFUL_TIMEOUT_SECS)), Ok(Duration::from_secs(MAX_GRACEFUL_TIMEOUT_SECS)));
+        assert!(graceful_timeout(Some(MAX_GRACEFUL_TIMEOUT_SECS + 1)).is_err());
+    }
+
+    #[test]
+    fn refuses_to_close_itself() {
+        assert!(close(0, Duration::ZERO).is_err());
+        assert!(close(std::process::id(), Duration::ZERO).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn close_lets_a_process_exit_on_sigterm() {
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        // Reap it as soon as it exits, as the supervisor would
+        let waiter = std::thread::spawn(move || child.wait());
+
+        let result = close(pid, Duration::from_secs(5)).unwrap();
+        assert!(result.graceful);
+        assert!(result.killed.is_empty());
+        assert!(waiter.join().unwrap().is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn close_kills_a_process_that_ignores_sigterm() {
+        let mut child = std::process::Command::new("sh").args(["-c", "trap '' TERM; sleep 30"]).spawn().unwrap();
+        let pid = child.id();
+        let waiter = std::thread::spawn(move || child.wait());
+        // Let the shell install its trap first
+        std::thread::sleep(Duration::from_millis(300));
+
+        let result = close(pid, Duration::from_secs(1)).unwrap();
+        assert!(!result.graceful);
+        assert!(result.killed.contains(&pid), "{:?}", result.killed);
+        assert!(waiter.join().unwrap().is_ok());
+    }
+}
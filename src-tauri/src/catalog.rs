@@ -0,0 +1,563 @@
+// Game catalog.
+//
+// Games added by staff on this PC ("local") and games pushed from the café-wide
+// catalog on the backend ("cafe") are kept in `games.json` in the app config
+// dir, so they survive webview storage being cleared. Launch profiles are
+// stored with the rest of the client config, keyed by the catalog id.
+//
+// Icons are extracted in the background into the icon cache; listed games carry
+// the cached PNG as `icon_path` (the stored `icon_path` is only the source).
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agent::AgentEvents;
+use crate::launch::LaunchProfile;
+
+const CATALOG_FILE: &str = "games.json";
+const CATALOG_VERSION: u32 = 1;
+/// Emitted with the full game list whenever the catalog changes
+pub const EVENT_CATALOG_CHANGED: &str = "catalog-changed";
+
+const MAX_NAME_LEN: usize = 128;
+const MAX_CATEGORY_LEN: usize = 32;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CatalogSource {
+    Local,
+    Cafe,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CatalogGame {
+    pub id: String,
+    pub name: String,
+    pub source: CatalogSource,
+    /// Executable or launcher URI
+    pub exe_path: String,
+    pub install_path: Option<String>,
+    /// Image to use instead of the executable's own icon; listed games get
+    /// the cached PNG here
+    pub icon_path: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Filled in from the config when listing; not stored in games.json
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub launch_profile: Option<LaunchProfile>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct GameInput {
+    pub name: String,
+    pub exe_path: String,
+    pub install_path: Option<String>,
+    pub icon_path: Option<String>,
+    pub categories: Vec<String>,
+    pub launch_profile: Option<LaunchProfile>,
+}
+
+/// Fields to change; absent fields are left alone
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct GamePatch {
+    pub name: Option<String>,
+    pub exe_path: Option<String>,
+    pub install_path: Option<String>,
+    pub icon_path: Option<String>,
+    pub categories: Option<Vec<String>>,
+    pub launch_profile: Option<LaunchProfile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CatalogFile {
+    version: u32,
+    games: Vec<CatalogGame>,
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Game name is required".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN || name.chars().any(|c| c.is_control()) {
+        return Err(format!("Game name must be at most {} printable characters", MAX_NAME_LEN));
+    }
+    Ok(name.to_string())
+}
+
+fn validate_target(exe_path: &str, must_exist: bool) -> Result<String, String> {
+    let exe_path = exe_path.trim();
+    if crate::library::is_launch_uri(exe_path) {
+        return Ok(exe_path.to_string());
+    }
+    if !Path::new(exe_path).is_absolute() {
+        return Err(format!("Game executable must be an absolute path: {}", exe_path));
+    }
+    if must_exist && !Path::new(exe_path).is_file() {
+        return Err("Game executable not found at specified path".to_string());
+    }
+    Ok(exe_path.to_string())
+}
+
+fn validate_optional_path(what: &str, path: Option<String>) -> Result<Option<String>, String> {
+    match path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()) {
+        Some(p) if !Path::new(&p).is_absolute() => Err(format!("{} must be an absolute path: {}", what, p)),
+        other => Ok(other),
+    }
+}
+
+fn validate_categories(categories: Vec<String>) -> Result<Vec<String>, String> {
+    let mut out: Vec<String> = Vec::new();
+    for category in categories {
+        let category = category.trim().to_string();
+        if category.is_empty() {
+            continue;
+        }
+        if category.chars().count() > MAX_CATEGORY_LEN {
+            return Err(format!("Category must be at most {} characters: {}", MAX_CATEGORY_LEN, category));
+        }
+        if !out.iter().any(|c| c.eq_ignore_ascii_case(&category)) {
+            out.push(category);
+        }
+    }
+    Ok(out)
+}
+
+/// Paths are only case-insensitive on Windows
+fn same_target(a: &str, b: &str) -> bool {
+    if cfg!(target_os = "windows") {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+/// What an icon was extracted from, so edits invalidate it
+fn icon_source(game: &CatalogGame) -> String {
+    format!("{}|{}", game.exe_path, game.icon_path.as_deref().unwrap_or(""))
+}
+
+/// Synced from the café catalog on the backend, not added through the UI
+pub fn is_backend_game(game: &CatalogGame) -> bool {
+    game.source == CatalogSource::Cafe && game.id.starts_with("cafe:")
+}
+
+/// Games stored in `games.json`, without profiles or cached icons
+pub fn read_games(config_dir: &Path) -> Vec<CatalogGame> {
+    match fs::read_to_string(config_dir.join(CATALOG_FILE)) {
+        Ok(data) => match serde_json::from_str::<CatalogFile>(&data) {
+            Ok(file) => file.games,
+            Err(e) => {
+                log_warn!("[Catalog] Ignoring invalid {}: {}", CATALOG_FILE, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+pub struct CatalogStore {
+    config_dir: PathBuf,
+    games: Mutex<Vec<CatalogGame>>,
+    icon_dir: PathBuf,
+    /// game id -> (icon source, cached PNG)
+    icons: Mutex<HashMap<String, (String, Option<PathBuf>)>>,
+}
+
+impl CatalogStore {
+    pub fn open(config_dir: PathBuf, icon_dir: PathBuf) -> Self {
+        let games = read_games(&config_dir);
+        Self { config_dir, games: Mutex::new(games), icon_dir, icons: Mutex::new(HashMap::new()) }
+    }
+
+    fn persist(&self, games: &[CatalogGame]) -> Result<(), String> {
+        fs::create_dir_all(&self.config_dir).map_err(|e| e.to_string())?;
+        let path = self.config_dir.join(CATALOG_FILE);
+        let tmp = path.with_extension("json.tmp");
+        let data = serde_json::to_string_pretty(&CatalogFile { version: CATALOG_VERSION, games: games.to_vec() })
+            .map_err(|e| e.to_string())?;
+        fs::write(&tmp, data).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    /// All games, with their launch profiles and cached icons attached
+    pub fn list(&self) -> Vec<CatalogGame> {
+        let profiles = crate::config::current().launch_profiles;
+        let icons = self.icons.lock().unwrap();
+        let mut games = self.games.lock().unwrap().clone();
+        for game in games.iter_mut() {
+            game.launch_profile = profiles.get(&game.id).cloned();
+            game.icon_path = icons
+                .get(&game.id)
+                .filter(|(source, _)| *source == icon_source(game))
+                .and_then(|(_, icon)| icon.as_ref())
+                .map(|icon| icon.to_string_lossy().to_string());
+        }
+        games.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        games
+    }
+
+    pub fn get(&self, id: &str) -> Option<CatalogGame> {
+        self.list().into_iter().find(|g| g.id == id)
+    }
+
+    pub fn add(&self, input: GameInput) -> Result<CatalogGame, String> {
+        let now = Utc::now();
+        let game = CatalogGame {
+            id: format!("local:{}", uuid::Uuid::new_v4().simple()),
+            name: validate_name(&input.name)?,
+            source: CatalogSource::Local,
+            exe_path: validate_target(&input.exe_path, true)?,
+            install_path: validate_optional_path("Install path", input.install_path)?,
+            icon_path: validate_optional_path("Icon path", input.icon_path)?,
+            categories: validate_categories(input.categories)?,
+            launch_profile: None,
+            created_at: now,
+            updated_at: now,
+        };
+        {
+            let mut games = self.games.lock().unwrap();
+            if games.iter().any(|g| same_target(&g.exe_path, &game.exe_path)) {
+                return Err(format!("'{}' is already in the catalog", game.exe_path));
+            }
+            let mut updated = games.clone();
+            updated.push(game.clone());
+            self.persist(&updated)?;
+            *games = updated;
+        }
+        if let Some(profile) = input.launch_profile {
+            set_profile(&game.id, Some(profile))?;
+        }
+        self.get(&game.id).ok_or_else(|| "Game vanished after adding".to_string())
+    }
+
+    pub fn update(&self, id: &str, patch: GamePatch) -> Result<CatalogGame, String> {
+        {
+            let mut games = self.games.lock().unwrap();
+            let mut updated = games.clone();
+            let game = updated.iter_mut().find(|g| g.id == id).ok_or_else(|| format!("No game with id {}", id))?;
+            if let Some(name) = patch.name {
+                game.name = validate_name(&name)?;
+            }
+            if let Some(exe_path) = patch.exe_path {
+                game.exe_path = validate_target(&exe_path, true)?;
+            }
+            if patch.install_path.is_some() {
+                game.install_path = validate_optional_path("Install path", patch.install_path)?;
+            }
+            if patch.icon_path.is_some() {
+                game.icon_path = validate_optional_path("Icon path", patch.icon_path)?;
+            }
+            if let Some(categories) = patch.categories {
+                game.categories = validate_categories(categories)?;
+            }
+            game.updated_at = Utc::now();
+            self.persist(&updated)?;
+            *games = updated;
+        }
+        if let Some(profile) = patch.launch_profile {
+            set_profile(id, Some(profile))?;
+        }
+        self.get(id).ok_or_else(|| format!("No game with id {}", id))
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        {
+            let mut games = self.games.lock().unwrap();
+            let updated: Vec<CatalogGame> = games.iter().filter(|g| g.id != id).cloned().collect();
+            if updated.len() == games.len() {
+                return Err(format!("No game with id {}", id));
+            }
+            self.persist(&updated)?;
+            *games = updated;
+        }
+        if crate::config::current().launch_profiles.contains_key(id) {
+            set_profile(id, None)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the café entries with the backend's catalog. Local entries
+    /// are kept. Returns the number of café games.
+    pub fn apply_cafe_catalog(&self, doc: &Value) -> Result<usize, String> {
+        let items = doc.as_array()
+            .or_else(|| doc.get("games").and_then(|g| g.as_array()))
+            .ok_or("Unexpected catalog response")?;
+        let now = Utc::now();
+        let mut games = self.games.lock().unwrap();
+        let mut updated: Vec<CatalogGame> = games.iter().filter(|g| g.source == CatalogSource::Local).cloned().collect();
+        let mut count = 0;
+
+        for item in items {
+            let field = |key: &str| item.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+            let id = match item.get("id") {
+                Some(Value::Number(n)) => n.to_string(),
+                Some(Value::String(s)) => s.clone(),
+                _ => continue,
+            };
+            // Café games without a path for this PC are not launchable here
+            let (Some(name), Some(exe_path)) = (field("name"), field("exe_path")) else { continue };
+            let (Ok(name), Ok(exe_path)) = (validate_name(&name), validate_target(&exe_path, false)) else { continue };
+            let Ok(install_path) = validate_optional_path("Install path", field("install_path")) else { continue };
+
+            let id = format!("cafe:{}", id);
+            let created_at = games.iter().find(|g| g.id == id).map(|g| g.created_at).unwrap_or(now);
+            let categories = item.get("categories").or_else(|| item.get("tags"))
+                .and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|c| c.as_str().map(String::from)).collect())
+                .or_else(|| field("genre").map(|g| vec![g]))
+                .unwrap_or_default();
+            updated.push(CatalogGame {
+                id,
+                name,
+                source: CatalogSource::Cafe,
+                exe_path,
+                install_path,
+                icon_path: None,
+                categories: validate_categories(categories).unwrap_or_default(),
+                launch_profile: None,
+                created_at,
+                updated_at: now,
+            });
+            count += 1;
+        }
+
+        self.persist(&updated)?;
+        *games = updated;
+        Ok(count)
+    }
+
+    /// Extract icons for games whose icon isn't cached yet. Slow: hashes
+    /// executables. Returns whether anything was added.
+    pub fn refresh_icons(&self) -> bool {
+        let games = self.games.lock().unwrap().clone();
+        let pending: Vec<CatalogGame> = {
+            let icons = self.icons.lock().unwrap();
+            games
+                .into_iter()
+                .filter(|g| icons.get(&g.id).map(|(source, _)| *source != icon_source(g)).unwrap_or(true))
+                .collect()
+        };
+        for game in &pending {
+            let icon = match &game.icon_path {
+                Some(path) => crate::icons::cache_image(&self.icon_dir, Path::new(path))
+                    .map_err(|e| log_warn!("[Catalog] Icon for {}: {}", game.name, e))
+                    .ok(),
+                None => crate::icons::resolve(&self.icon_dir, &game.exe_path, game.install_path.as_deref().unwrap_or("")),
+            };
+            self.icons.lock().unwrap().insert(game.id.clone(), (icon_source(game), icon));
+        }
+        !pending.is_empty()
+    }
+}
+
+/// Extract missing icons in the background and re-emit the catalog when done
+pub fn spawn_icon_refresh<E: AgentEvents>(store: Arc<CatalogStore>, events: Arc<E>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        if store.refresh_icons() {
+            events.emit(EVENT_CATALOG_CHANGED, json!(store.list()));
+        }
+    });
+}
+
+fn set_profile(id: &str, profile: Option<LaunchProfile>) -> Result<(), String> {
+    crate::config::modify(|cfg| {
+        match profile {
+            Some(profile) => {
+                cfg.launch_profiles.insert(id.to_string(), profile);
+            }
+            None => {
+                cfg.launch_profiles.remove(id);
+            }
+        }
+        Ok(())
+    })
+    .map(|_| ())
+}
+
+/// Pull the café-wide catalog from the backend into the local store
+pub async fn sync_from_backend(store: &CatalogStore, client: &crate::device_client::DeviceClient) -> Result<usize, String> {
+    let doc = client.get("/clientpc/games").await?;
+    let count = store.apply_cafe_catalog(&doc)?;
+    log_info!("[Catalog] Synced {} café games", count);
+    Ok(count)
+}
+
+pub fn emit_changed(app_handle: &tauri::AppHandle, store: &Arc<CatalogStore>) {
+    use tauri::Manager;
+    let _ = app_handle.emit_all(EVENT_CATALOG_CHANGED, store.list());
+    spawn_icon_refresh(store.clone(), Arc::new(app_handle.clone()));
+}
+
+type Store<'a> = tauri::State<'a, Arc<CatalogStore>>;
+
+#[tauri::command]
+pub async fn list_catalog_games(store: Store<'_>) -> Result<Vec<CatalogGame>, String> {
+    Ok(store.list())
+}
+
+#[tauri::command]
+pub async fn add_catalog_game(app_handle: tauri::AppHandle, store: Store<'_>, game: GameInput) -> Result<CatalogGame, String> {
+    let game = store.add(game)?;
+    emit_changed(&app_handle, &store);
+    Ok(game)
+}
+
+#[tauri::command]
+pub async fn update_catalog_game(app_handle: tauri::AppHandle, store: Store<'_>, id: String, patch: GamePatch) -> Result<CatalogGame, String> {
+    let game = store.update(&id, patch)?;
+    emit_changed(&app_handle, &store);
+    Ok(game)
+}
+
+#[tauri::command]
+pub async fn remove_catalog_game(app_handle: tauri::AppHandle, store: Store<'_>, id: String) -> Result<(), String> {
+    store.remove(&id)?;
+    emit_changed(&app_handle, &store);
+    Ok(())
+}
+
+/// One-off import of games kept in webview storage by older clients.
+/// Entries that fail validation or already exist are skipped.
+#[tauri::command]
+pub async fn import_catalog_games(app_handle: tauri::AppHandle, store: Store<'_>, games: Vec<GameInput>) -> Result<usize, String> {
+    let mut imported = 0;
+    for game in games {
+        match store.add(game) {
+            Ok(_) => imported += 1,
+            Err(e) => log_warn!("[Catalog] Skipped import: {}", e),
+        }
+    }
+    if imported > 0 {
+        emit_changed(&app_handle, &store);
+    }
+    Ok(imported)
+}
+
+#[tauri::command]
+pub async fn sync_catalog(app_handle: tauri::AppHandle, store: Store<'_>) -> Result<usize, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+    let client = crate::device_client::DeviceClient::from_config_dir(&config_dir)?;
+    let count = sync_from_backend(&store, &client).await?;
+    emit_changed(&app_handle, &store);
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-catalog-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn store(dir: &Path) -> CatalogStore {
+        CatalogStore::open(dir.to_path_buf(), dir.join("icons"))
+    }
+
+    /// An existing file to use as a game executable
+    fn exe(dir: &Path, name: &str) -> String {
+        let path = dir.join(name);
+        fs::write(&path, b"").unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn input(name: &str, exe_path: &str) -> GameInput {
+        GameInput { name: name.to_string(), exe_path: exe_path.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn added_games_survive_a_reopen() {
+        let dir = temp_dir();
+        let game = store(&dir).add(GameInput {
+            categories: vec!["FPS".to_string(), " fps ".to_string(), String::new()],
+            ..input("  Quake  ", &exe(&dir, "quake.exe"))
+        }).unwrap();
+        assert_eq!(game.name, "Quake");
+        assert_eq!(game.categories, vec!["FPS".to_string()]);
+
+        let reopened = store(&dir).list();
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened[0].id, game.id);
+        assert_eq!(reopened[0].exe_path, game.exe_path);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn add_rejects_invalid_input() {
+        let dir = temp_dir();
+        let store = store(&dir);
+        let quake = exe(&dir, "quake.exe");
+        assert!(store.add(input("", &quake)).is_err());
+        assert!(store.add(input("Quake", "games/quake.exe")).is_err());
+        assert!(store.add(input("Quake", &dir.join("missing.exe").to_string_lossy())).is_err());
+        assert!(store.add(GameInput { install_path: Some("relative".to_string()), ..input("Quake", &quake) }).is_err());
+
+        store.add(input("Quake", &quake)).unwrap();
+        assert!(store.add(input("Quake again", &quake)).is_err());
+        assert_eq!(store.list().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn duplicate_check_follows_platform_case_rules() {
+        let dir = temp_dir();
+        let store = store(&dir);
+        let lower = exe(&dir, "game.exe");
+        store.add(input("Game", &lower)).unwrap();
+        let upper = dir.join("GAME.exe").to_string_lossy().to_string();
+        if cfg!(target_os = "windows") {
+            assert!(store.add(input("Game", &upper)).is_err());
+        } else {
+            fs::write(&upper, b"").unwrap();
+            assert!(store.add(input("Game", &upper)).is_ok());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cafe_catalog_replaces_cafe_entries_and_keeps_local_ones() {
+        let dir = temp_dir();
+        let store = store(&dir);
+        let local = store.add(input("Local game", &exe(&dir, "local.exe"))).unwrap();
+        let install = dir.join("cs2").to_string_lossy().to_string();
+
+        let first = json!([
+            { "id": 1, "name": "CS2", "exe_path": "steam://rungameid/730", "install_path": install, "genre": "FPS" },
+            { "id": 2, "name": "No path here" },
+            { "id": 3, "name": "Bad install", "exe_path": "steam://rungameid/570", "install_path": "relative/dir" },
+            { "id": "4", "name": "Relative exe", "exe_path": "game.exe" },
+        ]);
+        assert_eq!(store.apply_cafe_catalog(&first).unwrap(), 1);
+        let cs2 = store.get("cafe:1").unwrap();
+        assert_eq!(cs2.source, CatalogSource::Cafe);
+        assert_eq!(cs2.install_path.as_deref(), Some(install.as_str()));
+        assert_eq!(cs2.categories, vec!["FPS".to_string()]);
+
+        let second = json!({ "games": [
+            { "id": 1, "name": "Counter-Strike 2", "exe_path": "steam://rungameid/730" },
+            { "id": 5, "name": "Dota 2", "exe_path": "steam://rungameid/570" },
+        ] });
+        assert_eq!(store.apply_cafe_catalog(&second).unwrap(), 2);
+        let renamed = store.get("cafe:1").unwrap();
+        assert_eq!(renamed.name, "Counter-Strike 2");
+        assert_eq!(renamed.created_at, cs2.created_at);
+
+        let mut ids: Vec<String> = CatalogStore::open(dir.clone(), dir.join("icons")).list().into_iter().map(|g| g.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["cafe:1".to_string(), "cafe:5".to_string(), local.id]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,455 @@
+// Persistent client configuration.
+//
+// Stored as JSON in `<config dir>/PrimusClient/config.json`. The file carries a
+// `version`; older files are migrated step by step on load. Every write is
+// validated, and the in-memory copy is swapped atomically so running loops
+// (command agent, heartbeat) pick up changes without a restart. External edits
+// to the file are picked up by a background watcher.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::launch::LaunchProfile;
+use crate::screenshot::ScreenshotSettings;
+
+pub const CONFIG_VERSION: u32 = 1;
+/// Emitted with the new `ClientConfig` whenever it changes
+pub const EVENT_CONFIG_CHANGED: &str = "config-changed";
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_HEARTBEAT_SECS: u64 = 5;
+const MAX_HEARTBEAT_SECS: u64 = 300;
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct KioskPolicy {
+    /// Turn on shortcut blocking as soon as the client starts
+    pub enable_on_startup: bool,
+    pub block_shortcuts: bool,
+    /// Keep the Primus window above everything while no app is running
+    pub always_on_top: bool,
+    /// Shortcuts blocked in each kiosk mode, when `block_shortcuts` is on
+    pub shortcuts: crate::shortcuts::ShortcutPolicy,
+}
+
+impl Default for KioskPolicy {
+    fn default() -> Self {
+        Self {
+            enable_on_startup: false,
+            block_shortcuts: true,
+            always_on_top: true,
+            shortcuts: crate::shortcuts::ShortcutPolicy::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ClientConfig {
+    pub version: u32,
+    pub backend_url: String,
+    pub heartbeat_interval_secs: u64,
+    /// Most verbose console output that gets printed
+    pub log_level: LogLevel,
+    pub kiosk: KioskPolicy,
+    /// Extra Steam library / Epic manifest folders scanned for installed games
+    pub launcher_paths: Vec<String>,
+    pub cafe_name: String,
+    pub pc_name: String,
+    /// Defaults for admin-requested screenshots
+    pub screenshot: ScreenshotSettings,
+    /// Per-game launch settings, keyed by game id or executable path
+    pub launch_profiles: BTreeMap<String, LaunchProfile>,
+    /// Pull the café-wide game catalog from the backend on agent start
+    pub catalog_sync: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            backend_url: std::env::var("PRIMUS_BACKEND_URL")
+                .unwrap_or_else(|_| String::from("https://api.primustech.in")),
+            heartbeat_interval_secs: 15,
+            log_level: LogLevel::Info,
+            kiosk: KioskPolicy::default(),
+            launcher_paths: Vec::new(),
+            cafe_name: String::new(),
+            pc_name: String::new(),
+            screenshot: ScreenshotSettings::default(),
+            launch_profiles: BTreeMap::new(),
+            catalog_sync: false,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Check and normalize a config before it is stored
+    pub fn validate(mut self) -> Result<Self, String> {
+        let url = self.backend_url.trim().trim_end_matches('/').to_string();
+        let parsed = reqwest::Url::parse(&url).map_err(|e| format!("Invalid backend URL '{}': {}", url, e))?;
+        if parsed.scheme() != "http" && parsed.scheme() != "https" {
+            return Err(format!("Backend URL must use http or https, got '{}'", parsed.scheme()));
+        }
+        if parsed.host_str().map(|h| h.is_empty()).unwrap_or(true) {
+            return Err("Backend URL has no host".to_string());
+        }
+        if parsed.query().is_some() || parsed.fragment().is_some() {
+            return Err("Backend URL must not contain a query or fragment".to_string());
+        }
+        self.backend_url = url;
+
+        if !(MIN_HEARTBEAT_SECS..=MAX_HEARTBEAT_SECS).contains(&self.heartbeat_interval_secs) {
+            return Err(format!(
+                "Heartbeat interval must be between {} and {} seconds",
+                MIN_HEARTBEAT_SECS, MAX_HEARTBEAT_SECS
+            ));
+        }
+
+        for path in &self.launcher_paths {
+            if !Path::new(path).is_absolute() {
+                return Err(format!("Launcher path must be absolute: {}", path));
+            }
+        }
+
+        for (field, value) in [("cafe_name", &mut self.cafe_name), ("pc_name", &mut self.pc_name)] {
+            *value = value.trim().to_string();
+            if value.chars().count() > MAX_NAME_LEN {
+                return Err(format!("{} must be at most {} characters", field, MAX_NAME_LEN));
+            }
+            if value.chars().any(|c| c.is_control()) {
+                return Err(format!("{} must not contain control characters", field));
+            }
+        }
+
+        self.kiosk.shortcuts.validate()?;
+        self.screenshot.validate()?;
+        for (key, profile) in &self.launch_profiles {
+            profile.validate().map_err(|e| format!("Launch profile '{}': {}", key, e))?;
+        }
+
+        self.version = CONFIG_VERSION;
+        Ok(self)
+    }
+
+    pub fn heartbeat_interval(&self) -> Duration {
+        Duration::from_secs(self.heartbeat_interval_secs)
+    }
+}
+
+/// Bring a raw config document up to `CONFIG_VERSION`
+fn migrate(mut doc: Value) -> Value {
+    let mut version = doc.get("version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    while version < CONFIG_VERSION {
+        doc = match version {
+            // v0: bare `{ "backend_url": "..." }`, possibly empty
+            0 => {
+                if doc.get("backend_url").and_then(|v| v.as_str()).map(|s| s.is_empty()).unwrap_or(false) {
+                    if let Some(obj) = doc.as_object_mut() {
+                        obj.remove("backend_url");
+                    }
+                }
+                doc
+            }
+            _ => doc,
+        };
+        version += 1;
+        if let Some(obj) = doc.as_object_mut() {
+            obj.insert("version".to_string(), Value::from(version));
+        }
+    }
+    doc
+}
+
+lazy_static! {
+    static ref CONFIG: RwLock<ClientConfig> = RwLock::new(load_from_disk().unwrap_or_else(|e| {
+        log_error!("[Config] Falling back to defaults: {}", e);
+        ClientConfig::default()
+    }));
+    static ref LAST_MTIME: RwLock<Option<SystemTime>> = RwLock::new(None);
+    static ref LISTENERS: RwLock<Vec<Listener>> = RwLock::new(Vec::new());
+    /// Serializes read-modify-write cycles so concurrent writers can't drop
+    /// each other's changes
+    static ref WRITE_LOCK: Mutex<()> = Mutex::new(());
+}
+
+type Listener = Arc<dyn Fn(&ClientConfig) + Send + Sync>;
+
+fn config_path() -> PathBuf {
+    // Use %APPDATA%/PrimusClient/config.json on Windows
+    let mut dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    dir.push("PrimusClient");
+    fs::create_dir_all(&dir).ok();
+    dir.push("config.json");
+    dir
+}
+
+fn file_mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn load_from_disk() -> Result<ClientConfig, String> {
+    load_from(&config_path())
+}
+
+fn write_to_disk(cfg: &ClientConfig) -> Result<(), String> {
+    write_to(&config_path(), cfg)
+}
+
+/// Read, migrate and validate the config at `path`, rewriting it if migrated
+fn load_from(path: &Path) -> Result<ClientConfig, String> {
+    if !path.exists() {
+        return Ok(ClientConfig::default());
+    }
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let doc: Value = serde_json::from_str(&data).map_err(|e| format!("Invalid config.json: {}", e))?;
+    let from_version = doc.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+    let cfg: ClientConfig = serde_json::from_value(migrate(doc)).map_err(|e| format!("Invalid config.json: {}", e))?;
+    let cfg = cfg.validate()?;
+    if from_version < CONFIG_VERSION as u64 {
+        log_info!("[Config] Migrated config.json from v{} to v{}", from_version, CONFIG_VERSION);
+        write_to(path, &cfg)?;
+    }
+    *LAST_MTIME.write().unwrap() = file_mtime(path);
+    Ok(cfg)
+}
+
+fn write_to(path: &Path, cfg: &ClientConfig) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(cfg).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, data).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())?;
+    *LAST_MTIME.write().unwrap() = file_mtime(path);
+    Ok(())
+}
+
+/// Snapshot of the current config
+pub fn current() -> ClientConfig {
+    CONFIG.read().unwrap().clone()
+}
+
+/// Run `listener` after every config change, whatever its source.
+/// Listeners run while the write lock is held and must not call `modify`.
+pub fn on_change(listener: impl Fn(&ClientConfig) + Send + Sync + 'static) {
+    LISTENERS.write().unwrap().push(Arc::new(listener));
+}
+
+fn publish(cfg: &ClientConfig) {
+    *CONFIG.write().unwrap() = cfg.clone();
+    crate::logging::set_level(cfg.log_level);
+    // Call listeners on a copy so one can register another without deadlocking
+    let listeners: Vec<Listener> = LISTENERS.read().unwrap().clone();
+    for listener in listeners {
+        listener(cfg);
+    }
+}
+
+/// Read, change, validate, persist and publish the config as one step.
+/// Returns the stored value; nothing is written if `f` or validation fails.
+pub fn modify(f: impl FnOnce(&mut ClientConfig) -> Result<(), String>) -> Result<ClientConfig, String> {
+    let _guard = WRITE_LOCK.lock().unwrap();
+    let mut cfg = current();
+    f(&mut cfg)?;
+    let cfg = cfg.validate()?;
+    write_to_disk(&cfg)?;
+    publish(&cfg);
+    Ok(cfg)
+}
+
+/// Apply a partial JSON update (e.g. `{ "backend_url": "..." }`)
+pub fn apply_patch(patch: Value) -> Result<ClientConfig, String> {
+    modify(move |cfg| {
+        let mut doc = serde_json::to_value(&*cfg).map_err(|e| e.to_string())?;
+        merge(&mut doc, patch);
+        *cfg = serde_json::from_value(doc).map_err(|e| format!("Invalid config: {}", e))?;
+        Ok(())
+    })
+}
+
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+pub fn emit_changed(app_handle: &tauri::AppHandle, cfg: &ClientConfig) {
+    use tauri::Manager;
+    let _ = app_handle.emit_all(EVENT_CONFIG_CHANGED, cfg.clone());
+}
+
+/// Poll config.json for external edits (provisioning scripts, manual fixes)
+pub fn spawn_watcher(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WATCH_INTERVAL);
+        let mtime = file_mtime(&config_path());
+        if mtime.is_none() || mtime == *LAST_MTIME.read().unwrap() {
+            continue;
+        }
+        let _guard = WRITE_LOCK.lock().unwrap();
+        match load_from_disk() {
+            Ok(cfg) => {
+                if cfg != current() {
+                    log_info!("[Config] Reloaded config.json after external change");
+                    publish(&cfg);
+                    emit_changed(&app_handle, &cfg);
+                }
+            }
+            Err(e) => {
+                // Keep running on the last good config
+                log_warn!("[Config] Ignoring invalid config.json: {}", e);
+                *LAST_MTIME.write().unwrap() = mtime;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn get_config() -> Result<ClientConfig, String> {
+    Ok(current())
+}
+
+/// Merge `patch` into the current config, validate and apply it live
+#[tauri::command]
+pub async fn update_config(app_handle: tauri::AppHandle, patch: Value) -> Result<ClientConfig, String> {
+    let cfg = apply_patch(patch)?;
+    log_info!("[Config] Updated (backend: {})", cfg.backend_url);
+    emit_changed(&app_handle, &cfg);
+    Ok(cfg)
+}
+
+#[tauri::command]
+pub async fn reload_config(app_handle: tauri::AppHandle) -> Result<ClientConfig, String> {
+    let cfg = {
+        let _guard = WRITE_LOCK.lock().unwrap();
+        let cfg = load_from_disk()?;
+        publish(&cfg);
+        cfg
+    };
+    emit_changed(&app_handle, &cfg);
+    Ok(cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn temp_config_path() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-config-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("config.json")
+    }
+
+    fn valid() -> ClientConfig {
+        ClientConfig { backend_url: "https://api.example.com".to_string(), ..ClientConfig::default() }
+    }
+
+    #[test]
+    fn validate_normalizes_url_and_names() {
+        let cfg = ClientConfig {
+            backend_url: " https://api.example.com/ ".to_string(),
+            pc_name: "  PC-01 ".to_string(),
+            version: 0,
+            ..valid()
+        };
+        let cfg = cfg.validate().unwrap();
+        assert_eq!(cfg.backend_url, "https://api.example.com");
+        assert_eq!(cfg.pc_name, "PC-01");
+        assert_eq!(cfg.version, CONFIG_VERSION);
+    }
+
+    #[test]
+    fn validate_rejects_bad_values() {
+        let cases = [
+            ClientConfig { backend_url: "ftp://api.example.com".to_string(), ..valid() },
+            ClientConfig { backend_url: "not a url".to_string(), ..valid() },
+            ClientConfig { backend_url: "https://api.example.com/?x=1".to_string(), ..valid() },
+            ClientConfig { backend_url: "https://api.example.com/#top".to_string(), ..valid() },
+            ClientConfig { heartbeat_interval_secs: MIN_HEARTBEAT_SECS - 1, ..valid() },
+            ClientConfig { heartbeat_interval_secs: MAX_HEARTBEAT_SECS + 1, ..valid() },
+            ClientConfig { launcher_paths: vec!["relative/steamapps".to_string()], ..valid() },
+            ClientConfig { cafe_name: "x".repeat(MAX_NAME_LEN + 1), ..valid() },
+            ClientConfig { pc_name: "PC\n01".to_string(), ..valid() },
+        ];
+        for cfg in cases {
+            let debug = format!("{:?}", cfg);
+            assert!(cfg.validate().is_err(), "accepted {}", debug);
+        }
+    }
+
+    #[test]
+    fn migrate_v0_drops_empty_backend_url() {
+        let doc = migrate(json!({ "backend_url": "" }));
+        assert_eq!(doc, json!({ "version": CONFIG_VERSION }));
+
+        let doc = migrate(json!({ "backend_url": "https://api.example.com" }));
+        assert_eq!(doc["backend_url"], "https://api.example.com");
+        assert_eq!(doc["version"], CONFIG_VERSION);
+    }
+
+    #[test]
+    fn migrate_leaves_current_version_alone() {
+        let doc = json!({ "version": CONFIG_VERSION, "backend_url": "" });
+        assert_eq!(migrate(doc.clone()), doc);
+    }
+
+    #[test]
+    fn merge_recurses_into_objects_and_replaces_scalars() {
+        let mut doc = json!({ "kiosk": { "block_shortcuts": true, "always_on_top": true }, "launcher_paths": ["/a"] });
+        merge(&mut doc, json!({ "kiosk": { "always_on_top": false }, "launcher_paths": [], "cafe_name": "Arena" }));
+        assert_eq!(
+            doc,
+            json!({ "kiosk": { "block_shortcuts": true, "always_on_top": false }, "launcher_paths": [], "cafe_name": "Arena" })
+        );
+    }
+
+    #[test]
+    fn load_migrates_and_rewrites_old_file() {
+        let path = temp_config_path();
+        fs::write(&path, r#"{ "backend_url": "https://api.example.com/", "log_level": "debug" }"#).unwrap();
+
+        let cfg = load_from(&path).unwrap();
+        assert_eq!(cfg.backend_url, "https://api.example.com");
+        assert_eq!(cfg.log_level, LogLevel::Debug);
+
+        let stored: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(stored["version"], CONFIG_VERSION);
+        assert_eq!(load_from(&path).unwrap(), cfg);
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn load_rejects_invalid_file_and_defaults_missing_one() {
+        let path = temp_config_path();
+        assert_eq!(load_from(&path).unwrap(), ClientConfig::default());
+
+        fs::write(&path, r#"{ "version": 1, "heartbeat_interval_secs": 1 }"#).unwrap();
+        assert!(load_from(&path).is_err());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,343 @@
+// Encrypted-at-rest store for the device credentials (`device.json`).
+//
+// The file holds an envelope instead of the plaintext credentials:
+//
+//     { "version": 1, "scheme": "...", "nonce": "<hex>", "ciphertext": "<hex>" }
+//
+// Schemes:
+// - `dpapi` (Windows): sealed with CryptProtectData for the kiosk user
+// - `aes-256-gcm/machine-v2`: AES-256-GCM with a key derived from the most
+//   stable machine identifier, used wherever no OS keyring is available
+//
+// Writes go to a temp file that is permission-hardened, fsynced and then
+// renamed over the old file. Plaintext files from older clients are migrated
+// transparently on first read.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+const CREDENTIALS_FILE: &str = "device.json";
+const ENVELOPE_VERSION: u32 = 1;
+const SCHEME_DPAPI: &str = "dpapi";
+const SCHEME_AES_MACHINE_V2: &str = "aes-256-gcm/machine-v2";
+const KDF_CONTEXT: &[u8] = b"primus-device-credentials-v1";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeviceCredentials {
+    pub pc_id: i64,
+    pub license_key: String,
+    pub device_secret: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    scheme: String,
+    #[serde(default)]
+    nonce: String,
+    ciphertext: String,
+}
+
+pub struct CredentialStore {
+    dir: PathBuf,
+}
+
+impl CredentialStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(CREDENTIALS_FILE)
+    }
+
+    /// `Ok(None)` means the device has not been registered yet.
+    pub fn load(&self) -> Result<Option<DeviceCredentials>, String> {
+        let path = self.path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+        if let Ok(envelope) = serde_json::from_str::<Envelope>(&data) {
+            let plaintext = open(&envelope)?;
+            let creds = serde_json::from_slice(&plaintext)
+                .map_err(|e| format!("Invalid credentials: {}", e))?;
+            return Ok(Some(creds));
+        }
+
+        // Plaintext file written by an older client: re-save it encrypted
+        let creds: DeviceCredentials = serde_json::from_str(&data)
+            .map_err(|e| format!("Invalid credentials: {}", e))?;
+        match self.save(&creds) {
+            Ok(()) => log_info!("[Credentials] Migrated plaintext device.json to encrypted store"),
+            Err(e) => log_error!("[Credentials] Failed to migrate plaintext device.json: {}", e),
+        }
+        Ok(Some(creds))
+    }
+
+    pub fn save(&self, creds: &DeviceCredentials) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let plaintext = serde_json::to_vec(creds).map_err(|e| e.to_string())?;
+        let envelope = seal(&plaintext)?;
+        let data = serde_json::to_string(&envelope).map_err(|e| e.to_string())?;
+        write_atomic(&self.path(), data.as_bytes())
+    }
+
+    pub fn reset(&self) -> Result<(), String> {
+        let path = self.path();
+        if path.exists() {
+            fs::remove_file(path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+fn seal(plaintext: &[u8]) -> Result<Envelope, String> {
+    #[cfg(target_os = "windows")]
+    {
+        match dpapi::protect(plaintext) {
+            Ok(ciphertext) => {
+                return Ok(Envelope {
+                    version: ENVELOPE_VERSION,
+                    scheme: SCHEME_DPAPI.to_string(),
+                    nonce: String::new(),
+                    ciphertext: to_hex(&ciphertext),
+                })
+            }
+            Err(e) => log_warn!("[Credentials] DPAPI unavailable, falling back to machine key: {}", e),
+        }
+    }
+
+    let cipher = Aes256Gcm::new(&derive_key(&crate::fingerprint::machine_key_material()));
+    let nonce_bytes: [u8; 12] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|_| "Failed to encrypt credentials".to_string())?;
+    Ok(Envelope {
+        version: ENVELOPE_VERSION,
+        scheme: SCHEME_AES_MACHINE_V2.to_string(),
+        nonce: to_hex(&nonce_bytes),
+        ciphertext: to_hex(&ciphertext),
+    })
+}
+
+fn open(envelope: &Envelope) -> Result<Vec<u8>, String> {
+    if envelope.version != ENVELOPE_VERSION {
+        return Err(format!("Unsupported credentials version: {}", envelope.version));
+    }
+    let ciphertext = from_hex(&envelope.ciphertext)?;
+    match envelope.scheme.as_str() {
+        #[cfg(target_os = "windows")]
+        SCHEME_DPAPI => dpapi::unprotect(&ciphertext),
+        SCHEME_AES_MACHINE_V2 => {
+            let nonce = from_hex(&envelope.nonce)?;
+            if nonce.len() != 12 {
+                return Err("Invalid credentials nonce".to_string());
+            }
+            Aes256Gcm::new(&derive_key(&crate::fingerprint::machine_key_material()))
+                .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+                // Wrong key means the file was copied from another machine
+                .map_err(|_| "Failed to decrypt credentials (hardware changed or file copied from another PC)".to_string())
+        }
+        other => Err(format!("Unsupported credentials scheme: {}", other)),
+    }
+}
+
+/// AES key bound to this machine
+fn derive_key(key_material: &str) -> Key<Aes256Gcm> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key_material.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(KDF_CONTEXT);
+    let bytes = mac.finalize().into_bytes();
+    *Key::<Aes256Gcm>::from_slice(&bytes)
+}
+
+/// Write via temp file + rename so a crash never leaves a half-written file
+fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("json.tmp");
+    // A leftover from an interrupted write would make create_new fail
+    let _ = fs::remove_file(&tmp);
+    {
+        let mut file = create_private(&tmp)?;
+        file.write_all(data).map_err(|e| format!("Failed to write credentials: {}", e))?;
+        file.sync_all().map_err(|e| format!("Failed to flush credentials: {}", e))?;
+    }
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("Failed to replace credentials file: {}", e)
+    })
+}
+
+/// Create the temp file owner-only from the start, leaving no window where
+/// another user could open it before the permissions are tightened
+#[cfg(unix)]
+fn create_private(path: &Path) -> Result<fs::File, String> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .map_err(|e| format!("Failed to create temp file: {}", e))
+}
+
+#[cfg(target_os = "windows")]
+fn create_private(path: &Path) -> Result<fs::File, String> {
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| format!("Failed to create temp file: {}", e))?;
+    harden_permissions(path)?;
+    Ok(file)
+}
+
+#[cfg(target_os = "windows")]
+fn harden_permissions(path: &Path) -> Result<(), String> {
+    use std::process::Command;
+    // Drop inherited ACEs and only allow the current user, SYSTEM and Administrators
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    let mut args = vec![
+        path.to_string_lossy().to_string(),
+        "/inheritance:r".to_string(),
+        "/grant:r".to_string(), "*S-1-5-18:F".to_string(),
+        "/grant:r".to_string(), "*S-1-5-32-544:F".to_string(),
+    ];
+    if !user.is_empty() {
+        args.push("/grant:r".to_string());
+        args.push(format!("{}:F", user));
+    }
+    let output = Command::new("icacls")
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to run icacls: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to set credentials ACL: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    let digit = |b: u8| (b as char).to_digit(16).ok_or_else(|| "Invalid hex data".to_string());
+    let bytes = s.as_bytes();
+    if bytes.len() % 2 != 0 {
+        return Err("Invalid hex data".to_string());
+    }
+    // Work on bytes: a tampered file may hold non-ASCII text
+    bytes.chunks(2).map(|pair| Ok((digit(pair[0])? << 4 | digit(pair[1])?) as u8)).collect()
+}
+
+#[cfg(target_os = "windows")]
+mod dpapi {
+    use winapi::um::dpapi::{CryptProtectData, CryptUnprotectData, CRYPTPROTECT_UI_FORBIDDEN};
+    use winapi::um::wincrypt::DATA_BLOB;
+    use winapi::um::winbase::LocalFree;
+
+    fn run(input: &[u8], protect: bool) -> Result<Vec<u8>, String> {
+        let mut in_blob = DATA_BLOB {
+            cbData: input.len() as u32,
+            pbData: input.as_ptr() as *mut u8,
+        };
+        let mut out_blob = DATA_BLOB { cbData: 0, pbData: std::ptr::null_mut() };
+        let ok = unsafe {
+            if protect {
+                CryptProtectData(&mut in_blob, std::ptr::null(), std::ptr::null_mut(), std::ptr::null_mut(),
+                    std::ptr::null_mut(), CRYPTPROTECT_UI_FORBIDDEN, &mut out_blob)
+            } else {
+                CryptUnprotectData(&mut in_blob, std::ptr::null_mut(), std::ptr::null_mut(), std::ptr::null_mut(),
+                    std::ptr::null_mut(), CRYPTPROTECT_UI_FORBIDDEN, &mut out_blob)
+            }
+        };
+        if ok == 0 {
+            return Err(format!("DPAPI call failed: {}", std::io::Error::last_os_error()));
+        }
+        let output = unsafe {
+            let data = std::slice::from_raw_parts(out_blob.pbData, out_blob.cbData as usize).to_vec();
+            LocalFree(out_blob.pbData as *mut _);
+            data
+        };
+        Ok(output)
+    }
+
+    pub fn protect(plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        run(plaintext, true)
+    }
+
+    pub fn unprotect(ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        run(ciphertext, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> CredentialStore {
+        let dir = std::env::temp_dir().join(format!("primus-credentials-test-{}", uuid::Uuid::new_v4().simple()));
+        CredentialStore::new(dir)
+    }
+
+    fn creds() -> DeviceCredentials {
+        DeviceCredentials { pc_id: 42, license_key: "LIC-1234".to_string(), device_secret: "s3cret".to_string() }
+    }
+
+    #[test]
+    fn save_and_load_roundtrip_without_plaintext_on_disk() {
+        let store = temp_store();
+        assert!(store.load().unwrap().is_none());
+        store.save(&creds()).unwrap();
+
+        let data = fs::read_to_string(store.path()).unwrap();
+        assert!(!data.contains("s3cret"));
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!((loaded.pc_id, loaded.license_key, loaded.device_secret), (42, "LIC-1234".to_string(), "s3cret".to_string()));
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn plaintext_file_is_migrated_to_an_envelope() {
+        let store = temp_store();
+        fs::create_dir_all(&store.dir).unwrap();
+        fs::write(store.path(), serde_json::to_string(&creds()).unwrap()).unwrap();
+
+        assert_eq!(store.load().unwrap().unwrap().device_secret, "s3cret");
+        let envelope: Envelope = serde_json::from_str(&fs::read_to_string(store.path()).unwrap()).unwrap();
+        assert_eq!(envelope.version, ENVELOPE_VERSION);
+        assert_eq!(store.load().unwrap().unwrap().pc_id, 42);
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let store = temp_store();
+        store.save(&creds()).unwrap();
+        let mut envelope: Envelope = serde_json::from_str(&fs::read_to_string(store.path()).unwrap()).unwrap();
+        let last = envelope.ciphertext.pop().unwrap();
+        envelope.ciphertext.push(if last == '0' { '1' } else { '0' });
+        fs::write(store.path(), serde_json::to_string(&envelope).unwrap()).unwrap();
+
+        assert!(store.load().is_err());
+        let _ = fs::remove_dir_all(&store.dir);
+    }
+
+    #[test]
+    fn from_hex_rejects_bad_input_without_panicking() {
+        assert_eq!(from_hex("00ff7a").unwrap(), vec![0x00, 0xff, 0x7a]);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+        assert!(from_hex("é0").is_err());
+        assert!(from_hex("éé").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// Signed HTTP client used for every device-authenticated backend call.
+//
+// Canonical string (must match backend verify_device_signature):
+//
+//     METHOD + PATH + TIMESTAMP + NONCE + BODY
+//
+// METHOD is upper-case, PATH is the full request path including the `/api`
+// prefix and without query string (a query in the path is dropped before
+// signing, but still sent), TIMESTAMP is unix seconds, BODY is the exact
+// bytes sent (empty string when there is no body). The HMAC-SHA256 of that
+// string keyed with the device secret is sent hex-encoded in
+// `X-Device-Signature`, alongside `X-PC-ID`, `X-Device-Timestamp` and
+// `X-Device-Nonce`.
+
+use std::path::Path;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::Method;
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::credentials::DeviceCredentials;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Build the canonical string that gets signed.
+pub fn canonical_string(method: &str, path: &str, timestamp: &str, nonce: &str, body: &str) -> String {
+    format!("{}{}{}{}{}", method.to_uppercase(), path, timestamp, nonce, body)
+}
+
+/// Hex-encoded HMAC-SHA256 of `message` keyed with `secret`.
+pub fn sign(secret: &str, message: &str) -> Result<String, String> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| e.to_string())?;
+    mac.update(message.as_bytes());
+    Ok(format!("{:x}", mac.finalize().into_bytes()))
+}
+
+/// Headers for one signed request
+#[derive(Debug, Clone)]
+pub struct SignedHeaders {
+    pub pc_id: String,
+    pub signature: String,
+    pub timestamp: String,
+    pub nonce: String,
+}
+
+pub struct DeviceClient {
+    http: reqwest::Client,
+    creds: DeviceCredentials,
+    /// Overrides the configured backend URL
+    backend: Option<String>,
+}
+
+impl DeviceClient {
+    pub fn new(creds: DeviceCredentials) -> Result<Self, String> {
+        Self::with_timeout(creds, DEFAULT_TIMEOUT)
+    }
+
+    pub fn with_timeout(creds: DeviceCredentials, timeout: Duration) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+        Ok(Self { http, creds, backend: None })
+    }
+
+    /// Client for the credentials stored in `config_dir`.
+    pub fn from_config_dir(config_dir: &Path) -> Result<Self, String> {
+        let creds = crate::load_device_credentials(config_dir)?
+            .ok_or("Device not registered. Please register first.")?;
+        Self::new(creds)
+    }
+
+    /// Talk to `url` instead of the configured backend (a local stub in tests)
+    #[cfg(test)]
+    pub fn with_backend(mut self, url: &str) -> Self {
+        self.backend = Some(url.to_string());
+        self
+    }
+
+    pub fn pc_id(&self) -> i64 {
+        self.creds.pc_id
+    }
+
+    /// Sign a request with an explicit timestamp and nonce. Any query string
+    /// in `path` is not part of the signature.
+    pub fn sign_with(&self, method: &str, path: &str, body: &str, timestamp: String, nonce: String) -> Result<SignedHeaders, String> {
+        let path = path.split_once('?').map(|(path, _)| path).unwrap_or(path);
+        let canonical = canonical_string(method, path, &timestamp, &nonce, body);
+        Ok(SignedHeaders {
+            pc_id: self.creds.pc_id.to_string(),
+            signature: sign(&self.creds.device_secret, &canonical)?,
+            timestamp,
+            nonce,
+        })
+    }
+
+    /// Sign a request with the current time and a fresh nonce.
+    pub fn sign_request(&self, method: &str, path: &str, body: &str) -> Result<SignedHeaders, String> {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        self.sign_with(method, path, body, timestamp, nonce)
+    }
+
+    /// Send a signed request to `{backend}/api{path}` and return the JSON
+    /// response (`Null` for empty bodies).
+    pub async fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+        let method_upper = method.to_uppercase();
+        let http_method = Method::from_bytes(method_upper.as_bytes())
+            .map_err(|_| format!("Invalid HTTP method: {}", method))?;
+        let api_path = format!("/api{}", path);
+        let body_str = body.map(|b| b.to_string()).unwrap_or_default();
+        let headers = self.sign_request(&method_upper, &api_path, &body_str)?;
+
+        let backend = self.backend.clone().unwrap_or_else(crate::backend_url);
+        let url = format!("{}{}", backend, api_path);
+        let mut req = self.http
+            .request(http_method, &url)
+            .header("X-PC-ID", headers.pc_id)
+            .header("X-Device-Signature", headers.signature)
+            .header("X-Device-Timestamp", headers.timestamp)
+            .header("X-Device-Nonce", headers.nonce);
+        if body.is_some() {
+            req = req.header("Content-Type", "application/json").body(body_str);
+        }
+
+        let response = req.send().await.map_err(|e| format!("Network error: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let err_text = response.text().await.unwrap_or_default();
+            return Err(format!("HTTP {} - {}", status, err_text));
+        }
+        Ok(response.json().await.unwrap_or(Value::Null))
+    }
+
+    pub async fn post(&self, path: &str, body: &Value) -> Result<Value, String> {
+        self.request("POST", path, Some(body)).await
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Value, String> {
+        self.request("GET", path, None).await
+    }
+}
+
+/// Signed backend request on behalf of the UI. `path` is relative to `/api`.
+#[tauri::command]
+pub async fn signed_request(app_handle: tauri::AppHandle, method: String, path: String, body: Option<Value>) -> Result<Value, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+    let client = DeviceClient::from_config_dir(&config_dir)?;
+    client.request(&method, &path, body.as_ref()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "device-secret";
+    const TIMESTAMP: &str = "1700000000";
+    const NONCE: &str = "0123456789abcdef0123456789abcdef";
+    const BODY: &str = r#"{"status":"ok"}"#;
+
+    fn client() -> DeviceClient {
+        DeviceClient::new(DeviceCredentials { pc_id: 7, license_key: "license".to_string(), device_secret: SECRET.to_string() }).unwrap()
+    }
+
+    #[test]
+    fn canonical_string_concatenates_fields_in_order() {
+        assert_eq!(
+            canonical_string("post", "/api/clientpc/heartbeat", TIMESTAMP, NONCE, BODY),
+            r#"POST/api/clientpc/heartbeat17000000000123456789abcdef0123456789abcdef{"status":"ok"}"#
+        );
+    }
+
+    #[test]
+    fn signature_is_hex_hmac_sha256_of_canonical_string() {
+        let headers = client().sign_with("POST", "/api/clientpc/heartbeat", BODY, TIMESTAMP.to_string(), NONCE.to_string()).unwrap();
+        assert_eq!(headers.signature, "a0f2ad5186424765bc6965469ecb20ea488b0646ef42c3ff3eade71ca28e1c6c");
+        assert_eq!(headers.pc_id, "7");
+        assert_eq!(headers.timestamp, TIMESTAMP);
+        assert_eq!(headers.nonce, NONCE);
+    }
+
+    #[test]
+    fn query_string_is_not_signed() {
+        let client = client();
+        let plain = client.sign_with("GET", "/api/games", "", TIMESTAMP.to_string(), "n1".to_string()).unwrap();
+        let query = client.sign_with("GET", "/api/games?page=2", "", TIMESTAMP.to_string(), "n1".to_string()).unwrap();
+        assert_eq!(plain.signature, "eeb1b81d31b638bb4afc555ad2364333815494d4d9e8d5db692abe2f5f5166f1");
+        assert_eq!(query.signature, plain.signature);
+    }
+}
@@ -0,0 +1,352 @@
+// Device enrollment (the Rust port of the old `handshake.ts` flow).
+//
+// 1. Authenticate an admin (one-time, token is never stored)
+// 2. Generate the hardware fingerprint
+// 3. Fetch cafe info (optional, for logging)
+// 4. Look up a license key
+// 5. Register the device via /clientpc/register (idempotent on the backend)
+// 6. Persist the device credentials
+//
+// Every step reports its own error variant so the UI and provisioning
+// scripts can tell exactly where enrollment stopped.
+
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::credentials::DeviceCredentials;
+
+const CLIENT_FEATURES: &[&str] = &["lock", "unlock", "message", "screenshot", "shutdown", "reboot", "restart", "login", "logout", "logoff"];
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "step", content = "message", rename_all = "snake_case")]
+pub enum EnrollmentError {
+    Login(String),
+    Fingerprint(String),
+    License(String),
+    Register(String),
+    Persist(String),
+    Config(String),
+    Client(String),
+}
+
+impl EnrollmentError {
+    /// Process exit code used by `--enroll`
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            EnrollmentError::Login(_) => 2,
+            EnrollmentError::Fingerprint(_) => 3,
+            EnrollmentError::License(_) => 4,
+            EnrollmentError::Register(_) => 5,
+            EnrollmentError::Persist(_) => 6,
+            EnrollmentError::Config(_) => 7,
+            EnrollmentError::Client(_) => 8,
+        }
+    }
+}
+
+impl fmt::Display for EnrollmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnrollmentError::Login(m) => write!(f, "Login failed: {}", m),
+            EnrollmentError::Fingerprint(m) => write!(f, "Failed to generate hardware fingerprint: {}", m),
+            EnrollmentError::License(m) => write!(f, "License lookup failed: {}", m),
+            EnrollmentError::Register(m) => write!(f, "Device registration failed: {}", m),
+            EnrollmentError::Persist(m) => write!(f, "Failed to save device credentials: {}", m),
+            EnrollmentError::Config(m) => write!(f, "Invalid backend configuration: {}", m),
+            EnrollmentError::Client(m) => write!(f, "Failed to set up the HTTP client: {}", m),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EnrollmentRequest {
+    pub admin_email: String,
+    pub admin_password: String,
+    pub pc_name: String,
+    /// Skip the license lookup and use this key
+    #[serde(default)]
+    pub license_key: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnrollmentResult {
+    pub pc_id: i64,
+    pub license_key: String,
+    pub cafe_id: Option<i64>,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct RegisterResponse {
+    id: i64,
+    #[serde(default)]
+    device_secret: Option<String>,
+    #[serde(default)]
+    cafe_id: Option<i64>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Pull the backend's `detail` message out of an error response
+async fn error_detail(response: reqwest::Response) -> String {
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    let detail = serde_json::from_str::<Value>(&text).ok()
+        .and_then(|v| v.get("detail").map(|d| d.as_str().map(String::from).unwrap_or_else(|| d.to_string())));
+    format!("HTTP {} - {}", status, detail.unwrap_or(text))
+}
+
+async fn fetch_licenses(http: &reqwest::Client, url: &str, token: &str) -> Result<Vec<Value>, String> {
+    let response = http.get(url).bearer_auth(token).send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(error_detail(response).await);
+    }
+    let body: Value = response.json().await.map_err(|e| e.to_string())?;
+    Ok(body.as_array().cloned().unwrap_or_default())
+}
+
+/// First active license (or the first one if none is flagged active)
+fn pick_license(licenses: &[Value]) -> Option<String> {
+    licenses.iter()
+        .find(|l| l.get("is_active").and_then(|a| a.as_bool()) != Some(false))
+        .or_else(|| licenses.first())
+        .and_then(|l| l.get("key"))
+        .and_then(|k| k.as_str())
+        .map(String::from)
+}
+
+pub async fn enroll(config_dir: &Path, req: &EnrollmentRequest) -> Result<EnrollmentResult, EnrollmentError> {
+    let base = crate::backend_url();
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| EnrollmentError::Client(e.to_string()))?;
+    log_info!("[Enroll] Enrolling '{}' against {}", req.pc_name, base);
+
+    // 1. Authenticate admin
+    let response = http
+        .post(format!("{}/api/auth/login", base))
+        .form(&[("username", req.admin_email.as_str()), ("password", req.admin_password.as_str())])
+        .send()
+        .await
+        .map_err(|e| EnrollmentError::Login(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(EnrollmentError::Login(error_detail(response).await));
+    }
+    let token = response.json::<LoginResponse>().await
+        .map_err(|e| EnrollmentError::Login(format!("Unexpected login response: {}", e)))?
+        .access_token;
+    log_info!("[Enroll] Step 1: admin authenticated");
+
+    // 2. Hardware fingerprint (becomes the baseline once registration succeeds)
+    let dir = config_dir.to_path_buf();
+    let fingerprint = tauri::async_runtime::spawn_blocking(move || crate::fingerprint::compare(&dir))
+        .await
+        .map_err(|e| EnrollmentError::Fingerprint(e.to_string()))?
+        .map_err(EnrollmentError::Fingerprint)?
+        .current;
+    if fingerprint.components.is_empty() {
+        return Err(EnrollmentError::Fingerprint("No hardware identifiers available".to_string()));
+    }
+    log_info!("[Enroll] Step 2: fingerprint {} ({} components)", fingerprint.fingerprint, fingerprint.components.len());
+
+    // 3. Cafe info is informational only
+    match http.get(format!("{}/api/cafe/mine", base)).bearer_auth(&token).send().await {
+        Ok(r) if r.status().is_success() => {
+            let cafe: Value = r.json().await.unwrap_or(Value::Null);
+            log_info!("[Enroll] Step 3: cafe {}", cafe.get("name").and_then(|n| n.as_str()).unwrap_or("N/A"));
+        }
+        Ok(r) => log_info!("[Enroll] Step 3: could not fetch cafe info ({})", r.status()),
+        Err(e) => log_info!("[Enroll] Step 3: could not fetch cafe info ({})", e),
+    }
+
+    // 4. License: explicit key, then /license/, then /license/mine
+    let license_key = match &req.license_key {
+        Some(key) if !key.is_empty() => key.clone(),
+        _ => {
+            let mut found = None;
+            let mut errors = Vec::new();
+            for endpoint in ["/api/license/", "/api/license/mine"] {
+                match fetch_licenses(&http, &format!("{}{}", base, endpoint), &token).await {
+                    Ok(licenses) => {
+                        found = pick_license(&licenses);
+                        if found.is_some() {
+                            break;
+                        }
+                    }
+                    Err(e) => errors.push(format!("{}: {}", endpoint, e)),
+                }
+            }
+            found.ok_or_else(|| {
+                let mut message = "No active license found for this cafe".to_string();
+                if !errors.is_empty() {
+                    message = format!("{} ({})", message, errors.join("; "));
+                }
+                EnrollmentError::License(message)
+            })?
+        }
+    };
+    log_info!("[Enroll] Step 4: using license {}", redact(&license_key));
+
+    // 5. Register device
+    let body = json!({
+        "name": req.pc_name,
+        "license_key": license_key,
+        "hardware_fingerprint": fingerprint.fingerprint,
+        "fingerprint_version": fingerprint.version,
+        "fingerprint_components": fingerprint.components,
+        "capabilities": {
+            "os": std::env::consts::OS,
+            "version": env!("CARGO_PKG_VERSION"),
+            "features": CLIENT_FEATURES,
+        }
+    });
+    let response = http
+        .post(format!("{}/api/clientpc/register", base))
+        .bearer_auth(&token)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| EnrollmentError::Register(e.to_string()))?;
+    if !response.status().is_success() {
+        return Err(EnrollmentError::Register(error_detail(response).await));
+    }
+    let pc = response.json::<RegisterResponse>().await
+        .map_err(|e| EnrollmentError::Register(format!("Unexpected register response: {}", e)))?;
+    let device_secret = pc.device_secret
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| EnrollmentError::Register("Backend did not return a device secret".to_string()))?;
+    log_info!("[Enroll] Step 5: registered as PC #{}", pc.id);
+
+    // The backend now knows this fingerprint, so it becomes the baseline
+    if let Err(e) = crate::fingerprint::commit(config_dir, &fingerprint) {
+        log_warn!("[Enroll] Failed to store fingerprint baseline: {}", e);
+    }
+
+    // 6. Persist credentials
+    let creds = DeviceCredentials {
+        pc_id: pc.id,
+        license_key: license_key.clone(),
+        device_secret,
+    };
+    let dir = config_dir.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || crate::write_device_credentials(&dir, &creds))
+        .await
+        .map_err(|e| EnrollmentError::Persist(e.to_string()))?
+        .map_err(EnrollmentError::Persist)?;
+    log_info!("[Enroll] Step 6: credentials saved");
+
+    Ok(EnrollmentResult {
+        pc_id: pc.id,
+        license_key,
+        cafe_id: pc.cafe_id,
+        name: pc.name.unwrap_or_else(|| req.pc_name.clone()),
+    })
+}
+
+/// Keep only the last four characters of a secret for log output
+fn redact(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 4 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{}", tail)
+}
+
+/// Headless enrollment for provisioning scripts:
+///
+///     primus-client --enroll --email admin@cafe --name PC-07 [--license KEY] [--backend URL]
+///
+/// The admin password is read from `PRIMUS_ADMIN_PASSWORD` so it never shows
+/// up in the process list. Returns `None` when `--enroll` was not passed.
+pub fn run_cli(config_dir: Option<std::path::PathBuf>) -> Option<i32> {
+    let args: Vec<String> = std::env::args().collect();
+    if !args.iter().any(|a| a == "--enroll") {
+        return None;
+    }
+    let arg = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned();
+
+    if let Some(url) = arg("--backend") {
+        if let Err(e) = crate::config::apply_patch(json!({ "backend_url": url })) {
+            eprintln!("{}", e);
+            return Some(1);
+        }
+    }
+    let (admin_email, admin_password) = match (arg("--email"), std::env::var("PRIMUS_ADMIN_PASSWORD").ok()) {
+        (Some(email), Some(password)) => (email, password),
+        _ => {
+            eprintln!("Usage: --enroll --email <admin email> [--name <pc name>] [--license <key>] [--backend <url>]");
+            eprintln!("The admin password must be provided in PRIMUS_ADMIN_PASSWORD");
+            return Some(1);
+        }
+    };
+    let pc_name = arg("--name")
+        .or_else(|| Some(crate::config::current().pc_name).filter(|n| !n.is_empty()))
+        .unwrap_or_else(|| {
+            hostname::get().map(|h| h.to_string_lossy().to_string()).unwrap_or_else(|_| "Unknown PC".to_string())
+        });
+    let config_dir = match config_dir {
+        Some(dir) => dir,
+        None => {
+            eprintln!("Could not find config dir");
+            return Some(1);
+        }
+    };
+
+    let req = EnrollmentRequest { admin_email, admin_password, pc_name, license_key: arg("--license") };
+    match tauri::async_runtime::block_on(enroll(&config_dir, &req)) {
+        Ok(result) => {
+            println!("{}", serde_json::to_string(&result).unwrap_or_default());
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("{}", serde_json::to_string(&e).unwrap_or_else(|_| e.to_string()));
+            Some(e.exit_code())
+        }
+    }
+}
+
+/// Enroll this PC with the backend and start the command agent.
+#[tauri::command]
+pub async fn register_pc_with_backend(
+    app_handle: tauri::AppHandle,
+    agent_state: tauri::State<'_, crate::agent::AgentState>,
+    admin_email: String,
+    admin_password: String,
+    pc_name: String,
+    license_key: Option<String>,
+    backend_url: Option<String>,
+) -> Result<EnrollmentResult, EnrollmentError> {
+    // The setup screen lets the installer pick the backend to enroll against
+    if let Some(url) = backend_url.filter(|u| !u.is_empty()) {
+        let cfg = crate::config::apply_patch(json!({ "backend_url": url }))
+            .map_err(EnrollmentError::Config)?;
+        crate::config::emit_changed(&app_handle, &cfg);
+    }
+    let config_dir = app_handle.path_resolver().app_config_dir()
+        .ok_or_else(|| EnrollmentError::Persist("Could not find config dir".to_string()))?;
+
+    let req = EnrollmentRequest { admin_email, admin_password, pc_name, license_key };
+    let result = enroll(&config_dir, &req).await?;
+
+    if let Err(e) = crate::config::apply_patch(json!({ "pc_name": &result.name })) {
+        log_warn!("[Enroll] Failed to store PC name in config: {}", e);
+    }
+
+    // Restart the agent so it picks up the new credentials
+    agent_state.stop();
+    if let Err(e) = crate::agent::start(&app_handle, &agent_state, config_dir) {
+        log_warn!("[Enroll] Enrolled but failed to start command agent: {}", e);
+    }
+    Ok(result)
+}
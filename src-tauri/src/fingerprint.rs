@@ -0,0 +1,384 @@
+// Multi-source hardware fingerprint.
+//
+// The fingerprint is built from several independent hardware/OS identifiers,
+// each with a weight. Only stable, machine-unique identifiers are used, so a
+// renamed PC or extra RAM no longer looks like a new device and two identical
+// builds don't collide.
+//
+// Format: `v2:<sha256 hex>` where the hash covers the sorted
+// `name=digest` pairs of all collected components. Raw identifiers never
+// leave the machine; only per-component SHA-256 digests are reported, which
+// lets the backend fuzzy-match a device whose hardware partly changed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sysinfo::{CpuExt, System, SystemExt};
+
+pub const FINGERPRINT_VERSION: u32 = 2;
+const BASELINE_FILE: &str = "fingerprint.json";
+/// Share of the total weight that must still match to call it the same device
+const SAME_DEVICE_THRESHOLD: f64 = 0.6;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ComponentDigest {
+    pub name: String,
+    pub weight: u32,
+    pub digest: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Fingerprint {
+    pub version: u32,
+    pub fingerprint: String,
+    pub components: Vec<ComponentDigest>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MatchReport {
+    /// Weighted share of the baseline that still matches (0.0 - 1.0)
+    pub score: f64,
+    pub same_device: bool,
+    pub unchanged: Vec<String>,
+    pub changed: Vec<String>,
+    pub missing: Vec<String>,
+    pub added: Vec<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FingerprintReport {
+    pub current: Fingerprint,
+    /// `None` on first run (no baseline yet)
+    pub baseline_version: Option<u32>,
+    pub report: Option<MatchReport>,
+}
+
+fn sha256_hex(data: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Raw identifiers, keyed by component name
+type RawComponents = BTreeMap<&'static str, (u32, String)>;
+
+fn insert(raw: &mut RawComponents, name: &'static str, weight: u32, value: Option<String>) {
+    if let Some(value) = value.map(|v| v.trim().to_string()) {
+        // Firmware placeholders are not identifiers
+        let lower = value.to_lowercase();
+        let placeholder = value.is_empty()
+            || lower.contains("to be filled")
+            || lower.contains("default string")
+            || lower.contains("not specified")
+            || lower == "none"
+            || value.chars().all(|c| c == '0' || c == '-' || c == 'f' || c == 'F');
+        if !placeholder {
+            raw.insert(name, (weight, value));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok()
+}
+
+#[cfg(target_os = "linux")]
+fn collect_platform(raw: &mut RawComponents) {
+    insert(raw, "machine_id", 30, read("/etc/machine-id").or_else(|| read("/var/lib/dbus/machine-id")));
+    // product_uuid/board_serial are root-only on most distros
+    insert(raw, "product_uuid", 30, read("/sys/class/dmi/id/product_uuid"));
+    insert(raw, "board_serial", 10, read("/sys/class/dmi/id/board_serial"));
+
+    // Serials of physical disks
+    let mut disks = Vec::new();
+    if let Ok(entries) = fs::read_dir("/sys/block") {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with("loop") || name.starts_with("ram") || name.starts_with("zram") || name.starts_with("dm-") {
+                continue;
+            }
+            let serial = read(&format!("/sys/block/{}/device/serial", name))
+                .or_else(|| read(&format!("/sys/block/{}/device/wwid", name)));
+            if let Some(serial) = serial.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+                disks.push(serial);
+            }
+        }
+    }
+    disks.sort();
+    insert(raw, "disk_serials", 15, Some(disks.join(",")));
+
+    // MACs of physical NICs only (virtual interfaces have no `device` link)
+    let mut macs = Vec::new();
+    if let Ok(entries) = fs::read_dir("/sys/class/net") {
+        for entry in entries.flatten() {
+            if !entry.path().join("device").exists() {
+                continue;
+            }
+            if let Some(mac) = read(entry.path().join("address")) {
+                macs.push(mac.trim().to_lowercase());
+            }
+        }
+    }
+    macs.sort();
+    insert(raw, "mac_addresses", 15, Some(macs.join(",")));
+}
+
+#[cfg(target_os = "windows")]
+fn collect_platform(raw: &mut RawComponents) {
+    use std::process::Command;
+
+    insert(raw, "machine_id", 30, windows_machine_guid());
+
+    // One PowerShell round-trip for all CIM identifiers
+    let script = "$p = Get-CimInstance Win32_ComputerSystemProduct; \
+        $b = Get-CimInstance Win32_BaseBoard; \
+        $d = Get-CimInstance Win32_DiskDrive | Where-Object { $_.MediaType -like 'Fixed*' } | ForEach-Object { $_.SerialNumber }; \
+        $m = Get-NetAdapter -Physical -ErrorAction SilentlyContinue | ForEach-Object { $_.MacAddress }; \
+        @{ uuid = $p.UUID; board = $b.SerialNumber; disks = @($d); macs = @($m) } | ConvertTo-Json -Compress";
+    let output = Command::new("powershell")
+        .args(&["-NoProfile", "-NonInteractive", "-Command", script])
+        .output();
+    let info: serde_json::Value = match output {
+        Ok(o) if o.status.success() => serde_json::from_slice(&o.stdout).unwrap_or_default(),
+        _ => serde_json::Value::Null,
+    };
+    let list = |key: &str, normalize: fn(&str) -> String| -> Option<String> {
+        let mut items: Vec<String> = info.get(key)?.as_array()?
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|s| normalize(s.trim()))
+            .filter(|s| !s.is_empty())
+            .collect();
+        items.sort();
+        Some(items.join(","))
+    };
+
+    insert(raw, "product_uuid", 30, info.get("uuid").and_then(|v| v.as_str()).map(String::from));
+    insert(raw, "board_serial", 10, info.get("board").and_then(|v| v.as_str()).map(String::from));
+    insert(raw, "disk_serials", 15, list("disks", |s| s.to_lowercase()));
+    // Get-NetAdapter writes MACs as AA-BB-..., Linux as aa:bb:...
+    insert(raw, "mac_addresses", 15, list("macs", |s| s.to_lowercase().replace('-', ":")));
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn collect_platform(_raw: &mut RawComponents) {}
+
+/// `HKLM\SOFTWARE\Microsoft\Cryptography\MachineGuid`, set at OS install
+#[cfg(target_os = "windows")]
+fn windows_machine_guid() -> Option<String> {
+    let output = std::process::Command::new("reg")
+        .args(&["query", "HKLM\\SOFTWARE\\Microsoft\\Cryptography", "/v", "MachineGuid"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|l| l.contains("MachineGuid"))
+        .and_then(|l| l.split_whitespace().last())
+        .map(String::from)
+}
+
+fn collect_raw() -> &'static RawComponents {
+    static RAW: OnceLock<RawComponents> = OnceLock::new();
+    RAW.get_or_init(|| {
+        let mut raw = RawComponents::new();
+        collect_platform(&mut raw);
+
+        // Weak signal, but keeps the fingerprint non-empty on exotic platforms
+        let mut s = System::new_all();
+        s.refresh_cpu();
+        insert(&mut raw, "cpu_brand", 5, s.cpus().first().map(|c| c.brand().to_string()));
+        raw
+    })
+}
+
+/// Collect the current fingerprint (cached for the process lifetime)
+pub fn current() -> Fingerprint {
+    let components: Vec<ComponentDigest> = collect_raw()
+        .iter()
+        .map(|(name, (weight, value))| ComponentDigest {
+            name: name.to_string(),
+            weight: *weight,
+            digest: sha256_hex(value),
+        })
+        .collect();
+
+    let combined = components.iter()
+        .map(|c| format!("{}={}", c.name, c.digest))
+        .collect::<Vec<_>>()
+        .join(";");
+
+    Fingerprint {
+        version: FINGERPRINT_VERSION,
+        fingerprint: format!("v{}:{}", FINGERPRINT_VERSION, sha256_hex(&combined)),
+        components,
+    }
+}
+
+/// Most stable single identifier available, used to bind local secrets to
+/// this machine without breaking when a disk or NIC is swapped.
+pub fn machine_key_material() -> String {
+    let raw = collect_raw();
+    ["machine_id", "product_uuid"]
+        .iter()
+        .find_map(|name| raw.get(name).map(|(_, value)| value.clone()))
+        .unwrap_or_else(|| current().fingerprint)
+}
+
+/// Compare a baseline against the current components
+pub fn match_components(baseline: &[ComponentDigest], current: &[ComponentDigest]) -> MatchReport {
+    let mut report = MatchReport {
+        score: 0.0,
+        same_device: false,
+        unchanged: Vec::new(),
+        changed: Vec::new(),
+        missing: Vec::new(),
+        added: Vec::new(),
+    };
+    let mut total = 0u32;
+    let mut matched = 0u32;
+
+    for old in baseline {
+        total += old.weight;
+        match current.iter().find(|c| c.name == old.name) {
+            Some(new) if new.digest == old.digest => {
+                matched += old.weight;
+                report.unchanged.push(old.name.clone());
+            }
+            Some(_) => report.changed.push(old.name.clone()),
+            None => report.missing.push(old.name.clone()),
+        }
+    }
+    for new in current {
+        if !baseline.iter().any(|c| c.name == new.name) {
+            report.added.push(new.name.clone());
+        }
+    }
+
+    report.score = if total == 0 { 0.0 } else { matched as f64 / total as f64 };
+    report.same_device = report.score >= SAME_DEVICE_THRESHOLD;
+    report
+}
+
+/// Compare the current fingerprint against the stored baseline. Read-only;
+/// the baseline only moves forward through `commit`.
+pub fn compare(config_dir: &Path) -> Result<FingerprintReport, String> {
+    let current = current();
+    let baseline: Option<Fingerprint> = fs::read_to_string(config_dir.join(BASELINE_FILE)).ok()
+        .and_then(|data| serde_json::from_str(&data).ok());
+    let report = baseline.as_ref().map(|b| match_components(&b.components, &current.components));
+
+    Ok(FingerprintReport {
+        current,
+        baseline_version: baseline.map(|b| b.version),
+        report,
+    })
+}
+
+/// Store `baseline` as the fingerprint later runs are compared against
+pub fn commit(config_dir: &Path, baseline: &Fingerprint) -> Result<(), String> {
+    fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+    let data = serde_json::to_string_pretty(baseline).map_err(|e| e.to_string())?;
+    fs::write(config_dir.join(BASELINE_FILE), data).map_err(|e| e.to_string())
+}
+
+/// Current fingerprint with per-component digests and a fuzzy match against
+/// the last known baseline.
+#[tauri::command]
+pub async fn get_fingerprint_report(app_handle: tauri::AppHandle) -> Result<FingerprintReport, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+    tauri::async_runtime::spawn_blocking(move || compare(&config_dir))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digests(items: &[(&str, u32, &str)]) -> Vec<ComponentDigest> {
+        items
+            .iter()
+            .map(|(name, weight, digest)| ComponentDigest { name: name.to_string(), weight: *weight, digest: digest.to_string() })
+            .collect()
+    }
+
+    fn baseline() -> Vec<ComponentDigest> {
+        digests(&[("machine_id", 30, "a"), ("product_uuid", 30, "b"), ("disk_serials", 15, "c"), ("mac_addresses", 15, "d"), ("cpu_brand", 10, "e")])
+    }
+
+    #[test]
+    fn match_components_scores_by_weight() {
+        // (current, score, same_device)
+        let cases = [
+            (baseline(), 1.0, true),
+            // New disk: 85 of 100 still match
+            (digests(&[("machine_id", 30, "a"), ("product_uuid", 30, "b"), ("disk_serials", 15, "X"), ("mac_addresses", 15, "d"), ("cpu_brand", 10, "e")]), 0.85, true),
+            // Exactly at the threshold
+            (digests(&[("machine_id", 30, "a"), ("product_uuid", 30, "b")]), 0.6, true),
+            // Below it
+            (digests(&[("machine_id", 30, "a"), ("disk_serials", 15, "c"), ("cpu_brand", 10, "e")]), 0.55, false),
+            (Vec::new(), 0.0, false),
+        ];
+        for (current, score, same_device) in cases {
+            let report = match_components(&baseline(), &current);
+            assert!((report.score - score).abs() < 0.001, "score {} != {}", report.score, score);
+            assert_eq!(report.same_device, same_device, "score {}", report.score);
+        }
+    }
+
+    #[test]
+    fn match_components_lists_changed_missing_and_added() {
+        let current = digests(&[("machine_id", 30, "a"), ("product_uuid", 30, "B"), ("mac_addresses", 15, "d"), ("board_serial", 10, "f")]);
+        let report = match_components(&baseline(), &current);
+        assert_eq!(report.unchanged, vec!["machine_id".to_string(), "mac_addresses".to_string()]);
+        assert_eq!(report.changed, vec!["product_uuid".to_string()]);
+        assert_eq!(report.missing, vec!["disk_serials".to_string(), "cpu_brand".to_string()]);
+        assert_eq!(report.added, vec!["board_serial".to_string()]);
+    }
+
+    #[test]
+    fn empty_baseline_is_never_the_same_device() {
+        let report = match_components(&[], &baseline());
+        assert_eq!(report.score, 0.0);
+        assert!(!report.same_device);
+        assert_eq!(report.added.len(), 5);
+    }
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-fingerprint-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn compare_does_not_store_a_baseline() {
+        let dir = temp_dir();
+        let first = compare(&dir).unwrap();
+        assert!(first.report.is_none());
+        assert!(!dir.join(BASELINE_FILE).exists());
+        assert!(compare(&dir).unwrap().report.is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compare_reports_against_the_committed_baseline() {
+        let dir = temp_dir();
+        let mut stale = current();
+        stale.components.push(ComponentDigest { name: "retired_disk".to_string(), weight: 1, digest: "00".to_string() });
+        commit(&dir, &stale).unwrap();
+
+        let report = compare(&dir).unwrap().report.unwrap();
+        assert_eq!(report.missing, vec!["retired_disk".to_string()]);
+        // Comparing again still sees the change until the new baseline is committed
+        assert_eq!(compare(&dir).unwrap().report.unwrap().missing, vec!["retired_disk".to_string()]);
+
+        commit(&dir, &current()).unwrap();
+        assert!(compare(&dir).unwrap().report.unwrap().missing.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
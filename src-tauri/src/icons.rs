@@ -0,0 +1,634 @@
+// Game and application icons.
+//
+// Icons are extracted once and cached as PNG under `<app cache>/icons/`, named
+// after the SHA-256 of the file they came from. The webview loads them through
+// the asset protocol (`convertFileSrc`), whose scope only covers that folder.
+//
+// - Windows executables: the first RT_GROUP_ICON in the PE resource section,
+//   largest image. The PE is parsed directly, so Proton/Wine games on Linux
+//   get their icon too.
+// - Linux: icons the launchers install into the hicolor theme
+//   (`steam_icon_<appid>`, `lutris_<slug>`), and `.desktop` entries whose
+//   Exec line runs the executable or launcher URI.
+// - Launcher URIs: otherwise the biggest executable in the install folder.
+
+use std::fs;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use image::{DynamicImage, ImageOutputFormat};
+
+/// Icons are downscaled to at most this size
+const MAX_ICON_SIZE: u32 = 256;
+/// Resource sections bigger than this are not read
+const MAX_RESOURCE_SECTION: u32 = 64 << 20;
+
+const RT_ICON: u32 = 3;
+const RT_GROUP_ICON: u32 = 14;
+
+// Executables in a game folder that are never the game itself
+const NON_GAME_EXE_HINTS: &[&str] = &["unins", "setup", "redist", "crash", "report", "helper", "launcher", "dxsetup", "vc_"];
+
+/// `<app cache>/icons`
+pub fn cache_dir(app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path_resolver()
+        .app_cache_dir()
+        .map(|dir| dir.join("icons"))
+        .ok_or_else(|| "Could not find cache dir".to_string())
+}
+
+/// Cached PNG icon for a launch target (executable or launcher URI)
+pub fn resolve(cache_dir: &Path, target: &str, install_path: &str) -> Option<PathBuf> {
+    if crate::library::is_launch_uri(target) {
+        #[cfg(target_os = "linux")]
+        if let Some(icon) = desktop_icon(target, launcher_icon_name(target).as_deref()) {
+            return cache_image(cache_dir, &icon).ok();
+        }
+        return main_executable(Path::new(install_path)).and_then(|exe| cache_exe_icon(cache_dir, &exe).ok());
+    }
+
+    let exe = Path::new(target);
+    match cache_exe_icon(cache_dir, exe) {
+        Ok(path) => Some(path),
+        Err(_) => {
+            #[cfg(target_os = "linux")]
+            if let Some(icon) = desktop_icon(target, None) {
+                return cache_image(cache_dir, &icon).ok();
+            }
+            None
+        }
+    }
+}
+
+/// Icon for an executable, extracted from its PE resources
+pub fn cache_exe_icon(cache_dir: &Path, exe: &Path) -> Result<PathBuf, String> {
+    let hash = crate::policy::sha256_file(exe)?;
+    let cached = cache_dir.join(format!("{}.png", hash));
+    if cached.exists() {
+        return Ok(cached);
+    }
+    let image = pe_icon(exe)?;
+    write_png(&cached, image)?;
+    Ok(cached)
+}
+
+/// Copy an image file (PNG/JPEG/ICO...) into the cache as PNG
+pub fn cache_image(cache_dir: &Path, path: &Path) -> Result<PathBuf, String> {
+    let hash = crate::policy::sha256_file(path)?;
+    let cached = cache_dir.join(format!("{}.png", hash));
+    if cached.exists() {
+        return Ok(cached);
+    }
+    let image = image::open(path).map_err(|e| format!("Cannot decode {}: {}", path.display(), e))?;
+    write_png(&cached, image)?;
+    Ok(cached)
+}
+
+fn write_png(dest: &Path, image: DynamicImage) -> Result<(), String> {
+    let image = if image.width() > MAX_ICON_SIZE || image.height() > MAX_ICON_SIZE {
+        image.resize(MAX_ICON_SIZE, MAX_ICON_SIZE, image::imageops::FilterType::Triangle)
+    } else {
+        image
+    };
+    let mut buf = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+        .map_err(|e| format!("Failed to encode icon: {}", e))?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    // Write then rename, so a half-written file is never served
+    let tmp = dest.with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+    fs::write(&tmp, &buf).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    fs::rename(&tmp, dest).map_err(|e| format!("Failed to write {}: {}", dest.display(), e))
+}
+
+/// Biggest plausible game executable in (or one level below) an install folder
+fn main_executable(install_dir: &Path) -> Option<PathBuf> {
+    if install_dir.as_os_str().is_empty() {
+        return None;
+    }
+    let mut best: Option<(u64, PathBuf)> = None;
+    let mut dirs = vec![(install_dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else { continue };
+            if meta.is_dir() {
+                if depth < 1 {
+                    dirs.push((path, depth + 1));
+                }
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if !name.ends_with(".exe") || NON_GAME_EXE_HINTS.iter().any(|hint| name.contains(hint)) {
+                continue;
+            }
+            if best.as_ref().map(|(size, _)| meta.len() > *size).unwrap_or(true) {
+                best = Some((meta.len(), path));
+            }
+        }
+    }
+    best.map(|(_, path)| path)
+}
+
+// ---------------------------------------------------------------------------
+// PE resources
+
+fn u16_at(buf: &[u8], offset: usize) -> Option<u16> {
+    buf.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+    buf.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// The `.rsrc` section, addressed by RVA
+struct Resources {
+    data: Vec<u8>,
+    /// RVA of `data[0]`
+    section_rva: u32,
+    /// Offset of the root resource directory in `data`
+    root: usize,
+}
+
+impl Resources {
+    fn read(path: &Path) -> Result<Self, String> {
+        let bad = || format!("{} is not a valid Windows executable", path.display());
+        let mut file = fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+        let mut headers = Vec::new();
+        file.by_ref()
+            .take(64 * 1024)
+            .read_to_end(&mut headers)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        if !headers.starts_with(b"MZ") {
+            return Err(bad());
+        }
+
+        let pe = u32_at(&headers, 0x3c).ok_or_else(bad)? as usize;
+        if headers.get(pe..pe + 4) != Some(&b"PE\0\0"[..]) {
+            return Err(bad());
+        }
+        let coff = pe + 4;
+        let sections = u16_at(&headers, coff + 2).ok_or_else(bad)? as usize;
+        let optional_size = u16_at(&headers, coff + 16).ok_or_else(bad)? as usize;
+        let optional = coff + 20;
+        // Data directories start at 96 (PE32) or 112 (PE32+); resources are #2
+        let directories = match u16_at(&headers, optional).ok_or_else(bad)? {
+            0x10b => optional + 96,
+            0x20b => optional + 112,
+            _ => return Err(bad()),
+        };
+        let rsrc_rva = u32_at(&headers, directories + 16).ok_or_else(bad)?;
+        if rsrc_rva == 0 {
+            return Err(format!("{} has no resources", path.display()));
+        }
+
+        for i in 0..sections {
+            let section = optional + optional_size + i * 40;
+            let virtual_size = u32_at(&headers, section + 8).ok_or_else(bad)?;
+            let rva = u32_at(&headers, section + 12).ok_or_else(bad)?;
+            let raw_size = u32_at(&headers, section + 16).ok_or_else(bad)?;
+            let raw_offset = u32_at(&headers, section + 20).ok_or_else(bad)?;
+            if rsrc_rva < rva || rsrc_rva >= rva.saturating_add(virtual_size.max(raw_size)) {
+                continue;
+            }
+            if raw_size > MAX_RESOURCE_SECTION {
+                return Err(format!("{} has an oversized resource section", path.display()));
+            }
+            let mut data = vec![0u8; raw_size as usize];
+            file.seek(SeekFrom::Start(raw_offset as u64))
+                .and_then(|_| file.read_exact(&mut data))
+                .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+            return Ok(Self { data, section_rva: rva, root: (rsrc_rva - rva) as usize });
+        }
+        Err(bad())
+    }
+
+    /// `(id, offset)` of each entry in a directory; named entries get `None`
+    fn entries(&self, dir: usize) -> Vec<(Option<u32>, u32)> {
+        let named = u16_at(&self.data, dir + 12).unwrap_or(0) as usize;
+        let ids = u16_at(&self.data, dir + 14).unwrap_or(0) as usize;
+        (0..named + ids)
+            .filter_map(|i| {
+                let entry = dir + 16 + i * 8;
+                let name = u32_at(&self.data, entry)?;
+                let offset = u32_at(&self.data, entry + 4)?;
+                Some(((name & 0x8000_0000 == 0).then_some(name), offset))
+            })
+            .collect()
+    }
+
+    fn subdir(&self, offset: u32) -> Option<usize> {
+        (offset & 0x8000_0000 != 0).then(|| self.root + (offset & 0x7fff_ffff) as usize)
+    }
+
+    /// Follow the first entry of each level down to the data
+    fn first_leaf(&self, mut offset: u32) -> Option<&[u8]> {
+        for _ in 0..4 {
+            match self.subdir(offset) {
+                Some(dir) => offset = self.entries(dir).first()?.1,
+                None => {
+                    let entry = self.root + offset as usize;
+                    let rva = u32_at(&self.data, entry)?;
+                    let size = u32_at(&self.data, entry + 4)? as usize;
+                    let start = rva.checked_sub(self.section_rva)? as usize;
+                    return self.data.get(start..start.checked_add(size)?);
+                }
+            }
+        }
+        None
+    }
+
+    /// Resource of `kind`, the first one when `id` is `None`
+    fn find(&self, kind: u32, id: Option<u32>) -> Option<&[u8]> {
+        let (_, types) = self.entries(self.root).into_iter().find(|(t, _)| *t == Some(kind))?;
+        let names = self.entries(self.subdir(types)?);
+        let (_, offset) = match id {
+            Some(id) => names.into_iter().find(|(n, _)| *n == Some(id))?,
+            None => names.into_iter().next()?,
+        };
+        self.first_leaf(offset)
+    }
+}
+
+/// The application icon of a Windows executable, largest image
+fn pe_icon(path: &Path) -> Result<DynamicImage, String> {
+    let resources = Resources::read(path)?;
+    let no_icon = || format!("{} has no icon", path.display());
+    let group = resources.find(RT_GROUP_ICON, None).ok_or_else(no_icon)?;
+
+    // GRPICONDIR: 6-byte header, then 14-byte entries ending in the RT_ICON id
+    let count = u16_at(group, 4).ok_or_else(no_icon)? as usize;
+    let (entry, _) = (0..count)
+        .filter_map(|i| {
+            let entry = group.get(6 + i * 14..6 + (i + 1) * 14)?;
+            let size = if entry[0] == 0 { 256 } else { entry[0] as u32 };
+            let bits = u16_at(entry, 6)?;
+            Some((entry, (size, bits)))
+        })
+        .max_by_key(|(_, rank)| *rank)
+        .ok_or_else(no_icon)?;
+    let icon_id = u16_at(entry, 12).ok_or_else(no_icon)? as u32;
+    let data = resources.find(RT_ICON, Some(icon_id)).ok_or_else(no_icon)?;
+
+    if data.starts_with(b"\x89PNG") {
+        return image::load_from_memory_with_format(data, image::ImageFormat::Png)
+            .map_err(|e| format!("Cannot decode icon of {}: {}", path.display(), e));
+    }
+    // A bare DIB: wrap it in a single-image .ico so the ICO decoder handles
+    // the AND mask and colour depths
+    let mut ico = Vec::with_capacity(22 + data.len());
+    ico.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    ico.extend_from_slice(&entry[..8]);
+    ico.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    ico.extend_from_slice(&22u32.to_le_bytes());
+    ico.extend_from_slice(data);
+    image::load_from_memory_with_format(&ico, image::ImageFormat::Ico)
+        .map_err(|e| format!("Cannot decode icon of {}: {}", path.display(), e))
+}
+
+// ---------------------------------------------------------------------------
+// Linux desktop entries and icon themes
+
+#[cfg(target_os = "linux")]
+fn data_dirs() -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => out.push(PathBuf::from(dir)),
+        _ => out.extend(dirs::home_dir().map(|h| h.join(".local/share"))),
+    }
+    let system = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    out.extend(system.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+    out.extend(dirs::home_dir().map(|h| h.join(".local/share/flatpak/exports/share")));
+    out.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    out
+}
+
+/// Theme icon names Steam and Lutris install for their games
+#[cfg(target_os = "linux")]
+fn launcher_icon_name(uri: &str) -> Option<String> {
+    if let Some(appid) = uri.strip_prefix("steam://rungameid/") {
+        return Some(format!("steam_icon_{}", appid));
+    }
+    uri.strip_prefix("lutris:rungame/").map(|slug| format!("lutris_{}", slug))
+}
+
+/// Largest raster icon called `name` in the hicolor theme or pixmaps of
+/// `data_dirs`
+#[cfg(target_os = "linux")]
+fn theme_icon(data_dirs: &[PathBuf], name: &str) -> Option<PathBuf> {
+    let direct = Path::new(name);
+    if direct.is_absolute() {
+        return direct.is_file().then(|| direct.to_path_buf());
+    }
+    if name.is_empty() || name.contains('/') {
+        return None;
+    }
+
+    let mut best: Option<(u32, PathBuf)> = None;
+    for data_dir in data_dirs {
+        let theme = data_dir.join("icons/hicolor");
+        let Ok(sizes) = fs::read_dir(&theme) else { continue };
+        for size_dir in sizes.flatten() {
+            // "256x256", "48x48@2"; scalable/ is SVG, which we can't decode
+            let dir_name = size_dir.file_name().to_string_lossy().to_string();
+            let Some(size) = dir_name.split('x').next().and_then(|s| s.parse::<u32>().ok()) else { continue };
+            let scale = dir_name.split('@').nth(1).and_then(|s| s.parse::<u32>().ok()).unwrap_or(1);
+            let candidate = size_dir.path().join("apps").join(format!("{}.png", name));
+            if candidate.is_file() && best.as_ref().map(|(s, _)| size * scale > *s).unwrap_or(true) {
+                best = Some((size * scale, candidate));
+            }
+        }
+    }
+    best.map(|(_, path)| path).or_else(|| {
+        data_dirs
+            .iter()
+            .map(|dir| dir.join("pixmaps").join(format!("{}.png", name)))
+            .find(|p| p.is_file())
+    })
+}
+
+/// `Exec` and `Icon` of the `[Desktop Entry]` group
+#[cfg(target_os = "linux")]
+fn parse_desktop_entry(content: &str) -> (Option<String>, Option<String>) {
+    let mut in_entry = false;
+    let (mut exec, mut icon) = (None, None);
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("Exec=") {
+            exec = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("Icon=") {
+            icon = Some(value.to_string());
+        }
+    }
+    (exec, icon)
+}
+
+/// Whether a desktop entry's Exec line runs `target`
+#[cfg(target_os = "linux")]
+fn exec_runs(exec: &str, target: &str) -> bool {
+    if crate::library::is_launch_uri(target) {
+        return exec.split_whitespace().any(|arg| arg.trim_matches('"') == target);
+    }
+    let program = match exec.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or(""),
+        None => exec.split_whitespace().next().unwrap_or(""),
+    };
+    program == target
+}
+
+/// Icon file for `target`, via a well-known theme name or a desktop entry
+#[cfg(target_os = "linux")]
+fn desktop_icon(target: &str, theme_name: Option<&str>) -> Option<PathBuf> {
+    let data_dirs = data_dirs();
+    let mut app_dirs: Vec<PathBuf> = data_dirs.iter().map(|d| d.join("applications")).collect();
+    app_dirs.extend(dirs::desktop_dir());
+    find_desktop_icon(&data_dirs, &app_dirs, target, theme_name)
+}
+
+#[cfg(target_os = "linux")]
+fn find_desktop_icon(data_dirs: &[PathBuf], app_dirs: &[PathBuf], target: &str, theme_name: Option<&str>) -> Option<PathBuf> {
+    if let Some(icon) = theme_name.and_then(|name| theme_icon(data_dirs, name)) {
+        return Some(icon);
+    }
+    for dir in app_dirs {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().map(|e| e != "desktop").unwrap_or(true) {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(&path) else { continue };
+            if let (Some(exec), Some(icon)) = parse_desktop_entry(&content) {
+                if exec_runs(&exec, target) {
+                    if let Some(icon) = theme_icon(data_dirs, &icon) {
+                        return Some(icon);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Cached icon for an executable or launcher URI, as a path for `convertFileSrc`
+#[tauri::command]
+pub async fn get_app_icon(app_handle: tauri::AppHandle, target: String, install_path: Option<String>) -> Result<Option<String>, String> {
+    let cache_dir = cache_dir(&app_handle)?;
+    let icon = tauri::async_runtime::spawn_blocking(move || resolve(&cache_dir, &target, install_path.as_deref().unwrap_or("")))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?;
+    Ok(icon.map(|p| p.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-icons-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn png(size: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(size, size, image::Rgba([200, 30, 30, 255])))
+            .write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+            .unwrap();
+        buf
+    }
+
+    fn put_u16(buf: &mut [u8], offset: usize, value: u16) {
+        buf[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(buf: &mut [u8], offset: usize, value: u32) {
+        buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Resource directory with the given `(id, offset)` entries
+    fn directory(entries: &[(u32, u32)]) -> Vec<u8> {
+        let mut dir = vec![0u8; 16 + entries.len() * 8];
+        put_u16(&mut dir, 14, entries.len() as u16);
+        for (i, (id, offset)) in entries.iter().enumerate() {
+            put_u32(&mut dir, 16 + i * 8, *id);
+            put_u32(&mut dir, 20 + i * 8, *offset);
+        }
+        dir
+    }
+
+    /// Smallest PE32 with a `.rsrc` section holding one group icon whose only
+    /// image is a 16x16 PNG
+    fn pe_with_icon() -> Vec<u8> {
+        const SECTION_RVA: u32 = 0x1000;
+        const SECTION_OFFSET: usize = 0x200;
+        const SUBDIR: u32 = 0x8000_0000;
+        let icon = png(16);
+
+        // root, type dirs, name dirs, language dirs... laid out back to back
+        let mut rsrc = directory(&[(RT_ICON, SUBDIR | 32), (RT_GROUP_ICON, SUBDIR | 56)]);
+        rsrc.extend(directory(&[(1, SUBDIR | 80)]));
+        rsrc.extend(directory(&[(1, SUBDIR | 104)]));
+        rsrc.extend(directory(&[(0x409, 128)]));
+        rsrc.extend(directory(&[(0x409, 144)]));
+        let mut data_entry = |offset: u32, size: usize| {
+            let mut entry = vec![0u8; 16];
+            put_u32(&mut entry, 0, SECTION_RVA + offset);
+            put_u32(&mut entry, 4, size as u32);
+            rsrc.extend(entry);
+        };
+        data_entry(180, icon.len());
+        data_entry(160, 20);
+        // GRPICONDIR with a single 16x16, 32 bpp entry pointing at RT_ICON #1
+        let mut group = vec![0u8; 20];
+        put_u16(&mut group, 2, 1);
+        put_u16(&mut group, 4, 1);
+        group[6] = 16;
+        group[7] = 16;
+        put_u16(&mut group, 10, 1);
+        put_u16(&mut group, 12, 32);
+        put_u32(&mut group, 14, icon.len() as u32);
+        put_u16(&mut group, 18, 1);
+        rsrc.extend(group);
+        rsrc.extend(&icon);
+
+        let mut pe = vec![0u8; SECTION_OFFSET];
+        pe[..2].copy_from_slice(b"MZ");
+        put_u32(&mut pe, 0x3c, 0x40);
+        pe[0x40..0x44].copy_from_slice(b"PE\0\0");
+        let coff = 0x44;
+        put_u16(&mut pe, coff, 0x14c);
+        put_u16(&mut pe, coff + 2, 1);
+        put_u16(&mut pe, coff + 16, 224);
+        let optional = coff + 20;
+        put_u16(&mut pe, optional, 0x10b);
+        put_u32(&mut pe, optional + 96 + 16, SECTION_RVA);
+        put_u32(&mut pe, optional + 96 + 20, rsrc.len() as u32);
+        let section = optional + 224;
+        pe[section..section + 5].copy_from_slice(b".rsrc");
+        put_u32(&mut pe, section + 8, rsrc.len() as u32);
+        put_u32(&mut pe, section + 12, SECTION_RVA);
+        put_u32(&mut pe, section + 16, rsrc.len() as u32);
+        put_u32(&mut pe, section + 20, SECTION_OFFSET as u32);
+        pe.extend(rsrc);
+        pe
+    }
+
+    #[test]
+    fn extracts_and_caches_the_group_icon() {
+        let dir = temp_dir();
+        let exe = dir.join("game.exe");
+        fs::write(&exe, pe_with_icon()).unwrap();
+
+        let icon = pe_icon(&exe).unwrap();
+        assert_eq!((icon.width(), icon.height()), (16, 16));
+
+        let cache = dir.join("cache");
+        let cached = resolve(&cache, &exe.to_string_lossy(), "").unwrap();
+        assert!(cached.starts_with(&cache));
+        assert_eq!(image::open(&cached).unwrap().width(), 16);
+        assert_eq!(cache_exe_icon(&cache, &exe).unwrap(), cached);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn truncated_executables_have_no_icon() {
+        let dir = temp_dir();
+        let full = pe_with_icon();
+        let exe = dir.join("game.exe");
+        for len in [0, 1, 2, 0x3c, 0x42, 0x60, 0x150, 0x200, 0x210, 0x290, full.len() - 1] {
+            fs::write(&exe, &full[..len]).unwrap();
+            assert!(pe_icon(&exe).is_err(), "accepted {} bytes", len);
+        }
+        assert!(resolve(&dir.join("cache"), &exe.to_string_lossy(), "").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bogus_executables_have_no_icon() {
+        let dir = temp_dir();
+        let exe = dir.join("game.exe");
+        let mut cases: Vec<Vec<u8>> = vec![b"#!/bin/sh\necho hi\n".to_vec(), b"MZ".iter().copied().chain(std::iter::repeat(0xff).take(4096)).collect()];
+        // PE offset, section offset and resource offsets pointing far outside the file
+        for offset in [0x3c, 0x44 + 20 + 224 + 20, 0x200 + 20, 0x200 + 16 + 8 + 4] {
+            let mut pe = pe_with_icon();
+            put_u32(&mut pe, offset, 0xffff_fff0);
+            cases.push(pe);
+        }
+        // A resource directory that points at itself
+        let mut pe = pe_with_icon();
+        put_u32(&mut pe, 0x200 + 32 + 20, 0x8000_0000 | 32);
+        cases.push(pe);
+
+        for (i, data) in cases.iter().enumerate() {
+            fs::write(&exe, data).unwrap();
+            assert!(pe_icon(&exe).is_err(), "case {} produced an icon", i);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parses_desktop_entry_exec_and_icon() {
+        let (exec, icon) = parse_desktop_entry(
+            "[Desktop Action x]\nExec=other\n[Desktop Entry]\nName=Game\nExec=\"/opt/My Game/game\" --fullscreen\nIcon=my-game\n",
+        );
+        assert_eq!(exec.as_deref(), Some("\"/opt/My Game/game\" --fullscreen"));
+        assert_eq!(icon.as_deref(), Some("my-game"));
+
+        assert!(exec_runs(&exec.unwrap(), "/opt/My Game/game"));
+        assert!(exec_runs("/opt/game/run.sh %U", "/opt/game/run.sh"));
+        assert!(!exec_runs("/opt/game/run.sh.bak", "/opt/game/run.sh"));
+        assert!(exec_runs("steam steam://rungameid/730", "steam://rungameid/730"));
+        assert!(!exec_runs("steam steam://rungameid/7300", "steam://rungameid/730"));
+
+        assert_eq!(launcher_icon_name("steam://rungameid/730").as_deref(), Some("steam_icon_730"));
+        assert_eq!(launcher_icon_name("lutris:rungame/quake").as_deref(), Some("lutris_quake"));
+        assert_eq!(launcher_icon_name("heroic://launch?appName=x"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn finds_the_largest_theme_icon_via_desktop_entries() {
+        let data = temp_dir();
+        for size in ["32x32", "128x128", "48x48@2"] {
+            let apps = data.join("icons/hicolor").join(size).join("apps");
+            fs::create_dir_all(&apps).unwrap();
+            fs::write(apps.join("my-game.png"), png(8)).unwrap();
+        }
+        fs::create_dir_all(data.join("icons/hicolor/scalable/apps")).unwrap();
+        fs::write(data.join("icons/hicolor/scalable/apps/my-game.svg"), "<svg/>").unwrap();
+        fs::create_dir_all(data.join("pixmaps")).unwrap();
+        fs::write(data.join("pixmaps/steam_icon_730.png"), png(8)).unwrap();
+        let applications = data.join("applications");
+        fs::create_dir_all(&applications).unwrap();
+        fs::write(applications.join("game.desktop"), "[Desktop Entry]\nExec=/opt/game/game\nIcon=my-game\n").unwrap();
+        let data_dirs = vec![data.clone()];
+        let app_dirs = vec![applications];
+
+        let best = data.join("icons/hicolor/128x128/apps/my-game.png");
+        assert_eq!(theme_icon(&data_dirs, "my-game"), Some(best.clone()));
+        assert_eq!(theme_icon(&data_dirs, "../my-game"), None);
+        assert_eq!(find_desktop_icon(&data_dirs, &app_dirs, "/opt/game/game", None), Some(best));
+        assert_eq!(find_desktop_icon(&data_dirs, &app_dirs, "/opt/other/game", None), None);
+        assert_eq!(
+            find_desktop_icon(&data_dirs, &app_dirs, "steam://rungameid/730", Some("steam_icon_730")),
+            Some(data.join("pixmaps/steam_icon_730.png"))
+        );
+        let _ = fs::remove_dir_all(&data);
+    }
+}
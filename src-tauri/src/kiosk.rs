@@ -0,0 +1,390 @@
+// Kiosk state.
+//
+// A single `KioskState`, managed by Tauri, holds what the kiosk needs to know
+// about the PC. The mode is derived from those facts, highest priority first:
+//
+//   Maintenance    staff lifted all restrictions
+//   DialogAllowed  a native dialog is open over the kiosk window (expires
+//                  after `DIALOG_TIMEOUT` if Primus never regains focus)
+//   AppRunning     a game launched through Primus is running
+//   Session        a user is logged in
+//   Locked         nobody is logged in
+//
+// Every change of mode is emitted as `kiosk-state-changed` and handed to the
+// transition callback (which updates the main window). While shortcut blocking
+// is enabled, the rules for the new mode are swapped in as well (see
+// shortcuts.rs).
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::AgentEvents;
+use crate::shortcuts::ShortcutBlocker;
+
+/// `Transition` whenever the kiosk mode changes
+pub const EVENT_KIOSK_STATE_CHANGED: &str = "kiosk-state-changed";
+/// Longest the restrictions stay lifted for a dialog
+pub const DIALOG_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KioskMode {
+    Locked,
+    Session,
+    AppRunning,
+    DialogAllowed,
+    Maintenance,
+}
+
+impl KioskMode {
+    const ALL: [KioskMode; 5] = [
+        KioskMode::Locked,
+        KioskMode::Session,
+        KioskMode::AppRunning,
+        KioskMode::DialogAllowed,
+        KioskMode::Maintenance,
+    ];
+
+    /// Whether the kiosk window should stay above everything
+    pub fn keeps_window_on_top(self) -> bool {
+        matches!(self, KioskMode::Locked | KioskMode::Session)
+    }
+
+    fn from_u8(value: u8) -> Self {
+        Self::ALL.get(value as usize).copied().unwrap_or(KioskMode::Locked)
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct KioskFacts {
+    pub session_active: bool,
+    /// PIDs of games launched through Primus
+    pub launched_pids: Vec<u32>,
+    pub dialog_open: bool,
+    pub maintenance: bool,
+}
+
+impl KioskFacts {
+    pub fn mode(&self) -> KioskMode {
+        if self.maintenance {
+            KioskMode::Maintenance
+        } else if self.dialog_open {
+            KioskMode::DialogAllowed
+        } else if !self.launched_pids.is_empty() {
+            KioskMode::AppRunning
+        } else if self.session_active {
+            KioskMode::Session
+        } else {
+            KioskMode::Locked
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Transition {
+    pub from: KioskMode,
+    pub to: KioskMode,
+    /// What caused it, e.g. `session_started`, `apps_changed`
+    pub reason: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct KioskSnapshot {
+    pub mode: KioskMode,
+    pub since: DateTime<Utc>,
+    pub facts: KioskFacts,
+    /// Shortcut blocking switched on
+    pub shortcuts_enabled: bool,
+}
+
+pub type TransitionCallback = Box<dyn Fn(&Transition) + Send + Sync>;
+
+struct Inner {
+    facts: KioskFacts,
+    since: DateTime<Utc>,
+    events: Option<Arc<dyn AgentEvents>>,
+    on_transition: Option<Arc<TransitionCallback>>,
+}
+
+pub struct KioskState {
+    inner: Mutex<Inner>,
+    /// Copy of the mode, readable without the lock
+    mode: AtomicU8,
+    shortcuts_enabled: AtomicBool,
+    blocker: Mutex<ShortcutBlocker>,
+    /// Held while a transition is applied (callback and shortcut rules), so
+    /// transitions take effect in the order they happened
+    applying: Mutex<()>,
+    /// Bumped by every `allow_dialog`, so only the latest timer expires it
+    dialog_generation: AtomicU64,
+}
+
+impl Default for KioskState {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner { facts: KioskFacts::default(), since: Utc::now(), events: None, on_transition: None }),
+            mode: AtomicU8::new(KioskMode::Locked as u8),
+            shortcuts_enabled: AtomicBool::new(false),
+            blocker: Mutex::new(ShortcutBlocker::default()),
+            applying: Mutex::new(()),
+            dialog_generation: AtomicU64::new(0),
+        }
+    }
+}
+
+impl KioskState {
+    /// Connect the event sink and transition callback once the app is running
+    pub fn attach(self: &Arc<Self>, events: Arc<dyn AgentEvents>, on_transition: TransitionCallback) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner.events = Some(events);
+            inner.on_transition = Some(Arc::new(on_transition));
+        }
+        // A new shortcut policy applies right away
+        let state = self.clone();
+        crate::config::on_change(move |_| {
+            if let Err(e) = state.refresh_shortcuts() {
+                log_warn!("[Kiosk] Failed to apply shortcut policy: {}", e);
+            }
+        });
+    }
+
+    pub fn mode(&self) -> KioskMode {
+        KioskMode::from_u8(self.mode.load(Ordering::SeqCst))
+    }
+
+    pub fn snapshot(&self) -> KioskSnapshot {
+        let inner = self.inner.lock().unwrap();
+        KioskSnapshot {
+            mode: inner.facts.mode(),
+            since: inner.since,
+            facts: inner.facts.clone(),
+            shortcuts_enabled: self.shortcuts_enabled.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Change the facts and report the transition, if the mode changed
+    pub fn update(&self, reason: &str, change: impl FnOnce(&mut KioskFacts)) -> Option<Transition> {
+        let (transition, events, callback, _applying) = {
+            let mut inner = self.inner.lock().unwrap();
+            let from = inner.facts.mode();
+            change(&mut inner.facts);
+            let to = inner.facts.mode();
+            if from == to {
+                return None;
+            }
+            let now = Utc::now();
+            inner.since = now;
+            self.mode.store(to as u8, Ordering::SeqCst);
+            let transition = Transition { from, to, reason: reason.to_string(), at: now };
+            // Taken before `inner` is released, so a later transition can't
+            // be applied before this one
+            let applying = self.applying.lock().unwrap();
+            (transition, inner.events.clone(), inner.on_transition.clone(), applying)
+        };
+
+        log_info!("[Kiosk] {:?} -> {:?} ({})", transition.from, transition.to, transition.reason);
+        if let Some(events) = events {
+            events.emit(EVENT_KIOSK_STATE_CHANGED, serde_json::to_value(&transition).unwrap_or_default());
+        }
+        if let Some(callback) = callback {
+            callback(&transition);
+        }
+        if let Err(e) = self.apply_shortcuts(transition.to) {
+            log_warn!("[Kiosk] Failed to apply shortcut policy: {}", e);
+        }
+        Some(transition)
+    }
+
+    pub fn set_session(&self, active: bool) -> Option<Transition> {
+        self.update(if active { "session_started" } else { "session_ended" }, |f| f.session_active = active)
+    }
+
+    pub fn set_launched(&self, pids: Vec<u32>) -> Option<Transition> {
+        self.update("apps_changed", |f| f.launched_pids = pids)
+    }
+
+    pub fn set_dialog_open(&self, open: bool) -> Option<Transition> {
+        self.update(if open { "dialog_opened" } else { "dialog_closed" }, |f| f.dialog_open = open)
+    }
+
+    /// Enter dialog mode for at most `timeout`; focusing Primus again ends it
+    /// earlier through `set_dialog_open(false)`
+    pub fn allow_dialog(self: &Arc<Self>, timeout: Duration) -> Option<Transition> {
+        let generation = self.dialog_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let transition = self.set_dialog_open(true);
+        let state = self.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(timeout);
+            if state.dialog_generation.load(Ordering::SeqCst) == generation {
+                state.update("dialog_expired", |f| f.dialog_open = false);
+            }
+        });
+        transition
+    }
+
+    pub fn set_maintenance(&self, enabled: bool) -> Option<Transition> {
+        self.update(if enabled { "maintenance_started" } else { "maintenance_ended" }, |f| f.maintenance = enabled)
+    }
+
+    pub fn launched_count(&self) -> usize {
+        self.inner.lock().unwrap().facts.launched_pids.len()
+    }
+
+    pub fn shortcuts_enabled(&self) -> bool {
+        self.shortcuts_enabled.load(Ordering::SeqCst)
+    }
+}
+
+impl KioskState {
+    /// Start blocking shortcuts according to the current mode
+    pub fn enable_shortcuts(&self) -> Result<(), String> {
+        self.shortcuts_enabled.store(true, Ordering::SeqCst);
+        self.refresh_shortcuts()
+    }
+
+    /// Whether the platform blocker is actually in place
+    pub fn shortcuts_installed(&self) -> bool {
+        self.blocker.lock().unwrap().is_installed()
+    }
+
+    pub fn disable_shortcuts(&self) {
+        self.shortcuts_enabled.store(false, Ordering::SeqCst);
+        self.blocker.lock().unwrap().stop();
+    }
+
+    /// Swap in the configured rules for the current mode
+    pub fn refresh_shortcuts(&self) -> Result<(), String> {
+        let _applying = self.applying.lock().unwrap();
+        self.apply_shortcuts(self.mode())
+    }
+
+    /// Callers must hold `applying`
+    fn apply_shortcuts(&self, mode: KioskMode) -> Result<(), String> {
+        if !self.shortcuts_enabled() {
+            return Ok(());
+        }
+        let policy = crate::config::current().kiosk;
+        let rules = if policy.block_shortcuts { policy.shortcuts.for_mode(mode) } else { Vec::new() };
+        self.blocker.lock().unwrap().apply(rules)
+    }
+}
+
+type Kiosk<'a> = tauri::State<'a, Arc<KioskState>>;
+
+#[tauri::command]
+pub async fn get_kiosk_state(kiosk: Kiosk<'_>) -> Result<KioskSnapshot, String> {
+    Ok(kiosk.snapshot())
+}
+
+/// Called by the UI on login and logout
+#[tauri::command]
+pub async fn set_kiosk_session(kiosk: Kiosk<'_>, active: bool) -> Result<KioskMode, String> {
+    kiosk.set_session(active);
+    Ok(kiosk.mode())
+}
+
+/// Enter maintenance (needs a staff token) or leave it
+#[tauri::command]
+pub async fn set_kiosk_maintenance(kiosk: Kiosk<'_>, enabled: bool, admin_token: Option<String>) -> Result<KioskMode, String> {
+    if enabled {
+        let token = admin_token.as_deref().map(str::trim).filter(|t| !t.is_empty());
+        crate::apps::verify_staff_token(token.ok_or("An admin token is required for maintenance mode")?).await?;
+    }
+    kiosk.set_maintenance(enabled);
+    Ok(kiosk.mode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facts(session_active: bool, launched: bool, dialog_open: bool, maintenance: bool) -> KioskFacts {
+        KioskFacts { session_active, launched_pids: if launched { vec![42] } else { Vec::new() }, dialog_open, maintenance }
+    }
+
+    #[test]
+    fn mode_follows_fact_priority() {
+        assert_eq!(facts(false, false, false, false).mode(), KioskMode::Locked);
+        assert_eq!(facts(true, false, false, false).mode(), KioskMode::Session);
+        assert_eq!(facts(true, true, false, false).mode(), KioskMode::AppRunning);
+        // A game started before login still counts as running
+        assert_eq!(facts(false, true, false, false).mode(), KioskMode::AppRunning);
+        assert_eq!(facts(true, true, true, false).mode(), KioskMode::DialogAllowed);
+        assert_eq!(facts(false, false, true, false).mode(), KioskMode::DialogAllowed);
+        assert_eq!(facts(true, true, true, true).mode(), KioskMode::Maintenance);
+        assert_eq!(facts(false, false, false, true).mode(), KioskMode::Maintenance);
+    }
+
+    #[test]
+    fn mode_round_trips_through_the_atomic() {
+        for mode in KioskMode::ALL {
+            assert_eq!(KioskMode::from_u8(mode as u8), mode);
+        }
+    }
+
+    #[test]
+    fn update_reports_only_mode_changes() {
+        let state = KioskState::default();
+        let transition = state.set_session(true).unwrap();
+        assert_eq!((transition.from, transition.to), (KioskMode::Locked, KioskMode::Session));
+        assert!(state.set_session(true).is_none());
+        // Launching under maintenance changes the facts but not the mode
+        state.set_maintenance(true);
+        assert!(state.set_launched(vec![1]).is_none());
+        assert_eq!(state.set_maintenance(false).map(|t| t.to), Some(KioskMode::AppRunning));
+        assert_eq!(state.mode(), KioskMode::AppRunning);
+    }
+
+    #[test]
+    fn dialog_mode_expires() {
+        let state = Arc::new(KioskState::default());
+        state.set_session(true);
+        assert_eq!(state.allow_dialog(Duration::from_millis(50)).map(|t| t.to), Some(KioskMode::DialogAllowed));
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(state.mode(), KioskMode::Session);
+    }
+
+    #[test]
+    fn only_the_latest_dialog_timer_expires_it() {
+        let state = Arc::new(KioskState::default());
+        state.allow_dialog(Duration::from_millis(50));
+        state.allow_dialog(Duration::from_secs(30));
+        std::thread::sleep(Duration::from_millis(500));
+        assert_eq!(state.mode(), KioskMode::DialogAllowed);
+        assert_eq!(state.set_dialog_open(false).map(|t| t.to), Some(KioskMode::Locked));
+    }
+
+    #[test]
+    fn concurrent_transitions_are_applied_in_order() {
+        let state = Arc::new(KioskState::default());
+        let applied = Arc::new(Mutex::new(Vec::new()));
+        let recorder = applied.clone();
+        let callback: TransitionCallback = Box::new(move |t| recorder.lock().unwrap().push(t.to));
+        state.inner.lock().unwrap().on_transition = Some(Arc::new(callback));
+
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let state = state.clone();
+                std::thread::spawn(move || {
+                    for n in 0..200 {
+                        state.set_session((i + n) % 2 == 0);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let applied = applied.lock().unwrap();
+        assert_eq!(applied.last().copied().unwrap_or(KioskMode::Locked), state.mode());
+        // Every transition starts from the mode the previous one ended in
+        assert!(applied.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+}
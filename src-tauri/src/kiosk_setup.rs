@@ -0,0 +1,531 @@
+// Kiosk setup and teardown.
+//
+// Turning a PC into a kiosk changes system settings: the Winlogon shell, the
+// HKCU/HKLM Run keys and the Task Manager / registry editor policies (or their
+// Linux equivalents, see settings.rs; settings a platform lacks are skipped).
+// All access goes through a `SettingsBackend`. Before a
+// value is written, its previous value (or its absence) is recorded in
+// `<app config>/kiosk_journal.json`. Teardown walks the journal backwards and
+// puts back exactly what was there, deleting values that did not exist, so a
+// setup interrupted halfway can still be undone.
+//
+// Applying is idempotent: settings that already hold the wanted value are left
+// alone, and re-applying never replaces a recorded original. Both directions
+// can run as a dry run, which only reports the plan.
+//
+// PCs set up before the journal existed have no record. Their values are reset
+// to the Windows defaults, but only where they still hold what Primus wrote.
+//
+// `status` reads the same settings back as a `KioskStatus`, which the agent
+// reports to the backend so drift across the café shows up in the admin UI.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::{RegData, SettingsBackend, MACHINE_RUN, RUN_VALUE, USER_POLICIES, USER_RUN, WINLOGON};
+
+const JOURNAL_FILE: &str = "kiosk_journal.json";
+
+/// Which part of the kiosk setup a command touches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Winlogon shell only
+    Shell,
+    /// Start with the user session (HKCU Run)
+    AutoBoot,
+    /// Shell, both Run keys and the lockdown policies
+    Complete,
+}
+
+/// A value Primus sets
+struct Setting {
+    key: &'static str,
+    name: &'static str,
+    value: RegData,
+    /// What Windows has without Primus, used when there is no journal entry
+    default: Option<RegData>,
+}
+
+fn settings(backend: &dyn SettingsBackend, scope: Scope, exe: &str) -> Vec<Setting> {
+    let shell = Setting {
+        key: WINLOGON,
+        name: "Shell",
+        value: RegData::Sz(exe.to_string()),
+        // Without a session script Linux display managers start the desktop
+        default: cfg!(target_os = "windows").then(|| RegData::Sz("explorer.exe".to_string())),
+    };
+    let user_run = Setting { key: USER_RUN, name: RUN_VALUE, value: RegData::Sz(exe.to_string()), default: None };
+    let all = match scope {
+        Scope::Shell => vec![shell],
+        Scope::AutoBoot => vec![user_run],
+        Scope::Complete => vec![
+            shell,
+            user_run,
+            Setting { key: MACHINE_RUN, name: RUN_VALUE, value: RegData::Sz(exe.to_string()), default: None },
+            Setting { key: USER_POLICIES, name: "DisableTaskMgr", value: RegData::Dword(1), default: None },
+            Setting { key: USER_POLICIES, name: "DisableRegistryTools", value: RegData::Dword(1), default: None },
+        ],
+    };
+    all.into_iter().filter(|s| backend.supports(s.key, s.name)).collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct JournalEntry {
+    key: String,
+    name: String,
+    /// None: the value did not exist
+    prior: Option<RegData>,
+    applied: RegData,
+    at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// A missing journal is empty; an unreadable one is an error, since
+    /// overwriting it would lose the originals
+    fn load(config_dir: &Path) -> Result<Self, String> {
+        let path = config_dir.join(JOURNAL_FILE);
+        match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| format!("Invalid {}: {}", JOURNAL_FILE, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Cannot read {}: {}", JOURNAL_FILE, e)),
+        }
+    }
+
+    fn save(&self, config_dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+        let path = config_dir.join(JOURNAL_FILE);
+        if self.entries.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            };
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    fn find(&self, key: &str, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.key == key && e.name == name)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Set,
+    Delete,
+    Unchanged,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PlanStep {
+    pub key: String,
+    pub name: String,
+    pub action: Action,
+    pub current: Option<RegData>,
+    /// None: the value is deleted
+    pub target: Option<RegData>,
+}
+
+impl PlanStep {
+    fn new(key: &str, name: &str, current: Option<RegData>, target: Option<RegData>) -> Self {
+        let action = match (&current, &target) {
+            (current, target) if current == target => Action::Unchanged,
+            (_, Some(_)) => Action::Set,
+            (_, None) => Action::Delete,
+        };
+        Self { key: key.to_string(), name: name.to_string(), action, current, target }
+    }
+}
+
+impl fmt::Display for PlanStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Option<RegData>| v.as_ref().map(|d| d.to_string()).unwrap_or_else(|| "(absent)".to_string());
+        match self.action {
+            Action::Unchanged => write!(f, "keep {}\\{} = {}", self.key, self.name, show(&self.current)),
+            Action::Set => write!(f, "set {}\\{}: {} -> {}", self.key, self.name, show(&self.current), show(&self.target)),
+            Action::Delete => write!(f, "delete {}\\{} (was {})", self.key, self.name, show(&self.current)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SetupReport {
+    pub dry_run: bool,
+    pub steps: Vec<PlanStep>,
+    /// Shell changes only take effect after a restart
+    pub restart_required: bool,
+}
+
+impl SetupReport {
+    fn new(dry_run: bool, steps: Vec<PlanStep>) -> Self {
+        let restart_required = steps.iter().any(|s| s.name == "Shell" && s.action != Action::Unchanged);
+        Self { dry_run, steps, restart_required }
+    }
+
+    pub fn changed(&self) -> usize {
+        self.steps.iter().filter(|s| s.action != Action::Unchanged).count()
+    }
+
+    fn log(&self, what: &str) {
+        let prefix = if self.dry_run { "[Kiosk] (dry run) " } else { "[Kiosk] " };
+        log_info!("{}{}: {} of {} settings to change", prefix, what, self.changed(), self.steps.len());
+        for step in &self.steps {
+            log_debug!("{}  {}", prefix, step);
+        }
+    }
+}
+
+/// Path of the running executable, as stored in the settings
+pub fn current_exe() -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to get executable path: {}", e))?;
+    let exe = exe.to_string_lossy();
+    Ok(if cfg!(target_os = "windows") { exe.replace('/', "\\") } else { exe.into_owned() })
+}
+
+/// Set the settings of `scope` to their kiosk values, journaling each change
+pub fn apply(backend: &dyn SettingsBackend, config_dir: &Path, scope: Scope, exe: &str, dry_run: bool) -> Result<SetupReport, String> {
+    let mut journal = Journal::load(config_dir)?;
+    let mut steps = Vec::new();
+    for setting in settings(backend, scope, exe) {
+        let current = backend.read(setting.key, setting.name)?;
+        steps.push(PlanStep::new(setting.key, setting.name, current, Some(setting.value)));
+    }
+
+    if !dry_run {
+        for step in steps.iter().filter(|s| s.action == Action::Set) {
+            let target = step.target.clone().unwrap();
+            // Record first, so a crash between the two steps can be undone.
+            // An existing entry keeps the original value but tracks what
+            // Primus wrote last (e.g. after the executable moved)
+            match journal.find(&step.key, &step.name) {
+                Some(index) => {
+                    let entry = &mut journal.entries[index];
+                    entry.applied = target.clone();
+                    entry.at = Utc::now();
+                }
+                None => journal.entries.push(JournalEntry {
+                    key: step.key.clone(),
+                    name: step.name.clone(),
+                    prior: step.current.clone(),
+                    applied: target.clone(),
+                    at: Utc::now(),
+                }),
+            }
+            journal.save(config_dir)?;
+            backend.write(&step.key, &step.name, &target)?;
+        }
+    }
+
+    let report = SetupReport::new(dry_run, steps);
+    report.log("Kiosk setup");
+    Ok(report)
+}
+
+/// Restore what the journal recorded for `scope` (everything if None)
+pub fn teardown(backend: &dyn SettingsBackend, config_dir: &Path, scope: Option<Scope>, exe: &str, dry_run: bool) -> Result<SetupReport, String> {
+    let mut journal = Journal::load(config_dir)?;
+    let in_scope = settings(backend, scope.unwrap_or(Scope::Complete), exe);
+    let covered = |key: &str, name: &str| scope.is_none() || in_scope.iter().any(|s| s.key == key && s.name == name);
+
+    // Newest change first
+    let mut steps = Vec::new();
+    for entry in journal.entries.iter().rev().filter(|e| covered(&e.key, &e.name)) {
+        let current = backend.read(&entry.key, &entry.name)?;
+        if current.as_ref() != Some(&entry.applied) {
+            log_warn!("[Kiosk] {}\\{} was changed after setup; restoring the original anyway", entry.key, entry.name);
+        }
+        steps.push(PlanStep::new(&entry.key, &entry.name, current, entry.prior.clone()));
+    }
+    // Settings from before the journal, still holding the Primus value
+    for setting in in_scope.iter().filter(|s| journal.find(s.key, s.name).is_none()) {
+        let current = backend.read(setting.key, setting.name)?;
+        if current.as_ref() == Some(&setting.value) {
+            steps.push(PlanStep::new(setting.key, setting.name, current, setting.default.clone()));
+        }
+    }
+
+    if !dry_run {
+        for step in &steps {
+            match (&step.action, &step.target) {
+                (Action::Set, Some(target)) => backend.write(&step.key, &step.name, target)?,
+                (Action::Delete, _) => backend.delete(&step.key, &step.name)?,
+                _ => {}
+            }
+            // Drop each entry as soon as it is restored
+            if let Some(index) = journal.find(&step.key, &step.name) {
+                journal.entries.remove(index);
+                journal.save(config_dir)?;
+            }
+        }
+    }
+
+    let report = SetupReport::new(dry_run, steps);
+    report.log("Kiosk teardown");
+    Ok(report)
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AutoBootEntry {
+    pub key: String,
+    pub command: String,
+    /// Points at this Primus executable
+    pub is_primus: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PolicyFlags {
+    pub task_manager_disabled: bool,
+    pub registry_tools_disabled: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct KioskStatus {
+    /// Winlogon shell, None if unset (Windows then uses explorer.exe)
+    pub shell: Option<String>,
+    pub shell_is_primus: bool,
+    /// Primus entries in the HKCU and HKLM Run keys
+    pub auto_boot: Vec<AutoBootEntry>,
+    pub policies: PolicyFlags,
+    /// Shortcut blocker running (keyboard hook / X11 grabs)
+    pub hook_installed: bool,
+    pub elevated: bool,
+    /// The OS wants a restart, or the configured shell is not the one running
+    pub pending_reboot: bool,
+    /// Changes recorded by setup and not torn down yet
+    pub journal_entries: usize,
+    pub exe_path: String,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Read back the kiosk settings of this PC
+pub fn status(backend: &dyn SettingsBackend, config_dir: &Path, exe: &str, hook_installed: bool) -> Result<KioskStatus, String> {
+    let is_primus = |value: &str| value.trim_matches('"').eq_ignore_ascii_case(exe);
+
+    let shell = match backend.read(WINLOGON, "Shell")? {
+        Some(RegData::Sz(shell)) => Some(shell),
+        _ => None,
+    };
+    let shell_is_primus = shell.as_deref().map(is_primus).unwrap_or(false);
+
+    let mut auto_boot = Vec::new();
+    for key in [USER_RUN, MACHINE_RUN] {
+        if let Some(RegData::Sz(command)) = backend.read(key, RUN_VALUE)? {
+            auto_boot.push(AutoBootEntry { key: key.to_string(), is_primus: is_primus(&command), command });
+        }
+    }
+
+    let flag = |name: &str| -> Result<bool, String> { Ok(matches!(backend.read(USER_POLICIES, name)?, Some(RegData::Dword(n)) if n != 0)) };
+    let policies = PolicyFlags {
+        task_manager_disabled: flag("DisableTaskMgr")?,
+        registry_tools_disabled: flag("DisableRegistryTools")?,
+    };
+
+    let pending_reboot = backend.restart_pending(shell_is_primus)?;
+
+    Ok(KioskStatus {
+        shell,
+        shell_is_primus,
+        auto_boot,
+        policies,
+        hook_installed,
+        elevated: backend.is_elevated(),
+        pending_reboot,
+        journal_entries: Journal::load(config_dir).map(|j| j.entries.len()).unwrap_or(0),
+        exe_path: exe.to_string(),
+        checked_at: Utc::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::MemorySettings;
+
+    const EXE: &str = "/opt/primus/primus-client";
+
+    fn temp_dir() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-kiosk-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn sz(value: &str) -> Option<RegData> {
+        Some(RegData::Sz(value.to_string()))
+    }
+
+    /// A PC with a stock shell and an unrelated HKCU Run entry elsewhere
+    fn stock_backend() -> MemorySettings {
+        let backend = MemorySettings::default();
+        backend.write(WINLOGON, "Shell", &RegData::Sz("explorer.exe".to_string())).unwrap();
+        backend.write(USER_RUN, "OneDrive", &RegData::Sz("onedrive.exe".to_string())).unwrap();
+        backend
+    }
+
+    fn snapshot(backend: &MemorySettings) -> Vec<Option<RegData>> {
+        [
+            (WINLOGON, "Shell"),
+            (USER_RUN, RUN_VALUE),
+            (USER_RUN, "OneDrive"),
+            (MACHINE_RUN, RUN_VALUE),
+            (USER_POLICIES, "DisableTaskMgr"),
+            (USER_POLICIES, "DisableRegistryTools"),
+        ]
+        .iter()
+        .map(|(key, name)| backend.read(key, name).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn status_reads_shell_run_keys_and_policy_flags() {
+        let dir = temp_dir();
+        let backend = stock_backend();
+        backend.write(WINLOGON, "Shell", &RegData::Sz(format!("\"{}\"", EXE.to_uppercase()))).unwrap();
+        backend.write(USER_RUN, RUN_VALUE, &RegData::Sz(format!("\"{}\"", EXE))).unwrap();
+        backend.write(MACHINE_RUN, RUN_VALUE, &RegData::Sz("/opt/other/client".to_string())).unwrap();
+        backend.write(USER_POLICIES, "DisableTaskMgr", &RegData::Dword(1)).unwrap();
+        backend.write(USER_POLICIES, "DisableRegistryTools", &RegData::Dword(0)).unwrap();
+
+        let status = status(&backend, &dir, EXE, true).unwrap();
+        assert!(status.shell_is_primus);
+        assert_eq!(status.shell, Some(format!("\"{}\"", EXE.to_uppercase())));
+        assert_eq!(status.auto_boot.len(), 2);
+        assert_eq!((status.auto_boot[0].key.as_str(), status.auto_boot[0].is_primus), (USER_RUN, true));
+        assert_eq!((status.auto_boot[1].key.as_str(), status.auto_boot[1].is_primus), (MACHINE_RUN, false));
+        assert_eq!(status.auto_boot[1].command, "/opt/other/client");
+        assert!(status.policies.task_manager_disabled);
+        assert!(!status.policies.registry_tools_disabled);
+        assert!(status.hook_installed);
+        assert_eq!(status.journal_entries, 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn status_of_a_stock_pc() {
+        let dir = temp_dir();
+        let status = status(&stock_backend(), &dir, EXE, false).unwrap();
+        assert_eq!(status.shell.as_deref(), Some("explorer.exe"));
+        assert!(!status.shell_is_primus);
+        assert!(status.auto_boot.is_empty());
+        assert!(!status.policies.task_manager_disabled);
+        assert!(!status.policies.registry_tools_disabled);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn apply_twice_is_idempotent() {
+        let dir = temp_dir();
+        let backend = stock_backend();
+
+        let first = apply(&backend, &dir, Scope::Complete, EXE, false).unwrap();
+        assert_eq!(first.changed(), 5);
+        assert!(first.restart_required);
+        assert_eq!(backend.read(WINLOGON, "Shell").unwrap(), sz(EXE));
+        assert_eq!(backend.read(USER_POLICIES, "DisableTaskMgr").unwrap(), Some(RegData::Dword(1)));
+
+        let second = apply(&backend, &dir, Scope::Complete, EXE, false).unwrap();
+        assert_eq!(second.changed(), 0);
+        assert!(!second.restart_required);
+
+        let journal = Journal::load(&dir).unwrap();
+        assert_eq!(journal.entries.len(), 5);
+        let shell = &journal.entries[journal.find(WINLOGON, "Shell").unwrap()];
+        assert_eq!(shell.prior, sz("explorer.exe"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reapply_keeps_the_original_and_tracks_the_new_value() {
+        let dir = temp_dir();
+        let backend = stock_backend();
+        apply(&backend, &dir, Scope::Shell, EXE, false).unwrap();
+        apply(&backend, &dir, Scope::Shell, "/usr/local/bin/primus-client", false).unwrap();
+
+        let journal = Journal::load(&dir).unwrap();
+        assert_eq!(journal.entries.len(), 1);
+        assert_eq!(journal.entries[0].prior, sz("explorer.exe"));
+        assert_eq!(Some(journal.entries[0].applied.clone()), sz("/usr/local/bin/primus-client"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn teardown_restores_priors_and_deletes_added_values() {
+        let dir = temp_dir();
+        let backend = stock_backend();
+        let before = snapshot(&backend);
+
+        apply(&backend, &dir, Scope::Complete, EXE, false).unwrap();
+        let report = teardown(&backend, &dir, None, EXE, false).unwrap();
+        assert_eq!(report.changed(), 5);
+        assert!(report.steps.iter().any(|s| s.name == RUN_VALUE && s.action == Action::Delete));
+
+        assert_eq!(snapshot(&backend), before);
+        assert_eq!(backend.read(USER_RUN, RUN_VALUE).unwrap(), None);
+        assert!(!dir.join(JOURNAL_FILE).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn scoped_teardown_leaves_other_settings_alone() {
+        let dir = temp_dir();
+        let backend = stock_backend();
+        apply(&backend, &dir, Scope::Complete, EXE, false).unwrap();
+
+        teardown(&backend, &dir, Some(Scope::AutoBoot), EXE, false).unwrap();
+        assert_eq!(backend.read(USER_RUN, RUN_VALUE).unwrap(), None);
+        assert_eq!(backend.read(WINLOGON, "Shell").unwrap(), sz(EXE));
+        assert_eq!(Journal::load(&dir).unwrap().entries.len(), 4);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn legacy_teardown_only_resets_values_primus_still_owns() {
+        let dir = temp_dir();
+        let backend = MemorySettings::default();
+        // Set up by an older client: no journal
+        backend.write(WINLOGON, "Shell", &RegData::Sz(EXE.to_string())).unwrap();
+        backend.write(MACHINE_RUN, RUN_VALUE, &RegData::Sz(EXE.to_string())).unwrap();
+        // Changed by someone else since
+        backend.write(USER_RUN, RUN_VALUE, &RegData::Sz("/opt/other/launcher".to_string())).unwrap();
+
+        let report = teardown(&backend, &dir, None, EXE, false).unwrap();
+        assert_eq!(report.changed(), 2);
+        let default_shell = if cfg!(target_os = "windows") { sz("explorer.exe") } else { None };
+        assert_eq!(backend.read(WINLOGON, "Shell").unwrap(), default_shell);
+        assert_eq!(backend.read(MACHINE_RUN, RUN_VALUE).unwrap(), None);
+        assert_eq!(backend.read(USER_RUN, RUN_VALUE).unwrap(), sz("/opt/other/launcher"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn dry_run_writes_nothing() {
+        let dir = temp_dir();
+        let backend = stock_backend();
+        let before = snapshot(&backend);
+
+        let plan = apply(&backend, &dir, Scope::Complete, EXE, true).unwrap();
+        assert!(plan.dry_run);
+        assert_eq!(plan.changed(), 5);
+        assert_eq!(snapshot(&backend), before);
+        assert!(!dir.join(JOURNAL_FILE).exists());
+
+        apply(&backend, &dir, Scope::Complete, EXE, false).unwrap();
+        let applied = snapshot(&backend);
+        let plan = teardown(&backend, &dir, None, EXE, true).unwrap();
+        assert_eq!(plan.changed(), 5);
+        assert_eq!(snapshot(&backend), applied);
+        assert_eq!(Journal::load(&dir).unwrap().entries.len(), 5);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
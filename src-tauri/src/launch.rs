@@ -0,0 +1,350 @@
+// Per-game launch profiles.
+//
+// Stored in the client config under `launch_profiles`, keyed by game id
+// (`steam:730`) or executable path. A profile sets the arguments, environment,
+// working directory, priority class, CPU affinity and optional hooks that run
+// before launch and after the game's session ends.
+//
+// Nothing here goes through a shell, but arguments and environment values are
+// still rejected if they contain shell metacharacters: some games are started
+// through `.bat`/`.cmd` wrappers that cmd.exe re-parses. Variables that steer
+// the loader (`LD_PRELOAD`, `PATH`, `DYLD_*`, ...) can't be set at all.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+const SHELL_METACHARACTERS: &[char] = &['&', '|', ';', '<', '>', '`', '$', '(', ')', '"', '\'', '%', '!', '^', '\n', '\r', '\0'];
+const MAX_HOOK_TIMEOUT_SECS: u64 = 300;
+/// Variables that control which code the loader runs; setting them would
+/// let a profile inject libraries into an allow-listed game
+const DENIED_ENV_VARS: &[&str] = &["GCONV_PATH", "PATH", "PATHEXT", "COMSPEC"];
+const DENIED_ENV_PREFIXES: &[&str] = &["LD_", "DYLD_"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Idle,
+    BelowNormal,
+    #[default]
+    Normal,
+    AboveNormal,
+    High,
+}
+
+impl Priority {
+    #[cfg(target_os = "windows")]
+    fn creation_flag(self) -> u32 {
+        match self {
+            Priority::Idle => 0x0000_0040,        // IDLE_PRIORITY_CLASS
+            Priority::BelowNormal => 0x0000_4000, // BELOW_NORMAL_PRIORITY_CLASS
+            Priority::Normal => 0x0000_0020,      // NORMAL_PRIORITY_CLASS
+            Priority::AboveNormal => 0x0000_8000, // ABOVE_NORMAL_PRIORITY_CLASS
+            Priority::High => 0x0000_0080,        // HIGH_PRIORITY_CLASS
+        }
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    fn nice(self) -> i32 {
+        match self {
+            Priority::Idle => 19,
+            Priority::BelowNormal => 10,
+            Priority::Normal => 0,
+            Priority::AboveNormal => -5,
+            Priority::High => -10,
+        }
+    }
+}
+
+/// A program run before launch or after exit, without a shell
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Hook {
+    /// Absolute path of the program to run
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_hook_timeout() -> u64 {
+    30
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct LaunchProfile {
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// Defaults to the executable's folder
+    pub working_dir: Option<String>,
+    pub priority: Priority,
+    /// Logical CPU indices to pin the game to (empty = all)
+    pub cpu_affinity: Vec<usize>,
+    pub pre_launch: Option<Hook>,
+    pub post_exit: Option<Hook>,
+}
+
+fn check_value(what: &str, value: &str) -> Result<(), String> {
+    if let Some(c) = value.chars().find(|c| SHELL_METACHARACTERS.contains(c)) {
+        return Err(format!("{} contains forbidden character {:?}: {}", what, c, value));
+    }
+    Ok(())
+}
+
+fn check_absolute(what: &str, path: &str) -> Result<(), String> {
+    if !Path::new(path).is_absolute() {
+        return Err(format!("{} must be an absolute path: {}", what, path));
+    }
+    if path.chars().any(|c| c.is_control()) {
+        return Err(format!("{} contains control characters", what));
+    }
+    Ok(())
+}
+
+impl Hook {
+    fn validate(&self, what: &str) -> Result<(), String> {
+        check_absolute(&format!("{} program", what), &self.program)?;
+        for arg in &self.args {
+            check_value(&format!("{} argument", what), arg)?;
+        }
+        if self.timeout_secs == 0 || self.timeout_secs > MAX_HOOK_TIMEOUT_SECS {
+            return Err(format!("{} timeout must be between 1 and {} seconds", what, MAX_HOOK_TIMEOUT_SECS));
+        }
+        Ok(())
+    }
+
+    /// Run to completion, killing it after the timeout
+    pub fn run(&self, what: &str) -> Result<(), String> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .spawn()
+            .map_err(|e| format!("Failed to run {} hook {}: {}", what, self.program, e))?;
+        let deadline = Instant::now() + Duration::from_secs(self.timeout_secs);
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => return Ok(()),
+                Ok(Some(status)) => return Err(format!("{} hook exited with {}", what, status)),
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("{} hook timed out after {}s", what, self.timeout_secs));
+                }
+                Ok(None) => std::thread::sleep(Duration::from_millis(100)),
+                Err(e) => return Err(format!("Failed to wait for {} hook: {}", what, e)),
+            }
+        }
+    }
+}
+
+impl LaunchProfile {
+    pub fn validate(&self) -> Result<(), String> {
+        for arg in &self.args {
+            check_value("Launch argument", arg)?;
+        }
+        for (name, value) in &self.env {
+            let valid_name = name.chars().next().map(|c| c.is_ascii_alphabetic() || c == '_').unwrap_or(false)
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid_name {
+                return Err(format!("Invalid environment variable name: {}", name));
+            }
+            let upper = name.to_ascii_uppercase();
+            if DENIED_ENV_VARS.contains(&upper.as_str()) || DENIED_ENV_PREFIXES.iter().any(|p| upper.starts_with(p)) {
+                return Err(format!("Environment variable {} can't be set by a launch profile", name));
+            }
+            check_value(&format!("Environment variable {}", name), value)?;
+        }
+        if let Some(dir) = &self.working_dir {
+            check_absolute("Working directory", dir)?;
+            check_value("Working directory", dir)?;
+        }
+        let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1).min(64);
+        if let Some(cpu) = self.cpu_affinity.iter().find(|&&cpu| cpu >= cpus) {
+            return Err(format!("CPU {} does not exist (this PC has {} logical CPUs)", cpu, cpus));
+        }
+        if let Some(hook) = &self.pre_launch {
+            hook.validate("Pre-launch")?;
+        }
+        if let Some(hook) = &self.post_exit {
+            hook.validate("Post-exit")?;
+        }
+        Ok(())
+    }
+
+    /// Build the command for `exe_path` with this profile applied
+    pub fn command(&self, exe_path: &Path) -> Command {
+        let mut cmd = Command::new(exe_path);
+        cmd.args(&self.args).envs(&self.env);
+
+        match &self.working_dir {
+            Some(dir) => {
+                cmd.current_dir(dir);
+            }
+            None => {
+                if let Some(parent) = exe_path.parent() {
+                    cmd.current_dir(parent);
+                }
+            }
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            use std::os::windows::process::CommandExt;
+            // CREATE_NEW_PROCESS_GROUP | CREATE_NEW_CONSOLE | DETACHED_PROCESS
+            // 0x00000200 | 0x00000010 | 0x00000008, plus the priority class
+            cmd.creation_flags(0x00000200 | 0x00000010 | 0x00000008 | self.priority.creation_flag());
+        }
+
+        cmd
+    }
+
+    /// Settings that can only be applied once the process exists. Failures
+    /// are logged, not fatal: the game is already running.
+    pub fn apply_to_process(&self, pid: u32) {
+        #[cfg(not(target_os = "windows"))]
+        if self.priority != Priority::Normal {
+            let status = Command::new("renice")
+                .args(&["-n", &self.priority.nice().to_string(), "-p", &pid.to_string()])
+                .output();
+            if !matches!(status, Ok(ref o) if o.status.success()) {
+                log_warn!("[Launch] Failed to set priority {:?} for PID {}", self.priority, pid);
+            }
+        }
+
+        if !self.cpu_affinity.is_empty() {
+            if let Err(e) = set_affinity(pid, &self.cpu_affinity) {
+                log_warn!("[Launch] Failed to set CPU affinity for PID {}: {}", pid, e);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn set_affinity(pid: u32, cpus: &[usize]) -> Result<(), String> {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
+    use winapi::um::winbase::SetProcessAffinityMask;
+    use winapi::um::winnt::{PROCESS_QUERY_INFORMATION, PROCESS_SET_INFORMATION};
+
+    let mask = cpus.iter().fold(0usize, |mask, cpu| mask | (1usize << cpu));
+    unsafe {
+        let handle = OpenProcess(PROCESS_SET_INFORMATION | PROCESS_QUERY_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        let ok = SetProcessAffinityMask(handle, mask);
+        let err = std::io::Error::last_os_error();
+        CloseHandle(handle);
+        if ok == 0 {
+            return Err(err.to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn set_affinity(pid: u32, cpus: &[usize]) -> Result<(), String> {
+    let list = cpus.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+    let output = Command::new("taskset")
+        .args(&["-a", "-p", "-c", &list, &pid.to_string()])
+        .output()
+        .map_err(|e| format!("Failed to run taskset: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(())
+}
+
+/// Stored profile for a game, looked up by id first and then by exe path
+pub fn profile_for(game_id: Option<&str>, exe_path: &str) -> LaunchProfile {
+    let profiles = crate::config::current().launch_profiles;
+    game_id
+        .and_then(|id| profiles.get(id))
+        .or_else(|| profiles.get(exe_path))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Store (or with `None`, remove) the launch profile for a game id or exe path
+#[tauri::command]
+pub async fn set_launch_profile(
+    app_handle: tauri::AppHandle,
+    key: String,
+    profile: Option<LaunchProfile>,
+) -> Result<crate::config::ClientConfig, String> {
+    if key.trim().is_empty() {
+        return Err("Launch profile key is required".to_string());
+    }
+    let cfg = crate::config::modify(|cfg| {
+        match profile {
+            Some(profile) => {
+                cfg.launch_profiles.insert(key, profile);
+            }
+            None => {
+                cfg.launch_profiles.remove(&key);
+            }
+        }
+        Ok(())
+    })?;
+    crate::config::emit_changed(&app_handle, &cfg);
+    Ok(cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_env(name: &str) -> LaunchProfile {
+        let mut profile = LaunchProfile::default();
+        profile.env.insert(name.to_string(), "/tmp/x".to_string());
+        profile
+    }
+
+    #[test]
+    fn rejects_loader_and_search_path_variables() {
+        for name in ["LD_PRELOAD", "LD_LIBRARY_PATH", "LD_BIND_NOW", "ld_debug", "GCONV_PATH", "PATH", "Path", "DYLD_INSERT_LIBRARIES"] {
+            assert!(with_env(name).validate().is_err(), "{} should be rejected", name);
+        }
+    }
+
+    #[test]
+    fn accepts_ordinary_variables() {
+        assert!(with_env("DXVK_HUD").validate().is_ok());
+        assert!(with_env("MY_PATH").validate().is_ok());
+        assert!(with_env("OLD_LD").validate().is_ok());
+    }
+
+    fn hook(args: Vec<String>) -> Hook {
+        Hook { program: "/usr/bin/true".to_string(), args, timeout_secs: 5 }
+    }
+
+    #[test]
+    fn rejects_shell_metacharacters_everywhere() {
+        for c in SHELL_METACHARACTERS {
+            let value = format!("a{}b", c);
+            let cases = [
+                LaunchProfile { args: vec![value.clone()], ..LaunchProfile::default() },
+                LaunchProfile { working_dir: Some(format!("/games/{}", value)), ..LaunchProfile::default() },
+                LaunchProfile { pre_launch: Some(hook(vec![value.clone()])), ..LaunchProfile::default() },
+                LaunchProfile { post_exit: Some(hook(vec![value.clone()])), ..LaunchProfile::default() },
+            ];
+            for profile in cases {
+                assert!(profile.validate().is_err(), "{:?} should be rejected in {:?}", c, profile);
+            }
+        }
+    }
+
+    #[test]
+    fn accepts_ordinary_arguments() {
+        let profile = LaunchProfile {
+            args: vec!["-dx11".to_string(), "+fps_max=240".to_string(), "-novid".to_string()],
+            working_dir: Some("/games/cs2".to_string()),
+            pre_launch: Some(hook(vec!["--quiet".to_string()])),
+            ..LaunchProfile::default()
+        };
+        assert_eq!(profile.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,801 @@
+// Installed game library scanner.
+//
+// Reads what the launchers themselves record instead of guessing executables:
+// - Steam: `libraryfolders.vdf` + `appmanifest_*.acf` (Windows and Linux)
+// - Epic: `.item` manifests under ProgramData
+// - GOG, Ubisoft Connect, EA: their registry keys
+// - Linux: Lutris (`lutris -lo --json`) and Heroic (legendary/GOG installed.json)
+//
+// Each game gets a stable `source:id` identifier. `exe_path` is what
+// `launch_game` runs: either an executable or a launcher URI such as
+// `steam://rungameid/730`, so the launcher handles updates and DRM.
+
+#[cfg(target_os = "windows")]
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// URI schemes `launch_game` hands to the OS instead of executing a file
+const LAUNCH_URI_SCHEMES: &[&str] = &[
+    "steam://",
+    "com.epicgames.launcher://",
+    "uplay://",
+    "goggalaxy://",
+    "origin2://",
+    "heroic://",
+    "lutris:",
+];
+
+// Steam "apps" that are runtimes/redistributables, not games
+const STEAM_TOOL_APPIDS: &[&str] = &["228980", "1070560", "1391110", "1628350", "1493710"];
+const STEAM_TOOL_PREFIXES: &[&str] = &["Proton", "Steam Linux Runtime", "Steamworks Common"];
+
+/// How long `is_detected_uri` trusts the last scan before rescanning
+const SCAN_CACHE_TTL: Duration = Duration::from_secs(300);
+
+struct CachedScan {
+    at: Instant,
+    extra_paths: Vec<PathBuf>,
+    games: Vec<GameInfo>,
+}
+
+lazy_static::lazy_static! {
+    static ref LAST_SCAN: Mutex<Option<CachedScan>> = Mutex::new(None);
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GameInfo {
+    /// `<source>:<launcher id>`, e.g. `steam:730`
+    pub id: String,
+    pub name: String,
+    pub source: String,
+    /// Executable or launcher URI passed to `launch_game`
+    pub exe_path: String,
+    pub install_path: String,
+    /// Cached PNG, for `convertFileSrc`
+    pub icon_path: Option<String>,
+    pub is_running: bool,
+}
+
+impl GameInfo {
+    fn new(source: &str, id: &str, name: &str, exe_path: String, install_path: &Path) -> Self {
+        Self {
+            id: format!("{}:{}", source, id),
+            name: name.trim().to_string(),
+            source: source.to_string(),
+            exe_path,
+            install_path: install_path.to_string_lossy().to_string(),
+            icon_path: None,
+            is_running: false,
+        }
+    }
+}
+
+/// True for URIs `launch_game` should open through the registered launcher
+pub fn is_launch_uri(target: &str) -> bool {
+    LAUNCH_URI_SCHEMES.iter().any(|scheme| target.starts_with(scheme))
+}
+
+/// Open a launcher URI without going through a shell
+pub fn open_launch_uri(uri: &str) -> Result<(), String> {
+    if !is_launch_uri(uri) {
+        return Err(format!("Unsupported launch URI: {}", uri));
+    }
+    if uri.chars().any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '\'') {
+        return Err("Launch URI contains invalid characters".to_string());
+    }
+
+    #[cfg(target_os = "windows")]
+    let mut cmd = Command::new("explorer.exe");
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = Command::new("xdg-open");
+
+    cmd.arg(uri)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {}", uri, e))
+}
+
+// ---------------------------------------------------------------------------
+// Valve KeyValues (VDF/ACF) parsing
+
+#[derive(Debug, Clone)]
+enum Vdf {
+    Str(String),
+    Map(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Map(entries) => entries.iter().find(|(k, _)| k.eq_ignore_ascii_case(key)).map(|(_, v)| v),
+            Vdf::Str(_) => None,
+        }
+    }
+
+    fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(Vdf::Str(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Map(entries) => entries,
+            Vdf::Str(_) => &[],
+        }
+    }
+}
+
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some('n') => s.push('\n'),
+                            Some('t') => s.push('\t'),
+                            Some(other) => s.push(other),
+                            None => break,
+                        },
+                        '"' => break,
+                        c => s.push(c),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut s = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '{' || next == '}' || next == '"' {
+                        break;
+                    }
+                    s.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Str(s));
+            }
+        }
+    }
+    tokens
+}
+
+fn parse_entries(tokens: &mut std::vec::IntoIter<Token>) -> Vec<(String, Vdf)> {
+    let mut entries = Vec::new();
+    loop {
+        match tokens.next() {
+            Some(Token::Str(key)) => match tokens.next() {
+                Some(Token::Str(value)) => entries.push((key, Vdf::Str(value))),
+                Some(Token::Open) => entries.push((key, Vdf::Map(parse_entries(tokens)))),
+                _ => break,
+            },
+            Some(Token::Open) => {
+                // Anonymous block, not used by Steam files
+                parse_entries(tokens);
+            }
+            Some(Token::Close) | None => break,
+        }
+    }
+    entries
+}
+
+fn parse_vdf(text: &str) -> Vdf {
+    Vdf::Map(parse_entries(&mut tokenize(text).into_iter()))
+}
+
+fn read_vdf(path: &Path) -> Option<Vdf> {
+    fs::read_to_string(path).ok().map(|text| parse_vdf(&text))
+}
+
+// ---------------------------------------------------------------------------
+// Steam
+
+fn steam_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(path) = reg_value("HKCU\\Software\\Valve\\Steam", "SteamPath") {
+            roots.push(PathBuf::from(path.replace('/', "\\")));
+        }
+        roots.push(PathBuf::from("C:\\Program Files (x86)\\Steam"));
+        roots.push(PathBuf::from("C:\\Program Files\\Steam"));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    if let Some(home) = dirs::home_dir() {
+        roots.push(home.join(".steam/steam"));
+        roots.push(home.join(".local/share/Steam"));
+        roots.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+    }
+
+    roots
+}
+
+/// Library folders listed by a Steam install, including the install itself
+fn steam_libraries(root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![root.to_path_buf()];
+    if let Some(vdf) = read_vdf(&root.join("steamapps").join("libraryfolders.vdf")) {
+        let folders = vdf.get("libraryfolders");
+        for (key, value) in folders.map(|f| f.entries()).unwrap_or(&[]) {
+            // New format: "0" { "path" "..." }, old format: "1" "D:\\SteamLibrary"
+            let path = match value {
+                Vdf::Map(_) => value.str("path").map(String::from),
+                Vdf::Str(s) if key.chars().all(|c| c.is_ascii_digit()) => Some(s.clone()),
+                Vdf::Str(_) => None,
+            };
+            if let Some(path) = path {
+                libraries.push(PathBuf::from(path));
+            }
+        }
+    }
+    libraries
+}
+
+/// Games installed in one Steam library folder
+fn scan_steam_library(library: &Path, games: &mut Vec<GameInfo>) {
+    let steamapps = library.join("steamapps");
+    let entries = match fs::read_dir(&steamapps) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with("appmanifest_") || !file_name.ends_with(".acf") {
+            continue;
+        }
+        let manifest = match read_vdf(&entry.path()) {
+            Some(vdf) => vdf,
+            None => continue,
+        };
+        let state = match manifest.get("AppState") {
+            Some(state) => state,
+            None => continue,
+        };
+        let (appid, name, installdir) = match (state.str("appid"), state.str("name"), state.str("installdir")) {
+            (Some(a), Some(n), Some(i)) => (a, n, i),
+            _ => continue,
+        };
+        if STEAM_TOOL_APPIDS.contains(&appid) || STEAM_TOOL_PREFIXES.iter().any(|p| name.starts_with(p)) {
+            continue;
+        }
+        // StateFlags bit 4 = fully installed
+        let installed = state.str("StateFlags").and_then(|f| f.parse::<u32>().ok()).map(|f| f & 4 != 0).unwrap_or(true);
+        if !installed {
+            continue;
+        }
+        games.push(GameInfo::new(
+            "steam",
+            appid,
+            name,
+            format!("steam://rungameid/{}", appid),
+            &steamapps.join("common").join(installdir),
+        ));
+    }
+}
+
+fn scan_steam(extra_paths: &[PathBuf], games: &mut Vec<GameInfo>) {
+    let mut libraries: Vec<PathBuf> = steam_roots().iter().flat_map(|root| steam_libraries(root)).collect();
+    libraries.extend(extra_paths.iter().filter(|p| p.join("steamapps").is_dir()).cloned());
+
+    // The same library is often reachable via several roots/symlinks
+    let mut seen = Vec::new();
+    for library in libraries {
+        let canonical = fs::canonicalize(&library).unwrap_or(library);
+        if seen.contains(&canonical) {
+            continue;
+        }
+        scan_steam_library(&canonical, games);
+        seen.push(canonical);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Epic Games Store
+
+fn scan_epic_manifests(dir: &Path, games: &mut Vec<GameInfo>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        if entry.path().extension().and_then(|e| e.to_str()) != Some("item") {
+            continue;
+        }
+        let item: serde_json::Value = match fs::read_to_string(entry.path()).ok().and_then(|d| serde_json::from_str(&d).ok()) {
+            Some(item) => item,
+            None => continue,
+        };
+        let field = |key: &str| item.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        if item.get("bIsIncompleteInstall").and_then(|v| v.as_bool()).unwrap_or(false) {
+            continue;
+        }
+        let is_game = item.get("AppCategories")
+            .and_then(|v| v.as_array())
+            .map(|cats| cats.iter().any(|c| c.as_str() == Some("games")))
+            .unwrap_or(true);
+        let app_name = field("AppName");
+        if !is_game || app_name.is_empty() {
+            continue;
+        }
+        let uri = format!(
+            "com.epicgames.launcher://apps/{}%3A{}%3A{}?action=launch&silent=true",
+            field("CatalogNamespace"),
+            field("CatalogItemId"),
+            app_name
+        );
+        games.push(GameInfo::new("epic", &app_name, &field("DisplayName"), uri, Path::new(&field("InstallLocation"))));
+    }
+}
+
+fn epic_manifest_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    {
+        let program_data = std::env::var("PROGRAMDATA").unwrap_or_else(|_| "C:\\ProgramData".to_string());
+        Some(PathBuf::from(program_data).join("Epic\\EpicGamesLauncher\\Data\\Manifests"))
+    }
+    #[cfg(not(target_os = "windows"))]
+    None
+}
+
+// ---------------------------------------------------------------------------
+// Windows registry based launchers
+
+/// `reg query <key> /s`, as (key path, lowercase value name -> data)
+#[cfg(target_os = "windows")]
+fn reg_query_tree(key: &str) -> Vec<(String, HashMap<String, String>)> {
+    let output = match Command::new("reg").args(&["query", key, "/s"]).output() {
+        Ok(o) if o.status.success() => o,
+        _ => return Vec::new(),
+    };
+    let mut keys: Vec<(String, HashMap<String, String>)> = Vec::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if line.starts_with("HKEY_") {
+            keys.push((line.trim().to_string(), HashMap::new()));
+        } else if line.starts_with("    ") {
+            let parts: Vec<&str> = line.trim_start().splitn(3, "    ").collect();
+            if let (Some((_, values)), [name, _kind, data]) = (keys.last_mut(), parts.as_slice()) {
+                values.insert(name.to_lowercase(), data.trim().to_string());
+            }
+        }
+    }
+    keys
+}
+
+#[cfg(target_os = "windows")]
+fn reg_value(key: &str, name: &str) -> Option<String> {
+    let output = Command::new("reg").args(&["query", key, "/v", name]).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .find(|l| l.trim_start().to_lowercase().starts_with(&name.to_lowercase()))
+        .and_then(|l| l.trim_start().splitn(3, "    ").nth(2))
+        .map(|v| v.trim().to_string())
+}
+
+#[cfg(target_os = "windows")]
+fn last_segment(key: &str) -> &str {
+    key.rsplit('\\').next().unwrap_or(key)
+}
+
+#[cfg(target_os = "windows")]
+fn scan_gog(games: &mut Vec<GameInfo>) {
+    for root in ["HKLM\\SOFTWARE\\WOW6432Node\\GOG.com\\Games", "HKLM\\SOFTWARE\\GOG.com\\Games"] {
+        for (_, values) in reg_query_tree(root) {
+            let (Some(id), Some(name), Some(exe)) = (values.get("gameid"), values.get("gamename"), values.get("exe")) else {
+                continue;
+            };
+            if !Path::new(exe).exists() {
+                continue;
+            }
+            let install = values.get("path").cloned().unwrap_or_default();
+            games.push(GameInfo::new("gog", id, name, exe.clone(), Path::new(&install)));
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn scan_ubisoft(games: &mut Vec<GameInfo>) {
+    let installs = "HKLM\\SOFTWARE\\WOW6432Node\\Ubisoft\\Launcher\\Installs";
+    for (key, values) in reg_query_tree(installs) {
+        let id = last_segment(&key);
+        let Some(dir) = values.get("installdir") else { continue };
+        let dir = PathBuf::from(dir.replace('/', "\\"));
+        if !dir.is_dir() {
+            continue;
+        }
+        let name = reg_value(
+            &format!("HKLM\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Uplay Install {}", id),
+            "DisplayName",
+        )
+        .or_else(|| dir.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| id.to_string());
+        games.push(GameInfo::new("ubisoft", id, &name, format!("uplay://launch/{}/0", id), &dir));
+    }
+}
+
+/// Game executable from an EA install's `__Installer\installerdata.xml`
+#[cfg(target_os = "windows")]
+fn ea_game_exe(install_dir: &Path) -> Option<PathBuf> {
+    let xml = fs::read_to_string(install_dir.join("__Installer").join("installerdata.xml")).ok()?;
+    let runtime = &xml[xml.find("<runtime>")?..];
+    let start = runtime.find("<filePath>")? + "<filePath>".len();
+    let end = start + runtime[start..].find("</filePath>")?;
+    // e.g. `[HKEY_LOCAL_MACHINE\SOFTWARE\...\Install Dir]bin\game.exe`
+    let relative = runtime[start..end].rsplit(']').next()?.trim();
+    let exe = install_dir.join(relative);
+    exe.exists().then_some(exe)
+}
+
+#[cfg(target_os = "windows")]
+fn scan_ea(games: &mut Vec<GameInfo>) {
+    for (key, values) in reg_query_tree("HKLM\\SOFTWARE\\WOW6432Node\\Electronic Arts") {
+        let name = last_segment(&key);
+        if name.starts_with("EA Desktop") || name.starts_with("EA Core") {
+            continue;
+        }
+        let Some(dir) = values.get("install dir") else { continue };
+        let dir = PathBuf::from(dir);
+        if let Some(exe) = ea_game_exe(&dir) {
+            let id: String = name.to_lowercase().chars().map(|c| if c.is_alphanumeric() { c } else { '-' }).collect();
+            games.push(GameInfo::new("ea", &id, name, exe.to_string_lossy().to_string(), &dir));
+        }
+    }
+}
+
+/// The launcher apps themselves, so players can open their own library
+#[cfg(target_os = "windows")]
+fn scan_launchers(games: &mut Vec<GameInfo>) {
+    let launchers = [
+        ("🎮 Steam", "C:\\Program Files (x86)\\Steam\\steam.exe"),
+        ("🎮 Steam", "C:\\Program Files\\Steam\\steam.exe"),
+        ("🎮 Epic Games", "C:\\Program Files (x86)\\Epic Games\\Launcher\\Portal\\Binaries\\Win32\\EpicGamesLauncher.exe"),
+        ("🎮 Epic Games", "C:\\Program Files (x86)\\Epic Games\\Launcher\\Portal\\Binaries\\Win64\\EpicGamesLauncher.exe"),
+        ("🎮 Riot Client", "C:\\Riot Games\\Riot Client\\RiotClientServices.exe"),
+        ("🎮 Riot Client", "C:\\Program Files\\Riot Games\\Riot Client\\RiotClientServices.exe"),
+        ("🎮 Origin", "C:\\Program Files (x86)\\Origin\\Origin.exe"),
+        ("🎮 Origin", "C:\\Program Files\\Origin\\Origin.exe"),
+        ("🎮 EA App", "C:\\Program Files\\Electronic Arts\\EA Desktop\\EA Desktop\\EADesktop.exe"),
+        ("🎮 Ubisoft Connect", "C:\\Program Files (x86)\\Ubisoft\\Ubisoft Game Launcher\\UbisoftConnect.exe"),
+        ("🎮 Battle.net", "C:\\Program Files (x86)\\Battle.net\\Battle.net Launcher.exe"),
+        ("🎮 GOG Galaxy", "C:\\Program Files (x86)\\GOG Galaxy\\GalaxyClient.exe"),
+    ];
+    for (name, path) in launchers {
+        let path = Path::new(path);
+        if path.exists() {
+            let id = name.trim_start_matches("🎮 ").to_lowercase().replace(' ', "-");
+            games.push(GameInfo::new("launcher", &id, name, path.to_string_lossy().to_string(), path.parent().unwrap_or(path)));
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Linux launchers
+
+#[cfg(target_os = "linux")]
+fn scan_lutris(games: &mut Vec<GameInfo>) {
+    // Lutris keeps its library in SQLite; its CLI is the stable interface
+    let output = match Command::new("lutris").args(&["-lo", "--json"]).output() {
+        Ok(o) if o.status.success() => o,
+        _ => return,
+    };
+    let list: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap_or_default();
+    for game in list {
+        let field = |key: &str| game.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let slug = field("slug");
+        if slug.is_empty() {
+            continue;
+        }
+        games.push(GameInfo::new("lutris", &slug, &field("name"), format!("lutris:rungame/{}", slug), Path::new(&field("directory"))));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn scan_heroic(games: &mut Vec<GameInfo>) {
+    let Some(home) = dirs::home_dir() else { return };
+    for root in [home.join(".config/heroic"), home.join(".var/app/com.heroicgameslauncher.hgl/config/heroic")] {
+        scan_heroic_root(&root, games);
+    }
+}
+
+/// Games recorded under one Heroic config dir (native or Flatpak)
+#[cfg(target_os = "linux")]
+fn scan_heroic_root(root: &Path, games: &mut Vec<GameInfo>) {
+    let read_json = |path: PathBuf| -> Option<serde_json::Value> {
+        fs::read_to_string(path).ok().and_then(|d| serde_json::from_str(&d).ok())
+    };
+    let uri = |runner: &str, app: &str| format!("heroic://launch?appName={}&runner={}", app, runner);
+
+    // Epic titles installed through legendary
+    if let Some(installed) = read_json(root.join("legendaryConfig/legendary/installed.json")) {
+        for (app_name, game) in installed.as_object().into_iter().flatten() {
+            if game.get("is_dlc").and_then(|v| v.as_bool()).unwrap_or(false) {
+                continue;
+            }
+            let title = game.get("title").and_then(|v| v.as_str()).unwrap_or(app_name);
+            let install = game.get("install_path").and_then(|v| v.as_str()).unwrap_or_default();
+            games.push(GameInfo::new("heroic", app_name, title, uri("legendary", app_name), Path::new(install)));
+        }
+    }
+    // GOG titles
+    if let Some(installed) = read_json(root.join("gog_store/installed.json")) {
+        for game in installed.get("installed").and_then(|v| v.as_array()).into_iter().flatten() {
+            let (Some(app_name), Some(install)) = (
+                game.get("appName").and_then(|v| v.as_str()),
+                game.get("install_path").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            let install = Path::new(install);
+            let title = install.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| app_name.to_string());
+            games.push(GameInfo::new("heroic", app_name, &title, uri("gog", app_name), install));
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+/// Scan every known launcher. `extra_paths` (from `launcher_paths` in the
+/// client config) may point at additional Steam libraries or Epic manifest
+/// folders.
+pub fn scan(extra_paths: &[PathBuf]) -> Vec<GameInfo> {
+    let mut games = Vec::new();
+
+    scan_steam(extra_paths, &mut games);
+    if let Some(dir) = epic_manifest_dir() {
+        scan_epic_manifests(&dir, &mut games);
+    }
+    for path in extra_paths {
+        scan_epic_manifests(path, &mut games);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        scan_gog(&mut games);
+        scan_ubisoft(&mut games);
+        scan_ea(&mut games);
+        scan_launchers(&mut games);
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        scan_lutris(&mut games);
+        scan_heroic(&mut games);
+    }
+
+    games.retain(|g| !g.name.is_empty());
+    games.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()).then(a.id.cmp(&b.id)));
+    games.dedup_by(|a, b| a.id == b.id);
+    games
+}
+
+fn configured_launcher_paths() -> Vec<PathBuf> {
+    crate::config::current().launcher_paths.iter().map(PathBuf::from).collect()
+}
+
+/// `scan`, remembering the result for `is_detected_uri`
+fn scan_and_cache(extra_paths: &[PathBuf]) -> Vec<GameInfo> {
+    let games = scan(extra_paths);
+    *LAST_SCAN.lock().unwrap() = Some(CachedScan {
+        at: Instant::now(),
+        extra_paths: extra_paths.to_vec(),
+        games: games.clone(),
+    });
+    games
+}
+
+/// True if `uri` is the launch target of a game found on this PC. Reuses the
+/// last scan when it is recent, otherwise scans the launchers, so call it off
+/// the async executor.
+pub fn is_detected_uri(uri: &str) -> bool {
+    let extra_paths = configured_launcher_paths();
+    let cached = LAST_SCAN.lock().unwrap().as_ref()
+        .filter(|c| c.extra_paths == extra_paths && c.at.elapsed() < SCAN_CACHE_TTL)
+        .map(|c| c.games.iter().any(|game| game.exe_path == uri));
+    cached.unwrap_or_else(|| scan_and_cache(&extra_paths).iter().any(|game| game.exe_path == uri))
+}
+
+#[tauri::command]
+pub async fn detect_installed_games(app_handle: tauri::AppHandle) -> Result<Vec<GameInfo>, String> {
+    let extra_paths = configured_launcher_paths();
+    let icon_dir = crate::icons::cache_dir(&app_handle)?;
+    let games = tauri::async_runtime::spawn_blocking(move || {
+        let mut games = scan_and_cache(&extra_paths);
+        for game in games.iter_mut() {
+            game.icon_path = crate::icons::resolve(&icon_dir, &game.exe_path, &game.install_path)
+                .map(|icon| icon.to_string_lossy().to_string());
+        }
+        games
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    log_info!("[Library] Found {} installed games", games.len());
+    Ok(games)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-library-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_nested_blocks() {
+        let vdf = parse_vdf(r#"
+            "AppState"
+            {
+                "appid"     "730"
+                "UserConfig"
+                {
+                    "language"  "english"
+                }
+            }
+        "#);
+        let state = vdf.get("appstate").unwrap();
+        assert_eq!(state.str("appid"), Some("730"));
+        assert_eq!(state.get("UserConfig").and_then(|c| c.str("language")), Some("english"));
+        assert_eq!(state.str("UserConfig"), None);
+    }
+
+    #[test]
+    fn unescapes_quotes_and_backslashes() {
+        let vdf = parse_vdf(r#""root" { "name" "The \"Best\" Game" "path" "D:\\SteamLibrary" }"#);
+        let root = vdf.get("root").unwrap();
+        assert_eq!(root.str("name"), Some(r#"The "Best" Game"#));
+        assert_eq!(root.str("path"), Some(r"D:\SteamLibrary"));
+    }
+
+    #[test]
+    fn skips_comments() {
+        let vdf = parse_vdf(r#"
+            // written by Steam
+            "root"
+            {
+                "a" "1" // trailing comment
+                // "b" "2"
+                "c" "http://example.com"
+            }
+        "#);
+        let root = vdf.get("root").unwrap();
+        assert_eq!(root.str("a"), Some("1"));
+        assert_eq!(root.str("b"), None);
+        assert_eq!(root.str("c"), Some("http://example.com"));
+    }
+
+    #[test]
+    fn reads_both_libraryfolders_layouts() {
+        let root = temp_dir();
+        fs::create_dir_all(root.join("steamapps")).unwrap();
+        fs::write(root.join("steamapps").join("libraryfolders.vdf"), r#"
+            "libraryfolders"
+            {
+                "TimeNextStatsReport"  "1700000000"
+                "ContentStatsID"       "-123"
+                "1"                    "D:\\SteamLibrary"
+                "2"
+                {
+                    "path"   "E:\\Games\\Steam"
+                    "label"  ""
+                }
+            }
+        "#).unwrap();
+
+        let libraries = steam_libraries(&root);
+        assert_eq!(libraries, vec![root.clone(), PathBuf::from(r"D:\SteamLibrary"), PathBuf::from(r"E:\Games\Steam")]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn scans_installed_app_manifests_only() {
+        let library = temp_dir();
+        let steamapps = library.join("steamapps");
+        fs::create_dir_all(&steamapps).unwrap();
+        let manifest = |appid: &str, name: &str, flags: u32| {
+            format!(
+                "\"AppState\"\n{{\n\t\"appid\"\t\t\"{}\"\n\t\"name\"\t\t\"{}\"\n\t\"StateFlags\"\t\t\"{}\"\n\t\"installdir\"\t\t\"{}\"\n}}\n",
+                appid, name, flags, name
+            )
+        };
+        fs::write(steamapps.join("appmanifest_730.acf"), manifest("730", "Counter-Strike 2", 4)).unwrap();
+        fs::write(steamapps.join("appmanifest_570.acf"), manifest("570", "Dota 2", 1026)).unwrap();
+        fs::write(steamapps.join("appmanifest_1493710.acf"), manifest("1493710", "Proton Experimental", 4)).unwrap();
+
+        let mut games = Vec::new();
+        scan_steam_library(&library, &mut games);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, "steam:730");
+        assert_eq!(games[0].exe_path, "steam://rungameid/730");
+        let _ = fs::remove_dir_all(&library);
+    }
+
+    #[test]
+    fn scans_complete_epic_game_manifests_only() {
+        let dir = temp_dir();
+        let item = |app: &str, name: &str, categories: &str, incomplete: bool| {
+            format!(
+                r#"{{ "AppName": "{}", "DisplayName": "{}", "CatalogNamespace": "ns", "CatalogItemId": "item",
+                    "InstallLocation": "C:\\Games\\{}", "AppCategories": [{}], "bIsIncompleteInstall": {} }}"#,
+                app, name, name, categories, incomplete
+            )
+        };
+        fs::write(dir.join("A.item"), item("Fortnite", "Fortnite", r#""public", "games""#, false)).unwrap();
+        fs::write(dir.join("B.item"), item("Half", "Half Installed", r#""games""#, true)).unwrap();
+        fs::write(dir.join("C.item"), item("Engine", "Unreal Engine", r#""engines""#, false)).unwrap();
+        fs::write(dir.join("D.item"), "{ not json").unwrap();
+        fs::write(dir.join("E.json"), item("Other", "Not A Manifest", r#""games""#, false)).unwrap();
+
+        let mut games = Vec::new();
+        scan_epic_manifests(&dir, &mut games);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].id, "epic:Fortnite");
+        assert_eq!(games[0].name, "Fortnite");
+        assert_eq!(games[0].exe_path, "com.epicgames.launcher://apps/ns%3Aitem%3AFortnite?action=launch&silent=true");
+        assert_eq!(games[0].install_path, r"C:\Games\Fortnite");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn scans_heroic_legendary_and_gog_installs() {
+        let root = temp_dir();
+        fs::create_dir_all(root.join("legendaryConfig/legendary")).unwrap();
+        fs::create_dir_all(root.join("gog_store")).unwrap();
+        fs::write(
+            root.join("legendaryConfig/legendary/installed.json"),
+            r#"{
+                "Sugar": { "title": "Rocket League", "install_path": "/games/RocketLeague", "is_dlc": false },
+                "SugarDlc": { "title": "Rocket Pass", "install_path": "/games/RocketLeague", "is_dlc": true }
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("gog_store/installed.json"),
+            r#"{ "installed": [
+                { "appName": "1207658924", "install_path": "/games/Witcher 3" },
+                { "appName": "missing-path" }
+            ] }"#,
+        )
+        .unwrap();
+
+        let mut games = Vec::new();
+        scan_heroic_root(&root, &mut games);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].id, "heroic:Sugar");
+        assert_eq!(games[0].name, "Rocket League");
+        assert_eq!(games[0].exe_path, "heroic://launch?appName=Sugar&runner=legendary");
+        assert_eq!(games[1].id, "heroic:1207658924");
+        assert_eq!(games[1].name, "Witcher 3");
+        assert_eq!(games[1].exe_path, "heroic://launch?appName=1207658924&runner=gog");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,183 @@
+// Native desktop notifications.
+//
+// - Linux: freedesktop notifications over D-Bus (notify-rust)
+// - Windows: toast notifications (tauri-winrt-notification)
+//
+// Clicks on action buttons come back as `notification-action` events. When the
+// OS notification service is unavailable (no notification daemon, toasts
+// disabled by policy) the notification is emitted as a `notification-overlay`
+// event so the UI can render it in-app instead.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::agent::AgentEvents;
+
+/// `{ id, action }` when the user clicks an action button
+pub const EVENT_NOTIFICATION_ACTION: &str = "notification-action";
+/// Full `Notification` to render in-app when native delivery failed
+pub const EVENT_NOTIFICATION_OVERLAY: &str = "notification-overlay";
+
+const APP_NAME: &str = "Primus";
+/// AppUserModelID the installer registers on the Start menu shortcut; the
+/// bundle identifier from tauri.conf.json
+#[cfg(target_os = "windows")]
+const APP_ID: &str = "com.primustech.client";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NotificationAction {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Notification {
+    pub id: String,
+    pub title: String,
+    pub body: String,
+    pub urgency: Urgency,
+    pub actions: Vec<NotificationAction>,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Delivery {
+    Native,
+    Overlay,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NotificationOutcome {
+    pub id: String,
+    pub delivery: Delivery,
+    /// Why native delivery was skipped, for overlay deliveries
+    pub error: Option<String>,
+}
+
+impl Notification {
+    pub fn new(title: String, body: String, urgency: Urgency, actions: Vec<NotificationAction>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            title,
+            body,
+            urgency,
+            actions,
+        }
+    }
+}
+
+fn emit_action(events: &Arc<dyn AgentEvents>, id: &str, action: &str) {
+    events.emit(EVENT_NOTIFICATION_ACTION, json!({ "id": id, "action": action }));
+}
+
+#[cfg(target_os = "linux")]
+fn show_native(events: Arc<dyn AgentEvents>, n: &Notification) -> Result<(), String> {
+    let mut native = notify_rust::Notification::new();
+    native
+        .appname(APP_NAME)
+        .summary(&n.title)
+        .body(&n.body)
+        .urgency(match n.urgency {
+            Urgency::Low => notify_rust::Urgency::Low,
+            Urgency::Normal => notify_rust::Urgency::Normal,
+            Urgency::Critical => notify_rust::Urgency::Critical,
+        });
+    for action in &n.actions {
+        native.action(&action.id, &action.label);
+    }
+    if n.urgency == Urgency::Critical {
+        // Stay on screen until dismissed
+        native.timeout(notify_rust::Timeout::Never);
+    }
+
+    let handle = native.show().map_err(|e| format!("Notification service unavailable: {}", e))?;
+    if !n.actions.is_empty() {
+        let id = n.id.clone();
+        // Blocks until an action is invoked or the notification is closed
+        std::thread::spawn(move || {
+            handle.wait_for_action(|action| {
+                if action != "__closed" {
+                    emit_action(&events, &id, action);
+                }
+            });
+        });
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn show_native(events: Arc<dyn AgentEvents>, n: &Notification) -> Result<(), String> {
+    use tauri_winrt_notification::{Duration, Scenario, Sound, Toast};
+
+    let mut toast = Toast::new(APP_ID)
+        .title(&n.title)
+        .text1(&n.body)
+        .duration(if n.urgency == Urgency::Low { Duration::Short } else { Duration::Long })
+        .sound(if n.urgency == Urgency::Low { None } else { Some(Sound::Default) });
+    if n.urgency == Urgency::Critical {
+        // Reminder toasts stay on screen until dismissed
+        toast = toast.scenario(Scenario::Reminder);
+    }
+    for action in &n.actions {
+        toast = toast.add_button(&action.label, &action.id);
+    }
+    let id = n.id.clone();
+    toast
+        .on_activated(move |action| {
+            if let Some(action) = action {
+                emit_action(&events, &id, &action);
+            }
+            Ok(())
+        })
+        .show()
+        .map_err(|e| format!("Toast notifications unavailable: {}", e))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn show_native(_events: Arc<dyn AgentEvents>, _n: &Notification) -> Result<(), String> {
+    Err("Native notifications are not supported on this platform".to_string())
+}
+
+/// Show a notification natively, falling back to the in-app overlay
+pub fn notify(events: Arc<dyn AgentEvents>, n: Notification) -> NotificationOutcome {
+    match show_native(events.clone(), &n) {
+        Ok(()) => NotificationOutcome { id: n.id, delivery: Delivery::Native, error: None },
+        Err(e) => {
+            log_warn!("[Notify] {}, falling back to overlay", e);
+            let id = n.id.clone();
+            events.emit(EVENT_NOTIFICATION_OVERLAY, serde_json::to_value(&n).unwrap_or_default());
+            NotificationOutcome { id, delivery: Delivery::Overlay, error: Some(e) }
+        }
+    }
+}
+
+/// Raise a desktop notification. Returns the notification id used in
+/// `notification-action` events.
+#[tauri::command]
+pub async fn show_notification(
+    app_handle: tauri::AppHandle,
+    title: String,
+    body: String,
+    urgency: Option<Urgency>,
+    actions: Option<Vec<NotificationAction>>,
+) -> Result<NotificationOutcome, String> {
+    if title.trim().is_empty() {
+        return Err("Notification title is required".to_string());
+    }
+    let n = Notification::new(title, body, urgency.unwrap_or_default(), actions.unwrap_or_default());
+    let events: Arc<dyn AgentEvents> = Arc::new(app_handle);
+    tauri::async_runtime::spawn_blocking(move || notify(events, n))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+}
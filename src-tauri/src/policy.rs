@@ -0,0 +1,497 @@
+// Executable allow-list.
+//
+// Everything Primus executes on behalf of the UI (games, launch profile hooks)
+// is checked against an admin-managed policy first. The policy lives in
+// `exec_policy.json` in the app config dir and can only be changed through
+// the signed device channel (fetched from the backend or pushed with a
+// `policy.update` command), never from the webview.
+//
+// Rules match on the executable's path (file or folder), its SHA-256, or on
+// Windows its Authenticode publisher. Deny rules win over allow rules; with no
+// matching rule the executable is denied in `enforce` mode. Denials, and
+// would-be denials in `audit` mode, are reported as security events.
+//
+// Until the backend has sent a policy, only the executables of café games
+// synced from the backend are allowed (enforced). Games added locally through
+// the UI are never trusted.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+const POLICY_FILE: &str = "exec_policy.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyMode {
+    /// Only allow-listed executables run
+    #[default]
+    Enforce,
+    /// Everything runs, but would-be denials are reported
+    Audit,
+    Disabled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Allow,
+    Deny,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum RuleMatch {
+    /// Exact executable or everything below a folder
+    Path { path: String },
+    Hash { sha256: String },
+    /// Authenticode signer (certificate CN), Windows only
+    Publisher { publisher: String },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ExecRule {
+    #[serde(flatten)]
+    pub matcher: RuleMatch,
+    #[serde(default)]
+    pub action: RuleAction,
+    #[serde(default)]
+    pub comment: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ExecPolicy {
+    pub mode: PolicyMode,
+    pub rules: Vec<ExecRule>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ExeIdentity {
+    pub path: String,
+    /// Resolved file that was hashed; what must actually be executed
+    #[serde(skip)]
+    pub canonical: PathBuf,
+    pub sha256: String,
+    /// `None` when not checked (no publisher rules) or not supported
+    pub signed: Option<bool>,
+    pub publisher: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Decision {
+    pub allowed: bool,
+    pub mode: PolicyMode,
+    pub reason: String,
+    /// Index of the matching rule
+    pub rule: Option<usize>,
+    pub identity: ExeIdentity,
+}
+
+impl ExecPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, rule) in self.rules.iter().enumerate() {
+            match &rule.matcher {
+                RuleMatch::Path { path } if !Path::new(path).is_absolute() => {
+                    return Err(format!("Rule {}: path must be absolute: {}", i, path));
+                }
+                RuleMatch::Hash { sha256 } if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) => {
+                    return Err(format!("Rule {}: invalid SHA-256: {}", i, sha256));
+                }
+                RuleMatch::Publisher { publisher } if publisher.trim().is_empty() => {
+                    return Err(format!("Rule {}: publisher is empty", i));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn has_publisher_rules(&self) -> bool {
+        self.rules.iter().any(|r| matches!(r.matcher, RuleMatch::Publisher { .. }))
+    }
+}
+
+pub fn load(config_dir: &Path) -> ExecPolicy {
+    let path = config_dir.join(POLICY_FILE);
+    match fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_else(|e| {
+            // A corrupt policy must not silently open everything up
+            log_warn!("[Policy] Invalid {}, enforcing an empty policy: {}", POLICY_FILE, e);
+            ExecPolicy { mode: PolicyMode::Enforce, ..Default::default() }
+        }),
+        Err(_) => catalog_policy(config_dir),
+    }
+}
+
+/// Policy used until the backend provides one: enforce, allowing only the
+/// executables of café games from the backend
+fn catalog_policy(config_dir: &Path) -> ExecPolicy {
+    let rules = crate::catalog::read_games(config_dir)
+        .into_iter()
+        .filter(|game| crate::catalog::is_backend_game(game))
+        .filter(|game| !crate::library::is_launch_uri(&game.exe_path) && Path::new(&game.exe_path).is_absolute())
+        .map(|game| ExecRule {
+            matcher: RuleMatch::Path { path: game.exe_path },
+            action: RuleAction::Allow,
+            comment: format!("Catalog: {}", game.name),
+        })
+        .collect();
+    ExecPolicy { mode: PolicyMode::Enforce, rules, updated_at: None }
+}
+
+pub fn save(config_dir: &Path, policy: &ExecPolicy) -> Result<(), String> {
+    policy.validate()?;
+    fs::create_dir_all(config_dir).map_err(|e| e.to_string())?;
+    let path = config_dir.join(POLICY_FILE);
+    let tmp = path.with_extension("json.tmp");
+    let data = serde_json::to_string_pretty(policy).map_err(|e| e.to_string())?;
+    fs::write(&tmp, data).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+/// Comparable form of a path: no verbatim prefix, case-folded on Windows
+fn normalize(path: &Path) -> String {
+    let s = path.to_string_lossy();
+    let s = s.strip_prefix("\\\\?\\").unwrap_or(&s[..]);
+    #[cfg(target_os = "windows")]
+    let s = s.replace('/', "\\").to_lowercase();
+    s.trim_end_matches(std::path::MAIN_SEPARATOR).to_string()
+}
+
+fn path_matches(rule: &str, exe: &str) -> bool {
+    let rule_path = fs::canonicalize(rule).unwrap_or_else(|_| PathBuf::from(rule));
+    let rule = normalize(&rule_path);
+    !rule.is_empty() && (exe == rule || exe.starts_with(&format!("{}{}", rule, std::path::MAIN_SEPARATOR)))
+}
+
+/// SHA-256 of a file, cached by path, size and mtime (game executables are big)
+pub(crate) fn sha256_file(path: &Path) -> Result<String, String> {
+    static CACHE: OnceLock<Mutex<HashMap<PathBuf, (u64, Option<SystemTime>, String)>>> = OnceLock::new();
+    let meta = fs::metadata(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let key = (meta.len(), meta.modified().ok());
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((len, mtime, hash)) = cache.lock().unwrap().get(path) {
+        if (*len, *mtime) == key {
+            return Ok(hash.clone());
+        }
+    }
+
+    let mut file = fs::File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    cache.lock().unwrap().insert(path.to_path_buf(), (key.0, key.1, hash.clone()));
+    Ok(hash)
+}
+
+/// Authenticode status and signer CN
+#[cfg(target_os = "windows")]
+fn authenticode(path: &Path) -> (bool, Option<String>) {
+    // Path is passed via the environment so it is never parsed as script
+    let script = "$s = Get-AuthenticodeSignature -LiteralPath $env:PRIMUS_VERIFY_PATH; \
+        @{ status = [string]$s.Status; subject = $s.SignerCertificate.Subject } | ConvertTo-Json -Compress";
+    let output = std::process::Command::new("powershell")
+        .args(&["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("PRIMUS_VERIFY_PATH", path)
+        .output();
+    let info: serde_json::Value = match output {
+        Ok(o) if o.status.success() => serde_json::from_slice(&o.stdout).unwrap_or_default(),
+        _ => return (false, None),
+    };
+    let valid = info.get("status").and_then(|v| v.as_str()) == Some("Valid");
+    let publisher = info.get("subject").and_then(|v| v.as_str()).and_then(subject_cn);
+    (valid, publisher)
+}
+
+/// `CN=Valve Corp., O=Valve Corp., C=US` -> `Valve Corp.`
+#[cfg(target_os = "windows")]
+fn subject_cn(subject: &str) -> Option<String> {
+    let rest = &subject[subject.find("CN=")? + 3..];
+    let cn = if let Some(quoted) = rest.strip_prefix('"') {
+        &quoted[..quoted.find('"').unwrap_or(quoted.len())]
+    } else {
+        &rest[..rest.find(", ").unwrap_or(rest.len())]
+    };
+    Some(cn.trim().to_string())
+}
+
+pub fn identify(path: &Path, with_publisher: bool) -> Result<ExeIdentity, String> {
+    let canonical = fs::canonicalize(path).map_err(|e| format!("Cannot resolve {}: {}", path.display(), e))?;
+    let sha256 = sha256_file(&canonical)?;
+    #[cfg(target_os = "windows")]
+    let (signed, publisher) = if with_publisher {
+        let (valid, publisher) = authenticode(&canonical);
+        (Some(valid), publisher)
+    } else {
+        (None, None)
+    };
+    #[cfg(not(target_os = "windows"))]
+    let (signed, publisher) = {
+        let _ = with_publisher;
+        (None, None)
+    };
+    Ok(ExeIdentity { path: normalize(&canonical), canonical, sha256, signed, publisher })
+}
+
+pub fn evaluate(policy: &ExecPolicy, identity: ExeIdentity) -> Decision {
+    let matches = |rule: &ExecRule| match &rule.matcher {
+        RuleMatch::Path { path } => path_matches(path, &identity.path),
+        RuleMatch::Hash { sha256 } => sha256.eq_ignore_ascii_case(&identity.sha256),
+        // Only a valid signature counts
+        RuleMatch::Publisher { publisher } => identity.signed == Some(true)
+            && identity.publisher.as_deref().map(|p| p.eq_ignore_ascii_case(publisher.trim())).unwrap_or(false),
+    };
+
+    let deny = policy.rules.iter().position(|r| r.action == RuleAction::Deny && matches(r));
+    let allow = policy.rules.iter().position(|r| r.action == RuleAction::Allow && matches(r));
+    let (permitted, rule, reason) = match (deny, allow) {
+        (Some(i), _) => (false, Some(i), format!("Denied by rule {}", i)),
+        (None, Some(i)) => (true, Some(i), format!("Allowed by rule {}", i)),
+        (None, None) => (false, None, "Not on the allow-list".to_string()),
+    };
+    Decision {
+        allowed: permitted || policy.mode != PolicyMode::Enforce,
+        mode: policy.mode,
+        reason,
+        rule,
+        identity,
+    }
+}
+
+/// Check an executable before running it. Returns the decision, or an error
+/// if it must not run. Denials (and audit-mode violations) are reported.
+pub fn check(config_dir: &Path, exe: &Path, context: &str) -> Result<Decision, String> {
+    let policy = load(config_dir);
+    if policy.mode == PolicyMode::Disabled {
+        let identity = ExeIdentity {
+            path: normalize(exe),
+            canonical: exe.to_path_buf(),
+            sha256: String::new(),
+            signed: None,
+            publisher: None,
+        };
+        return Ok(Decision { allowed: true, mode: policy.mode, reason: "Policy disabled".to_string(), rule: None, identity });
+    }
+
+    let identity = identify(exe, policy.has_publisher_rules())?;
+    let decision = evaluate(&policy, identity);
+    let violation = decision.rule.map(|i| policy.rules[i].action == RuleAction::Deny).unwrap_or(true);
+    if violation {
+        log_warn!("[Policy] {} {} ({}): {}", context, decision.identity.path, decision.identity.sha256, decision.reason);
+        report(config_dir.to_path_buf(), &decision, context);
+    }
+    if !decision.allowed {
+        return Err(format!("Blocked by executable policy: {} ({})", exe.display(), decision.reason));
+    }
+    Ok(decision)
+}
+
+/// Send a security event to the backend in the background
+fn report(config_dir: PathBuf, decision: &Decision, context: &str) {
+    let body = json!({
+        "type": if decision.allowed { "exec_audit" } else { "exec_denied" },
+        "context": context,
+        "mode": decision.mode,
+        "reason": decision.reason,
+        "path": decision.identity.path,
+        "sha256": decision.identity.sha256,
+        "signed": decision.identity.signed,
+        "publisher": decision.identity.publisher,
+        "at": chrono::Utc::now().to_rfc3339(),
+    });
+    tauri::async_runtime::spawn(async move {
+        let result = match crate::device_client::DeviceClient::from_config_dir(&config_dir) {
+            Ok(client) => client.post("/clientpc/security-event", &body).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            log_warn!("[Policy] Failed to report security event: {}", e);
+        }
+    });
+}
+
+/// Current policy (read-only for the UI)
+#[tauri::command]
+pub async fn get_exec_policy(app_handle: tauri::AppHandle) -> Result<ExecPolicy, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+    Ok(load(&config_dir))
+}
+
+/// Dry-run the policy against an executable
+#[tauri::command]
+pub async fn check_exec_policy(app_handle: tauri::AppHandle, exe_path: String) -> Result<Decision, String> {
+    let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let policy = load(&config_dir);
+        let identity = identify(Path::new(&exe_path), policy.has_publisher_rules())?;
+        Ok(evaluate(&policy, identity))
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-policy-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn missing_policy_enforces_an_empty_allow_list() {
+        let dir = temp_dir();
+        let policy = load(&dir);
+        assert_eq!(policy.mode, PolicyMode::Enforce);
+        assert!(policy.rules.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn missing_policy_allows_only_backend_catalog_executables() {
+        let dir = temp_dir();
+        let exe = std::env::temp_dir().join("game.exe").to_string_lossy().to_string();
+        let local = std::env::temp_dir().join("local.exe").to_string_lossy().to_string();
+        let game = |id: &str, exe_path: &str| json!({
+            "id": id,
+            "name": id,
+            "source": if id.starts_with("cafe:") { "cafe" } else { "local" },
+            "exe_path": exe_path,
+            "install_path": null,
+            "icon_path": null,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+        });
+        let catalog = json!({ "version": 1, "games": [
+            game("cafe:1", &exe),
+            game("cafe:2", "steam://rungameid/730"),
+            game("local:1", &local),
+        ] });
+        fs::write(dir.join("games.json"), catalog.to_string()).unwrap();
+
+        let policy = load(&dir);
+        assert_eq!(policy.mode, PolicyMode::Enforce);
+        assert_eq!(policy.rules.len(), 1);
+        assert_eq!(policy.rules[0].matcher, RuleMatch::Path { path: exe });
+        assert_eq!(policy.rules[0].action, RuleAction::Allow);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn stored_policy_without_mode_is_enforced() {
+        let policy: ExecPolicy = serde_json::from_str(r#"{ "rules": [] }"#).unwrap();
+        assert_eq!(policy.mode, PolicyMode::Enforce);
+    }
+
+    fn rule(matcher: RuleMatch, action: RuleAction) -> ExecRule {
+        ExecRule { matcher, action, comment: String::new() }
+    }
+
+    fn path_rule(path: &Path, action: RuleAction) -> ExecRule {
+        rule(RuleMatch::Path { path: path.to_string_lossy().to_string() }, action)
+    }
+
+    fn enforce(rules: Vec<ExecRule>) -> ExecPolicy {
+        ExecPolicy { mode: PolicyMode::Enforce, rules, updated_at: None }
+    }
+
+    /// `<dir>/games/cs2/cs2.exe`, identified
+    fn game_exe(dir: &Path) -> (PathBuf, ExeIdentity) {
+        let game_dir = dir.join("games").join("cs2");
+        fs::create_dir_all(&game_dir).unwrap();
+        let exe = game_dir.join("cs2.exe");
+        fs::write(&exe, b"MZ not really a game").unwrap();
+        let identity = identify(&exe, false).unwrap();
+        (exe, identity)
+    }
+
+    #[test]
+    fn path_rules_match_the_file_and_folders_above_it() {
+        let dir = temp_dir();
+        let (exe, identity) = game_exe(&dir);
+
+        for allowed in [exe.clone(), dir.join("games"), dir.join("games").join("cs2")] {
+            let decision = evaluate(&enforce(vec![path_rule(&allowed, RuleAction::Allow)]), identity.clone());
+            assert!(decision.allowed, "{} should allow {}", allowed.display(), exe.display());
+            assert_eq!(decision.rule, Some(0));
+        }
+        // A sibling whose name merely starts with the folder name is not inside it
+        let sibling = dir.join("games").join("cs");
+        assert!(!evaluate(&enforce(vec![path_rule(&sibling, RuleAction::Allow)]), identity.clone()).allowed);
+        assert!(!evaluate(&enforce(vec![]), identity).allowed);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn deny_rules_win_over_allow_rules() {
+        let dir = temp_dir();
+        let (exe, identity) = game_exe(&dir);
+        let policy = enforce(vec![
+            path_rule(&dir.join("games"), RuleAction::Allow),
+            path_rule(&exe, RuleAction::Deny),
+        ]);
+        let decision = evaluate(&policy, identity);
+        assert!(!decision.allowed);
+        assert_eq!(decision.rule, Some(1));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn hash_rules_match_case_insensitively() {
+        let dir = temp_dir();
+        let (_, identity) = game_exe(&dir);
+        let matching = rule(RuleMatch::Hash { sha256: identity.sha256.to_uppercase() }, RuleAction::Allow);
+        assert!(evaluate(&enforce(vec![matching]), identity.clone()).allowed);
+
+        let other = rule(RuleMatch::Hash { sha256: "0".repeat(64) }, RuleAction::Allow);
+        assert!(!evaluate(&enforce(vec![other]), identity).allowed);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn publisher_rules_need_a_valid_signature() {
+        let identity = |signed: Option<bool>| ExeIdentity {
+            path: "/games/cs2/cs2.exe".to_string(),
+            canonical: PathBuf::from("/games/cs2/cs2.exe"),
+            sha256: "0".repeat(64),
+            signed,
+            publisher: Some("Valve Corp.".to_string()),
+        };
+        let policy = enforce(vec![rule(RuleMatch::Publisher { publisher: " valve corp. ".to_string() }, RuleAction::Allow)]);
+        assert!(evaluate(&policy, identity(Some(true))).allowed);
+        assert!(!evaluate(&policy, identity(Some(false))).allowed);
+        assert!(!evaluate(&policy, identity(None)).allowed);
+    }
+
+    #[test]
+    fn audit_mode_allows_but_keeps_the_reason() {
+        let dir = temp_dir();
+        let (exe, identity) = game_exe(&dir);
+        let policy = ExecPolicy { mode: PolicyMode::Audit, rules: vec![path_rule(&exe, RuleAction::Deny)], updated_at: None };
+        let decision = evaluate(&policy, identity);
+        assert!(decision.allowed);
+        assert_eq!(decision.mode, PolicyMode::Audit);
+        assert_eq!(decision.rule, Some(0));
+        assert_eq!(decision.reason, "Denied by rule 0");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,465 @@
+// Power and session actions behind a platform abstraction.
+//
+// - Windows: `shutdown.exe` / `LockWorkStation`
+// - Linux: systemd-logind over D-Bus (`busctl`), with `loginctl` for sessions.
+//   Forced actions must be immediate; logind can't schedule past inhibitors.
+// - Dry run: records calls instead of touching the machine. Selected with
+//   `PRIMUS_POWER_DRY_RUN=1`, and used to exercise command dispatch on CI.
+//
+// Delayed shutdown/restart is scheduled with the OS (so it still happens if
+// Primus dies) and tracked by `PowerScheduler`, which emits a countdown event
+// every second so the UI can show a banner.
+
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::agent::AgentEvents;
+
+/// Emitted every second while an operation is pending (`PendingOperation` + `remaining_secs`)
+pub const EVENT_POWER_COUNTDOWN: &str = "power-countdown";
+/// Emitted when a pending operation is cancelled (`PendingOperation`)
+pub const EVENT_POWER_CANCELLED: &str = "power-cancelled";
+/// Emitted when the countdown reaches zero (`PendingOperation`)
+pub const EVENT_POWER_EXECUTING: &str = "power-executing";
+
+pub const DEFAULT_DELAY_SECS: u32 = 5;
+// shutdown.exe rejects anything above ten years
+const MAX_DELAY_SECS: u32 = 315_360_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerRequest {
+    pub delay_secs: u32,
+    pub reason: String,
+    /// Close applications without waiting / ignore inhibitors
+    pub force: bool,
+}
+
+pub trait PowerController: Send + Sync {
+    fn shutdown(&self, req: &PowerRequest) -> Result<(), String>;
+    fn restart(&self, req: &PowerRequest) -> Result<(), String>;
+    fn logoff(&self) -> Result<(), String>;
+    fn lock(&self) -> Result<(), String>;
+    /// Abort a pending shutdown/restart. `Ok(false)` if none was pending.
+    fn cancel_shutdown(&self) -> Result<bool, String>;
+}
+
+/// Run a command to completion and turn a non-zero exit into an error
+fn run(program: &str, args: &[&str], what: &str) -> Result<(), String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to {}: {}", what, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("Failed to {}: {}", what, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+pub struct WindowsPower;
+
+impl WindowsPower {
+    fn schedule(&self, mode: &str, req: &PowerRequest, what: &str) -> Result<(), String> {
+        let delay = req.delay_secs.to_string();
+        let mut args = vec![mode, "/t", delay.as_str(), "/c", req.reason.as_str()];
+        if req.force {
+            args.push("/f");
+        }
+        run("shutdown", &args, what)
+    }
+}
+
+impl PowerController for WindowsPower {
+    fn shutdown(&self, req: &PowerRequest) -> Result<(), String> {
+        self.schedule("/s", req, "initiate shutdown")
+    }
+
+    fn restart(&self, req: &PowerRequest) -> Result<(), String> {
+        self.schedule("/r", req, "initiate restart")
+    }
+
+    fn logoff(&self) -> Result<(), String> {
+        run("shutdown", &["/l"], "logoff")
+    }
+
+    fn lock(&self) -> Result<(), String> {
+        run("rundll32.exe", &["user32.dll,LockWorkStation"], "lock workstation")
+    }
+
+    fn cancel_shutdown(&self) -> Result<bool, String> {
+        let output = Command::new("shutdown")
+            .args(&["/a"])
+            .output()
+            .map_err(|e| format!("Failed to cancel shutdown: {}", e))?;
+        if output.status.success() {
+            return Ok(true);
+        }
+        // ERROR_NO_SHUTDOWN_IN_PROGRESS: nothing was pending
+        if output.status.code() == Some(1116) {
+            return Ok(false);
+        }
+        Err(format!("Failed to cancel shutdown: {}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// systemd-logind. Delayed actions use `ScheduleShutdown` so they can be
+/// cancelled the same way `shutdown /a` works on Windows.
+pub struct LinuxPower;
+
+const LOGIND_DEST: &str = "org.freedesktop.login1";
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_MANAGER: &str = "org.freedesktop.login1.Manager";
+
+impl LinuxPower {
+    fn schedule(&self, kind: &str, req: &PowerRequest, what: &str) -> Result<(), String> {
+        if req.force {
+            // logind has no way to ignore inhibitors for a scheduled shutdown
+            if req.delay_secs > 0 {
+                return Err(format!("Failed to {}: a forced {} can't be delayed on Linux", what, kind));
+            }
+            // Immediate and forced: don't let inhibitors (e.g. a game) block it
+            log_info!("[Power] {}", req.reason);
+            let verb = if kind == "reboot" { "reboot" } else { "poweroff" };
+            return run("systemctl", &[verb, "--ignore-inhibitors"], what);
+        }
+        log_info!("[Power] {}", req.reason);
+        let delay_secs = req.delay_secs;
+        let now_usec = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_micros() as u64;
+        let at_usec = (now_usec + delay_secs as u64 * 1_000_000).to_string();
+        run("busctl", &["call", LOGIND_DEST, LOGIND_PATH, LOGIND_MANAGER, "ScheduleShutdown", "st", kind, &at_usec], what)
+    }
+
+    fn session_args() -> Vec<String> {
+        // Without an id loginctl acts on the caller's session
+        std::env::var("XDG_SESSION_ID").map(|id| vec![id]).unwrap_or_default()
+    }
+}
+
+impl PowerController for LinuxPower {
+    fn shutdown(&self, req: &PowerRequest) -> Result<(), String> {
+        self.schedule("poweroff", req, "initiate shutdown")
+    }
+
+    fn restart(&self, req: &PowerRequest) -> Result<(), String> {
+        self.schedule("reboot", req, "initiate restart")
+    }
+
+    fn logoff(&self) -> Result<(), String> {
+        let mut args = vec!["terminate-session".to_string()];
+        args.extend(Self::session_args());
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        run("loginctl", &args, "logoff")
+    }
+
+    fn lock(&self) -> Result<(), String> {
+        let mut args = vec!["lock-session".to_string()];
+        args.extend(Self::session_args());
+        let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+        run("loginctl", &args, "lock session")
+    }
+
+    fn cancel_shutdown(&self) -> Result<bool, String> {
+        let output = Command::new("busctl")
+            .args(&["call", LOGIND_DEST, LOGIND_PATH, LOGIND_MANAGER, "CancelScheduledShutdown"])
+            .output()
+            .map_err(|e| format!("Failed to cancel shutdown: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("Failed to cancel shutdown: {}", String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        // Reply is "b true" when something was cancelled
+        Ok(String::from_utf8_lossy(&output.stdout).trim() == "b true")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PowerCall {
+    Shutdown { delay_secs: u32, reason: String, force: bool },
+    Restart { delay_secs: u32, reason: String, force: bool },
+    Logoff,
+    Lock,
+    CancelShutdown,
+}
+
+/// Records every call and never touches the machine
+#[derive(Default)]
+pub struct DryRunPower {
+    calls: Mutex<Vec<PowerCall>>,
+}
+
+impl DryRunPower {
+    pub fn calls(&self) -> Vec<PowerCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: PowerCall) -> Result<(), String> {
+        log_debug!("[Power] Dry run: {:?}", call);
+        self.calls.lock().unwrap().push(call);
+        Ok(())
+    }
+}
+
+impl PowerController for DryRunPower {
+    fn shutdown(&self, req: &PowerRequest) -> Result<(), String> {
+        self.record(PowerCall::Shutdown { delay_secs: req.delay_secs, reason: req.reason.clone(), force: req.force })
+    }
+
+    fn restart(&self, req: &PowerRequest) -> Result<(), String> {
+        self.record(PowerCall::Restart { delay_secs: req.delay_secs, reason: req.reason.clone(), force: req.force })
+    }
+
+    fn logoff(&self) -> Result<(), String> {
+        self.record(PowerCall::Logoff)
+    }
+
+    fn lock(&self) -> Result<(), String> {
+        self.record(PowerCall::Lock)
+    }
+
+    fn cancel_shutdown(&self) -> Result<bool, String> {
+        let pending = matches!(
+            self.calls.lock().unwrap().last(),
+            Some(PowerCall::Shutdown { .. }) | Some(PowerCall::Restart { .. })
+        );
+        self.record(PowerCall::CancelShutdown)?;
+        Ok(pending)
+    }
+}
+
+/// Controller for the current platform (or the dry-run one if requested)
+pub fn platform() -> Arc<dyn PowerController> {
+    lazy_static::lazy_static! {
+        static ref CONTROLLER: Arc<dyn PowerController> = {
+            if std::env::var("PRIMUS_POWER_DRY_RUN").map(|v| v == "1").unwrap_or(false) {
+                Arc::new(DryRunPower::default())
+            } else if cfg!(target_os = "windows") {
+                Arc::new(WindowsPower)
+            } else {
+                Arc::new(LinuxPower)
+            }
+        };
+    }
+    CONTROLLER.clone()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerKind {
+    Shutdown,
+    Restart,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingOperation {
+    pub id: u64,
+    pub kind: PowerKind,
+    pub reason: String,
+    pub force: bool,
+    pub delay_secs: u32,
+    /// Unix timestamp (seconds) when the operation fires
+    pub deadline: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CancelResult {
+    pub cancelled: bool,
+    /// The operation Primus had scheduled, if any
+    pub operation: Option<PendingOperation>,
+}
+
+/// Tracks the pending shutdown/restart and drives the countdown events
+pub struct PowerScheduler {
+    power: Arc<dyn PowerController>,
+    pending: Mutex<Option<PendingOperation>>,
+    next_id: AtomicU64,
+}
+
+impl PowerScheduler {
+    pub fn new(power: Arc<dyn PowerController>) -> Self {
+        Self {
+            power,
+            pending: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn controller(&self) -> &dyn PowerController {
+        self.power.as_ref()
+    }
+
+    pub fn pending(&self) -> Option<PendingOperation> {
+        self.pending.lock().unwrap().clone()
+    }
+
+    /// Schedule a shutdown/restart, replacing any pending one
+    pub fn schedule(
+        self: &Arc<Self>,
+        events: Arc<dyn AgentEvents>,
+        kind: PowerKind,
+        req: PowerRequest,
+    ) -> Result<PendingOperation, String> {
+        if req.delay_secs > MAX_DELAY_SECS {
+            return Err(format!("Delay must be at most {} seconds", MAX_DELAY_SECS));
+        }
+
+        // The OS only tracks one pending shutdown at a time
+        let mut pending = self.pending.lock().unwrap();
+        if pending.is_some() {
+            self.power.cancel_shutdown()?;
+            if let Some(previous) = pending.take() {
+                events.emit(EVENT_POWER_CANCELLED, json!(previous));
+            }
+        }
+        drop(pending);
+
+        match kind {
+            PowerKind::Shutdown => self.power.shutdown(&req)?,
+            PowerKind::Restart => self.power.restart(&req)?,
+        }
+
+        let op = PendingOperation {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            kind,
+            reason: req.reason,
+            force: req.force,
+            delay_secs: req.delay_secs,
+            deadline: chrono::Utc::now().timestamp() + req.delay_secs as i64,
+        };
+        *self.pending.lock().unwrap() = Some(op.clone());
+
+        let scheduler = self.clone();
+        let countdown = op.clone();
+        tauri::async_runtime::spawn(async move { scheduler.countdown(events, countdown).await });
+        Ok(op)
+    }
+
+    async fn countdown(&self, events: Arc<dyn AgentEvents>, op: PendingOperation) {
+        loop {
+            // Stop as soon as this operation was cancelled or replaced
+            if self.pending().map(|p| p.id) != Some(op.id) {
+                return;
+            }
+            let remaining = (op.deadline - chrono::Utc::now().timestamp()).max(0);
+            if remaining == 0 {
+                self.pending.lock().unwrap().take();
+                events.emit(EVENT_POWER_EXECUTING, json!(op));
+                return;
+            }
+            let mut payload = json!(op);
+            payload["remaining_secs"] = json!(remaining);
+            events.emit(EVENT_POWER_COUNTDOWN, payload);
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    /// Cancel the pending operation (or one scheduled outside Primus)
+    pub fn cancel(&self, events: &dyn AgentEvents) -> Result<CancelResult, String> {
+        // Keep the operation pending (and its countdown running) unless the
+        // OS actually dropped it
+        let mut pending = self.pending.lock().unwrap();
+        let os_cancelled = self.power.cancel_shutdown()?;
+        if !os_cancelled {
+            return Ok(CancelResult { cancelled: false, operation: None });
+        }
+        let operation = pending.take();
+        drop(pending);
+        if let Some(op) = &operation {
+            events.emit(EVENT_POWER_CANCELLED, json!(op));
+        }
+        Ok(CancelResult { cancelled: true, operation })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NullEvents;
+
+    impl AgentEvents for NullEvents {
+        fn emit(&self, _event: &str, _payload: serde_json::Value) {}
+    }
+
+    /// Schedules fine; every cancel returns the given result
+    struct FixedCancelPower(Result<bool, String>);
+
+    impl PowerController for FixedCancelPower {
+        fn shutdown(&self, _req: &PowerRequest) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn restart(&self, _req: &PowerRequest) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn logoff(&self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn lock(&self) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn cancel_shutdown(&self) -> Result<bool, String> {
+            self.0.clone()
+        }
+    }
+
+    fn request(delay_secs: u32, force: bool) -> PowerRequest {
+        PowerRequest { delay_secs, reason: "test".to_string(), force }
+    }
+
+    #[test]
+    fn failed_cancel_keeps_the_operation_pending() {
+        let scheduler = Arc::new(PowerScheduler::new(Arc::new(FixedCancelPower(Err("access denied".to_string())))));
+        let op = scheduler.schedule(Arc::new(NullEvents), PowerKind::Shutdown, request(60, false)).unwrap();
+
+        assert!(scheduler.cancel(&NullEvents).is_err());
+        assert_eq!(scheduler.pending().map(|p| p.id), Some(op.id));
+    }
+
+    #[test]
+    fn cancel_reports_nothing_when_the_os_had_nothing_pending() {
+        let scheduler = Arc::new(PowerScheduler::new(Arc::new(FixedCancelPower(Ok(false)))));
+        let op = scheduler.schedule(Arc::new(NullEvents), PowerKind::Shutdown, request(60, false)).unwrap();
+
+        let result = scheduler.cancel(&NullEvents).unwrap();
+        assert!(!result.cancelled);
+        assert!(result.operation.is_none());
+        assert_eq!(scheduler.pending().map(|p| p.id), Some(op.id));
+    }
+
+    #[test]
+    fn reschedule_fails_if_the_previous_operation_cannot_be_cancelled() {
+        let scheduler = Arc::new(PowerScheduler::new(Arc::new(FixedCancelPower(Err("access denied".to_string())))));
+        let first = scheduler.schedule(Arc::new(NullEvents), PowerKind::Shutdown, request(60, false)).unwrap();
+
+        assert!(scheduler.schedule(Arc::new(NullEvents), PowerKind::Restart, request(30, false)).is_err());
+        assert_eq!(scheduler.pending().map(|p| p.id), Some(first.id));
+    }
+
+    #[test]
+    fn successful_cancel_clears_the_operation() {
+        let scheduler = Arc::new(PowerScheduler::new(Arc::new(DryRunPower::default())));
+        scheduler.schedule(Arc::new(NullEvents), PowerKind::Restart, request(60, false)).unwrap();
+
+        let result = scheduler.cancel(&NullEvents).unwrap();
+        assert!(result.cancelled);
+        assert!(result.operation.is_some());
+        assert!(scheduler.pending().is_none());
+    }
+
+    #[test]
+    fn linux_rejects_delayed_forced_shutdown() {
+        let err = LinuxPower.shutdown(&request(30, true)).unwrap_err();
+        assert!(err.contains("can't be delayed"), "{}", err);
+    }
+}
@@ -0,0 +1,323 @@
+// Screen capture for the `screenshot` admin command.
+//
+// - Windows: GDI BitBlt of the whole virtual screen
+// - Linux: the first available of grim (wlroots), gnome-screenshot (goes
+//   through the GNOME Shell/portal on Wayland), spectacle (KDE), scrot and
+//   ImageMagick `import` (X11)
+//
+// The capture is optionally downscaled, encoded as PNG or JPEG and uploaded
+// with a signed request.
+
+use std::io::Cursor;
+
+use base64::Engine;
+use image::{DynamicImage, ImageOutputFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::device_client::DeviceClient;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ScreenshotSettings {
+    pub format: ImageFormat,
+    /// JPEG quality (1-100), ignored for PNG
+    pub quality: u8,
+    /// Downscale so the width is at most this many pixels (0 = keep size)
+    pub max_width: u32,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        Self {
+            format: ImageFormat::Jpeg,
+            quality: 70,
+            max_width: 1920,
+        }
+    }
+}
+
+impl ScreenshotSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=100).contains(&self.quality) {
+            return Err("Screenshot quality must be between 1 and 100".to_string());
+        }
+        Ok(())
+    }
+
+    /// Apply per-request overrides (`{ "format", "quality", "max_width" }`)
+    pub fn with_overrides(&self, params: &Value) -> Result<Self, String> {
+        let mut settings = self.clone();
+        if let Some(format) = params.get("format") {
+            settings.format = serde_json::from_value(format.clone()).map_err(|e| format!("Invalid format: {}", e))?;
+        }
+        if let Some(quality) = params.get("quality").and_then(|v| v.as_u64()) {
+            settings.quality = quality.min(100) as u8;
+        }
+        if let Some(max_width) = params.get("max_width").and_then(|v| v.as_u64()) {
+            settings.max_width = max_width as u32;
+        }
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+pub struct EncodedScreenshot {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Serialize)]
+pub struct ScreenshotInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: usize,
+    /// Backend response to the upload, if uploaded
+    pub upload: Option<Value>,
+}
+
+#[cfg(target_os = "windows")]
+fn capture_raw() -> Result<DynamicImage, String> {
+    use winapi::um::wingdi::{
+        BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDIBits,
+        SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, CAPTUREBLT, DIB_RGB_COLORS, SRCCOPY,
+    };
+    use winapi::um::winuser::{
+        GetDC, GetSystemMetrics, ReleaseDC, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+        SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+    };
+
+    unsafe {
+        let x = GetSystemMetrics(SM_XVIRTUALSCREEN);
+        let y = GetSystemMetrics(SM_YVIRTUALSCREEN);
+        let width = GetSystemMetrics(SM_CXVIRTUALSCREEN);
+        let height = GetSystemMetrics(SM_CYVIRTUALSCREEN);
+        if width <= 0 || height <= 0 {
+            return Err("No screen to capture".to_string());
+        }
+
+        let screen_dc = GetDC(std::ptr::null_mut());
+        if screen_dc.is_null() {
+            return Err("Failed to get screen DC".to_string());
+        }
+        let mem_dc = CreateCompatibleDC(screen_dc);
+        let bitmap = CreateCompatibleBitmap(screen_dc, width, height);
+        let old = SelectObject(mem_dc, bitmap as _);
+
+        let copied = BitBlt(mem_dc, 0, 0, width, height, screen_dc, x, y, SRCCOPY | CAPTUREBLT);
+
+        let mut info: BITMAPINFO = std::mem::zeroed();
+        info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+        info.bmiHeader.biWidth = width;
+        info.bmiHeader.biHeight = -height; // top-down rows
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let lines = if copied != 0 {
+            GetDIBits(mem_dc, bitmap, 0, height as u32, pixels.as_mut_ptr() as _, &mut info, DIB_RGB_COLORS)
+        } else {
+            0
+        };
+
+        SelectObject(mem_dc, old);
+        DeleteObject(bitmap as _);
+        DeleteDC(mem_dc);
+        ReleaseDC(std::ptr::null_mut(), screen_dc);
+
+        if lines == 0 {
+            return Err("Failed to copy screen contents".to_string());
+        }
+
+        // BGRA -> RGBA, force opaque
+        for px in pixels.chunks_exact_mut(4) {
+            px.swap(0, 2);
+            px[3] = 255;
+        }
+        RgbaImage::from_raw(width as u32, height as u32, pixels)
+            .map(DynamicImage::ImageRgba8)
+            .ok_or_else(|| "Invalid screen buffer".to_string())
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn capture_raw() -> Result<DynamicImage, String> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    // Fresh owner-only directory so other users can't read or pre-plant the capture
+    let dir = std::env::temp_dir().join(format!("primus-screenshot-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .map_err(|e| format!("Failed to create screenshot directory: {}", e))?;
+    let result = capture_with_tools(&dir.join("capture.png"));
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+#[cfg(not(target_os = "windows"))]
+fn capture_with_tools(tmp: &std::path::Path) -> Result<DynamicImage, String> {
+    use std::process::Command;
+
+    let tmp_str = tmp.to_string_lossy().to_string();
+    let wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
+
+    let mut tools: Vec<(&str, Vec<&str>)> = Vec::new();
+    if wayland {
+        tools.push(("grim", vec![tmp_str.as_str()]));
+    }
+    tools.push(("gnome-screenshot", vec!["-f", tmp_str.as_str()]));
+    tools.push(("spectacle", vec!["-b", "-n", "-f", "-o", tmp_str.as_str()]));
+    if !wayland {
+        tools.push(("scrot", vec!["-o", tmp_str.as_str()]));
+        tools.push(("import", vec!["-window", "root", tmp_str.as_str()]));
+    }
+
+    let mut errors = Vec::new();
+    for (tool, args) in tools {
+        let _ = std::fs::remove_file(tmp);
+        match Command::new(tool).args(&args).output() {
+            Ok(output) if output.status.success() && tmp.exists() => {
+                return image::open(tmp).map_err(|e| format!("Failed to read {} output: {}", tool, e));
+            }
+            Ok(output) => errors.push(format!("{}: {}", tool, String::from_utf8_lossy(&output.stderr).trim())),
+            Err(e) => errors.push(format!("{}: {}", tool, e)),
+        }
+    }
+    Err(format!("No screenshot backend available ({})", errors.join("; ")))
+}
+
+/// Capture, downscale and encode the screen
+pub fn capture(settings: &ScreenshotSettings) -> Result<EncodedScreenshot, String> {
+    let mut img = capture_raw()?;
+    if settings.max_width > 0 && img.width() > settings.max_width {
+        let height = (img.height() as u64 * settings.max_width as u64 / img.width() as u64).max(1) as u32;
+        img = img.resize_exact(settings.max_width, height, image::imageops::FilterType::Triangle);
+    }
+
+    let mut data = Vec::new();
+    let output_format = match settings.format {
+        ImageFormat::Png => ImageOutputFormat::Png,
+        ImageFormat::Jpeg => {
+            // JPEG has no alpha channel
+            img = DynamicImage::ImageRgb8(img.to_rgb8());
+            ImageOutputFormat::Jpeg(settings.quality)
+        }
+    };
+    img.write_to(&mut Cursor::new(&mut data), output_format)
+        .map_err(|e| format!("Failed to encode screenshot: {}", e))?;
+
+    Ok(EncodedScreenshot {
+        format: settings.format,
+        width: img.width(),
+        height: img.height(),
+        data,
+    })
+}
+
+/// Upload a screenshot via a signed request. `command_id` links it to the
+/// admin command that asked for it.
+pub async fn upload(client: &DeviceClient, shot: &EncodedScreenshot, command_id: Option<i64>) -> Result<Value, String> {
+    let body = json!({
+        "command_id": command_id,
+        "format": shot.format,
+        "width": shot.width,
+        "height": shot.height,
+        "taken_at": chrono::Utc::now().to_rfc3339(),
+        "data": base64::engine::general_purpose::STANDARD.encode(&shot.data),
+    });
+    client.post("/clientpc/screenshot", &body).await
+}
+
+/// Capture off the async runtime (GDI calls and CLI tools block)
+async fn capture_async(settings: ScreenshotSettings) -> Result<EncodedScreenshot, String> {
+    tauri::async_runtime::spawn_blocking(move || capture(&settings))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+/// Capture and optionally upload, returning what the command result reports
+pub async fn capture_and_upload(client: Option<&DeviceClient>, settings: ScreenshotSettings, command_id: Option<i64>) -> Result<ScreenshotInfo, String> {
+    let shot = capture_async(settings).await?;
+    let upload = match client {
+        Some(client) => Some(upload(client, &shot, command_id).await?),
+        None => None,
+    };
+    Ok(ScreenshotInfo {
+        format: shot.format,
+        width: shot.width,
+        height: shot.height,
+        bytes: shot.data.len(),
+        upload,
+    })
+}
+
+/// Capture the screen. Uses the configured format/quality/size unless
+/// overridden, and uploads it when `upload` is true.
+#[tauri::command]
+pub async fn capture_screenshot(
+    app_handle: tauri::AppHandle,
+    format: Option<ImageFormat>,
+    quality: Option<u8>,
+    max_width: Option<u32>,
+    upload: Option<bool>,
+) -> Result<ScreenshotInfo, String> {
+    let mut settings = crate::config::current().screenshot;
+    if let Some(format) = format {
+        settings.format = format;
+    }
+    if let Some(quality) = quality {
+        settings.quality = quality;
+    }
+    if let Some(max_width) = max_width {
+        settings.max_width = max_width;
+    }
+    settings.validate()?;
+
+    if upload.unwrap_or(false) {
+        let config_dir = app_handle.path_resolver().app_config_dir().ok_or("Could not find config dir")?;
+        let client = DeviceClient::from_config_dir(&config_dir)?;
+        return capture_and_upload(Some(&client), settings, None).await;
+    }
+    capture_and_upload(None, settings, None).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_only_given_fields() {
+        let base = ScreenshotSettings::default();
+        let settings = base.with_overrides(&json!({ "format": "png", "max_width": 0 })).unwrap();
+        assert_eq!(settings.format, ImageFormat::Png);
+        assert_eq!(settings.max_width, 0);
+        assert_eq!(settings.quality, base.quality);
+
+        assert_eq!(base.with_overrides(&json!({})).unwrap(), base);
+    }
+
+    #[test]
+    fn overrides_clamp_and_validate_quality() {
+        let base = ScreenshotSettings::default();
+        assert_eq!(base.with_overrides(&json!({ "quality": 500 })).unwrap().quality, 100);
+        assert!(base.with_overrides(&json!({ "quality": 0 })).is_err());
+    }
+
+    #[test]
+    fn overrides_reject_unknown_format() {
+        let err = ScreenshotSettings::default().with_overrides(&json!({ "format": "bmp" })).unwrap_err();
+        assert!(err.starts_with("Invalid format"), "{}", err);
+    }
+}
@@ -0,0 +1,588 @@
+// System settings touched by the kiosk setup, behind a platform abstraction.
+//
+// Settings are addressed the way the registry does it (`HKLM\...\Winlogon`,
+// value `Shell`), since that is what the kiosk journal records.
+//
+// - Windows: the registry, through the native API
+// - Linux: the session script (`~/.xsession`) stands in for the Winlogon shell
+//   (a script of the user's own is kept aside and put back untouched on
+//   teardown), and XDG autostart entries (`~/.config/autostart`, `/etc/xdg/autostart`)
+//   for the Run keys. The Windows lockdown policies have no equivalent.
+// - Memory: a map, never touching the machine. Selected with
+//   `PRIMUS_SETTINGS_MEMORY=1`, and used to exercise the kiosk setup and
+//   teardown logic on CI.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+pub const WINLOGON: &str = "HKLM\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\Winlogon";
+pub const USER_RUN: &str = "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+pub const MACHINE_RUN: &str = "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Run";
+pub const USER_POLICIES: &str = "HKCU\\Software\\Microsoft\\Windows\\CurrentVersion\\Policies\\System";
+/// Value name of the Primus entries in the Run keys
+pub const RUN_VALUE: &str = "Primus";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum RegData {
+    Sz(String),
+    Dword(u32),
+}
+
+impl fmt::Display for RegData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegData::Sz(s) => write!(f, "\"{}\"", s),
+            RegData::Dword(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+pub trait SettingsBackend: Send + Sync {
+    /// Whether this platform has an equivalent of `key\name`
+    fn supports(&self, key: &str, name: &str) -> bool;
+    /// None if the value does not exist
+    fn read(&self, key: &str, name: &str) -> Result<Option<RegData>, String>;
+    fn write(&self, key: &str, name: &str, value: &RegData) -> Result<(), String>;
+    /// Deleting a value that does not exist is not an error
+    fn delete(&self, key: &str, name: &str) -> Result<(), String>;
+    /// Whether machine-wide settings can be written
+    fn is_elevated(&self) -> bool;
+    /// The OS waits for a restart, or the session shell is not the configured
+    /// one yet (`primus_shell`: Primus is configured as the shell)
+    fn restart_pending(&self, primus_shell: bool) -> Result<bool, String>;
+}
+
+// ---------------------------------------------------------------------------
+// Windows registry
+
+#[cfg(target_os = "windows")]
+pub struct WindowsRegistry;
+
+#[cfg(target_os = "windows")]
+mod registry {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+
+    use winapi::shared::minwindef::{DWORD, HKEY};
+    use winapi::shared::winerror::{ERROR_FILE_NOT_FOUND, ERROR_SUCCESS};
+    use winapi::um::winnt::{KEY_QUERY_VALUE, KEY_SET_VALUE, KEY_WOW64_64KEY, REG_DWORD, REG_EXPAND_SZ, REG_OPTION_NON_VOLATILE, REG_SZ};
+    use winapi::um::winreg::{
+        RegCloseKey, RegCreateKeyExW, RegDeleteValueW, RegOpenKeyExW, RegQueryValueExW, RegSetValueExW, HKEY_CURRENT_USER,
+        HKEY_LOCAL_MACHINE,
+    };
+
+    use super::RegData;
+
+    fn wide(s: &str) -> Vec<u16> {
+        OsStr::new(s).encode_wide().chain(Some(0)).collect()
+    }
+
+    fn os_error(code: i32) -> String {
+        std::io::Error::from_raw_os_error(code).to_string()
+    }
+
+    /// An open key, closed on drop
+    pub(super) struct Key(HKEY);
+
+    impl Drop for Key {
+        fn drop(&mut self) {
+            unsafe {
+                RegCloseKey(self.0);
+            }
+        }
+    }
+
+    fn split(path: &str) -> Result<(HKEY, Vec<u16>), String> {
+        let (root, sub) = path.split_once('\\').ok_or_else(|| format!("Invalid registry key: {}", path))?;
+        let root = match root.to_ascii_uppercase().as_str() {
+            "HKLM" | "HKEY_LOCAL_MACHINE" => HKEY_LOCAL_MACHINE,
+            "HKCU" | "HKEY_CURRENT_USER" => HKEY_CURRENT_USER,
+            _ => return Err(format!("Unsupported registry root: {}", root)),
+        };
+        Ok((root, wide(sub)))
+    }
+
+    /// None if the key does not exist
+    pub(super) fn open(path: &str, write: bool) -> Result<Option<Key>, String> {
+        let (root, sub) = split(path)?;
+        let access = if write { KEY_SET_VALUE } else { KEY_QUERY_VALUE };
+        let mut hkey: HKEY = std::ptr::null_mut();
+        let status = unsafe { RegOpenKeyExW(root, sub.as_ptr(), 0, access | KEY_WOW64_64KEY, &mut hkey) };
+        match status as DWORD {
+            ERROR_SUCCESS => Ok(Some(Key(hkey))),
+            ERROR_FILE_NOT_FOUND => Ok(None),
+            _ => Err(format!("Cannot open {}: {}", path, os_error(status))),
+        }
+    }
+
+    pub(super) fn create(path: &str) -> Result<Key, String> {
+        let (root, sub) = split(path)?;
+        let mut hkey: HKEY = std::ptr::null_mut();
+        let status = unsafe {
+            RegCreateKeyExW(
+                root,
+                sub.as_ptr(),
+                0,
+                std::ptr::null_mut(),
+                REG_OPTION_NON_VOLATILE,
+                KEY_SET_VALUE | KEY_WOW64_64KEY,
+                std::ptr::null_mut(),
+                &mut hkey,
+                std::ptr::null_mut(),
+            )
+        };
+        if status as DWORD == ERROR_SUCCESS {
+            Ok(Key(hkey))
+        } else {
+            Err(format!("Cannot create {}: {}", path, os_error(status)))
+        }
+    }
+
+    pub(super) fn query(key: &Key, name: &str) -> Result<Option<RegData>, String> {
+        let name_w = wide(name);
+        let mut kind: DWORD = 0;
+        let mut len: DWORD = 0;
+        let status = unsafe { RegQueryValueExW(key.0, name_w.as_ptr(), std::ptr::null_mut(), &mut kind, std::ptr::null_mut(), &mut len) };
+        match status as DWORD {
+            ERROR_SUCCESS => {}
+            ERROR_FILE_NOT_FOUND => return Ok(None),
+            _ => return Err(format!("Cannot read {}: {}", name, os_error(status))),
+        }
+        let mut buf = vec![0u8; len as usize];
+        let status = unsafe { RegQueryValueExW(key.0, name_w.as_ptr(), std::ptr::null_mut(), &mut kind, buf.as_mut_ptr(), &mut len) };
+        if status as DWORD != ERROR_SUCCESS {
+            return Err(format!("Cannot read {}: {}", name, os_error(status)));
+        }
+        buf.truncate(len as usize);
+        match kind {
+            REG_SZ | REG_EXPAND_SZ => {
+                let units: Vec<u16> = buf.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                let end = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+                Ok(Some(RegData::Sz(String::from_utf16_lossy(&units[..end]))))
+            }
+            REG_DWORD if buf.len() >= 4 => Ok(Some(RegData::Dword(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]])))),
+            other => Err(format!("Unsupported registry type {} for {}", other, name)),
+        }
+    }
+
+    pub(super) fn set(key: &Key, name: &str, value: &RegData) -> Result<(), String> {
+        let (kind, data) = match value {
+            RegData::Sz(s) => (REG_SZ, wide(s).iter().flat_map(|u| u.to_le_bytes()).collect::<Vec<u8>>()),
+            RegData::Dword(n) => (REG_DWORD, n.to_le_bytes().to_vec()),
+        };
+        let status = unsafe { RegSetValueExW(key.0, wide(name).as_ptr(), 0, kind, data.as_ptr(), data.len() as DWORD) };
+        if status as DWORD == ERROR_SUCCESS {
+            Ok(())
+        } else {
+            Err(format!("Cannot write {}: {}", name, os_error(status)))
+        }
+    }
+
+    pub(super) fn delete(key: &Key, name: &str) -> Result<(), String> {
+        let status = unsafe { RegDeleteValueW(key.0, wide(name).as_ptr()) };
+        match status as DWORD {
+            ERROR_SUCCESS | ERROR_FILE_NOT_FOUND => Ok(()),
+            _ => Err(format!("Cannot delete {}: {}", name, os_error(status))),
+        }
+    }
+
+    pub(super) fn is_elevated() -> bool {
+        use winapi::um::handleapi::CloseHandle;
+        use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+        use winapi::um::securitybaseapi::GetTokenInformation;
+        use winapi::um::winnt::{TokenElevation, HANDLE, TOKEN_ELEVATION, TOKEN_QUERY};
+
+        unsafe {
+            let mut token: HANDLE = std::ptr::null_mut();
+            if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+                return false;
+            }
+            let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+            let mut len: DWORD = 0;
+            let ok = GetTokenInformation(
+                token,
+                TokenElevation,
+                &mut elevation as *mut TOKEN_ELEVATION as *mut _,
+                std::mem::size_of::<TOKEN_ELEVATION>() as DWORD,
+                &mut len,
+            );
+            CloseHandle(token);
+            ok != 0 && elevation.TokenIsElevated != 0
+        }
+    }
+}
+
+// Set by Windows servicing and Windows Update until the next restart
+#[cfg(target_os = "windows")]
+const REBOOT_PENDING_KEYS: &[&str] = &[
+    "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Component Based Servicing\\RebootPending",
+    "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\WindowsUpdate\\Auto Update\\RebootRequired",
+];
+
+#[cfg(target_os = "windows")]
+impl SettingsBackend for WindowsRegistry {
+    fn supports(&self, _key: &str, _name: &str) -> bool {
+        true
+    }
+
+    fn read(&self, key: &str, name: &str) -> Result<Option<RegData>, String> {
+        match registry::open(key, false)? {
+            Some(handle) => registry::query(&handle, name).map_err(|e| format!("{}: {}", key, e)),
+            None => Ok(None),
+        }
+    }
+
+    fn write(&self, key: &str, name: &str, value: &RegData) -> Result<(), String> {
+        // Policies\System does not exist until a policy is set
+        let handle = registry::create(key)?;
+        registry::set(&handle, name, value).map_err(|e| format!("{}: {}", key, e))
+    }
+
+    fn delete(&self, key: &str, name: &str) -> Result<(), String> {
+        match registry::open(key, true)? {
+            Some(handle) => registry::delete(&handle, name).map_err(|e| format!("{}: {}", key, e)),
+            None => Ok(()),
+        }
+    }
+
+    fn is_elevated(&self) -> bool {
+        registry::is_elevated()
+    }
+
+    fn restart_pending(&self, primus_shell: bool) -> Result<bool, String> {
+        for key in REBOOT_PENDING_KEYS {
+            if registry::open(key, false)?.is_some() {
+                return Ok(true);
+            }
+        }
+        // The shell is only started at logon: Explorer running under a Primus
+        // shell (or missing under Explorer) means the change is still pending
+        use sysinfo::{ProcessExt, ProcessRefreshKind, System, SystemExt};
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(ProcessRefreshKind::new());
+        let explorer_running = sys.processes().values().any(|p| p.name().eq_ignore_ascii_case("explorer.exe"));
+        Ok(primus_shell == explorer_running)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Linux session and autostart
+
+#[cfg(not(target_os = "windows"))]
+pub struct LinuxSession;
+
+#[cfg(not(target_os = "windows"))]
+mod session {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    const AUTOSTART_FILE: &str = "primus.desktop";
+    const MACHINE_AUTOSTART_DIR: &str = "/etc/xdg/autostart";
+    const SESSION_MARKER: &str = "# Written by Primus kiosk setup";
+
+    /// Where a setting lives on Linux
+    pub(super) enum Location {
+        /// Started by the display manager's "default Xsession" instead of a desktop
+        SessionScript(PathBuf),
+        Autostart(PathBuf),
+    }
+
+    pub(super) fn locate(key: &str, name: &str) -> Option<Location> {
+        let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        match (key, name) {
+            (super::WINLOGON, "Shell") => Some(Location::SessionScript(home().join(".xsession"))),
+            (super::USER_RUN, super::RUN_VALUE) => {
+                let config = dirs::config_dir().unwrap_or_else(|| home().join(".config"));
+                Some(Location::Autostart(config.join("autostart").join(AUTOSTART_FILE)))
+            }
+            (super::MACHINE_RUN, super::RUN_VALUE) => {
+                Some(Location::Autostart(PathBuf::from(MACHINE_AUTOSTART_DIR).join(AUTOSTART_FILE)))
+            }
+            _ => None,
+        }
+    }
+
+    fn unquote(s: &str) -> String {
+        s.trim().trim_matches('"').to_string()
+    }
+
+    /// The last `exec` of a session script, or the whole script
+    fn session_command(data: &str) -> String {
+        data.lines()
+            .rev()
+            .find_map(|l| l.trim().strip_prefix("exec "))
+            .map(unquote)
+            .unwrap_or_else(|| data.trim().to_string())
+    }
+
+    /// `~/.xsession.primus-bak`: the user's own script while Primus replaces it
+    fn backup_path(path: &Path) -> PathBuf {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        path.with_file_name(format!("{}.primus-bak", name))
+    }
+
+    /// The command the file runs: the `exec` line of a session script, the
+    /// `Exec=` key of a desktop entry
+    pub(super) fn read(location: &Location) -> Result<Option<String>, String> {
+        let path = match location {
+            Location::SessionScript(path) | Location::Autostart(path) => path,
+        };
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+        let command = match location {
+            Location::SessionScript(_) => session_command(&data),
+            Location::Autostart(_) => data.lines().find_map(|l| l.trim().strip_prefix("Exec=")).map(unquote).unwrap_or_default(),
+        };
+        Ok(Some(command))
+    }
+
+    pub(super) fn write(location: &Location, command: &str) -> Result<(), String> {
+        if let Location::SessionScript(path) = location {
+            if restore_session_script(path, command)? {
+                return Ok(());
+            }
+            back_up_session_script(path)?;
+        }
+        let (path, data) = match location {
+            Location::SessionScript(path) => (path, format!("#!/bin/sh\n{}\nexec \"{}\"\n", SESSION_MARKER, command)),
+            Location::Autostart(path) => (
+                path,
+                format!("[Desktop Entry]\nType=Application\nName=Primus\nExec=\"{}\"\nX-GNOME-Autostart-enabled=true\n", command),
+            ),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        fs::write(path, data).map_err(|e| format!("Cannot write {}: {}", path.display(), e))?;
+        if let Location::SessionScript(_) = location {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path, fs::Permissions::from_mode(0o755)).map_err(|e| format!("Cannot chmod {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Put the user's script back if it is the one that runs `command`
+    fn restore_session_script(path: &Path, command: &str) -> Result<bool, String> {
+        let backup = backup_path(path);
+        match fs::read_to_string(&backup) {
+            Ok(data) if session_command(&data) == command => {
+                fs::rename(&backup, path).map_err(|e| format!("Cannot restore {}: {}", path.display(), e))?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Keep a script Primus didn't write before it is replaced; an existing
+    /// backup is never overwritten
+    fn back_up_session_script(path: &Path) -> Result<(), String> {
+        let backup = backup_path(path);
+        if backup.exists() {
+            return Ok(());
+        }
+        match fs::read_to_string(path) {
+            Ok(data) if !data.contains(SESSION_MARKER) => {
+                fs::copy(path, &backup).map_err(|e| format!("Cannot back up {}: {}", path.display(), e))?;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(super) fn delete(location: &Location) -> Result<(), String> {
+        let path = match location {
+            Location::SessionScript(path) | Location::Autostart(path) => path,
+        };
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Cannot delete {}: {}", path.display(), e)),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const EXE: &str = "/opt/primus/primus-client";
+        const USER_SCRIPT: &str = "#!/bin/sh\nxrdb -merge ~/.Xresources\nexec startxfce4\n";
+
+        fn temp_dir() -> PathBuf {
+            let dir = std::env::temp_dir().join(format!("primus-settings-test-{}", uuid::Uuid::new_v4().simple()));
+            fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        #[test]
+        fn session_script_roundtrip_keeps_the_user_script() {
+            let dir = temp_dir();
+            let path = dir.join(".xsession");
+            fs::write(&path, USER_SCRIPT).unwrap();
+            let location = Location::SessionScript(path.clone());
+
+            let original = read(&location).unwrap().unwrap();
+            assert_eq!(original, "startxfce4");
+
+            write(&location, EXE).unwrap();
+            assert_eq!(read(&location).unwrap().as_deref(), Some(EXE));
+            // Re-applying must not back up the Primus script over the user's
+            write(&location, EXE).unwrap();
+            assert_eq!(fs::read_to_string(backup_path(&path)).unwrap(), USER_SCRIPT);
+
+            write(&location, &original).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), USER_SCRIPT);
+            assert!(!backup_path(&path).exists());
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn session_script_without_exec_is_restored_verbatim() {
+            let dir = temp_dir();
+            let path = dir.join(".xsession");
+            let script = "xset s off\nxfwm4 &\nfirefox\n";
+            fs::write(&path, script).unwrap();
+            let location = Location::SessionScript(path.clone());
+
+            let original = read(&location).unwrap().unwrap();
+            write(&location, EXE).unwrap();
+            write(&location, &original).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), script);
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn missing_session_script_is_created_and_deleted() {
+            let dir = temp_dir();
+            let path = dir.join(".xsession");
+            let location = Location::SessionScript(path.clone());
+
+            assert_eq!(read(&location).unwrap(), None);
+            write(&location, EXE).unwrap();
+            assert_eq!(read(&location).unwrap().as_deref(), Some(EXE));
+            assert!(!backup_path(&path).exists());
+            delete(&location).unwrap();
+            assert!(!path.exists());
+            delete(&location).unwrap();
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn autostart_entry_roundtrip() {
+            let dir = temp_dir();
+            let location = Location::Autostart(dir.join("autostart").join(AUTOSTART_FILE));
+            write(&location, EXE).unwrap();
+            assert_eq!(read(&location).unwrap().as_deref(), Some(EXE));
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl SettingsBackend for LinuxSession {
+    fn supports(&self, key: &str, name: &str) -> bool {
+        session::locate(key, name).is_some()
+    }
+
+    fn read(&self, key: &str, name: &str) -> Result<Option<RegData>, String> {
+        match session::locate(key, name) {
+            Some(location) => Ok(session::read(&location)?.map(RegData::Sz)),
+            None => Ok(None),
+        }
+    }
+
+    fn write(&self, key: &str, name: &str, value: &RegData) -> Result<(), String> {
+        let location = session::locate(key, name).ok_or_else(|| format!("{}\\{} has no Linux equivalent", key, name))?;
+        match value {
+            RegData::Sz(command) => session::write(&location, command),
+            RegData::Dword(_) => Err(format!("{}\\{} takes a command, not a number", key, name)),
+        }
+    }
+
+    fn delete(&self, key: &str, name: &str) -> Result<(), String> {
+        match session::locate(key, name) {
+            Some(location) => session::delete(&location),
+            None => Ok(()),
+        }
+    }
+
+    fn is_elevated(&self) -> bool {
+        // Effective UID is the second field of `Uid:`
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|s| s.lines().find_map(|l| l.strip_prefix("Uid:")).and_then(|l| l.split_whitespace().nth(1)).map(|uid| uid == "0"))
+            .unwrap_or(false)
+    }
+
+    fn restart_pending(&self, primus_shell: bool) -> Result<bool, String> {
+        // Debian/Ubuntu flag for updates that need a restart
+        if std::path::Path::new("/run/reboot-required").exists() {
+            return Ok(true);
+        }
+        // A desktop environment is running although Primus is the session
+        let desktop_running = std::env::var("XDG_CURRENT_DESKTOP").map(|d| !d.is_empty()).unwrap_or(false);
+        Ok(primus_shell && desktop_running)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// In memory
+
+/// Keeps every value in a map and never touches the machine
+#[derive(Default)]
+pub struct MemorySettings {
+    values: Mutex<BTreeMap<(String, String), RegData>>,
+}
+
+impl SettingsBackend for MemorySettings {
+    fn supports(&self, _key: &str, _name: &str) -> bool {
+        true
+    }
+
+    fn read(&self, key: &str, name: &str) -> Result<Option<RegData>, String> {
+        Ok(self.values.lock().unwrap().get(&(key.to_string(), name.to_string())).cloned())
+    }
+
+    fn write(&self, key: &str, name: &str, value: &RegData) -> Result<(), String> {
+        log_debug!("[Settings] Memory: {}\\{} = {}", key, name, value);
+        self.values.lock().unwrap().insert((key.to_string(), name.to_string()), value.clone());
+        Ok(())
+    }
+
+    fn delete(&self, key: &str, name: &str) -> Result<(), String> {
+        log_debug!("[Settings] Memory: delete {}\\{}", key, name);
+        self.values.lock().unwrap().remove(&(key.to_string(), name.to_string()));
+        Ok(())
+    }
+
+    fn is_elevated(&self) -> bool {
+        true
+    }
+
+    fn restart_pending(&self, _primus_shell: bool) -> Result<bool, String> {
+        Ok(false)
+    }
+}
+
+/// Backend for the current platform (or the in-memory one if requested)
+pub fn platform() -> Arc<dyn SettingsBackend> {
+    lazy_static::lazy_static! {
+        static ref BACKEND: Arc<dyn SettingsBackend> = {
+            if std::env::var("PRIMUS_SETTINGS_MEMORY").map(|v| v == "1").unwrap_or(false) {
+                Arc::new(MemorySettings::default())
+            } else {
+                #[cfg(target_os = "windows")]
+                let backend: Arc<dyn SettingsBackend> = Arc::new(WindowsRegistry);
+                #[cfg(not(target_os = "windows"))]
+                let backend: Arc<dyn SettingsBackend> = Arc::new(LinuxSession);
+                backend
+            }
+        };
+    }
+    BACKEND.clone()
+}
@@ -0,0 +1,544 @@
+// Keyboard shortcut blocking.
+//
+// What is blocked depends on the kiosk mode and comes from `kiosk.shortcuts` in
+// the client config, which the backend can push with the `kiosk.shortcuts`
+// command. Shortcuts are written like "Ctrl+Shift+Esc", "Alt+Tab" or "Win"; a
+// lone modifier blocks that key itself.
+//
+// - Windows: a low-level keyboard hook on its own message-loop thread. A
+//   shortcut also matches when extra modifiers are held.
+// - Linux: passive key grabs on the X11 root window, which swallow the
+//   combination before any other client sees it. X11 grabs need the exact
+//   modifiers (Caps/Num Lock are ignored). Native Wayland sessions are not
+//   covered; the compositor owns the keyboard there.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::kiosk::KioskMode;
+
+pub const CTRL: u8 = 1;
+pub const ALT: u8 = 2;
+pub const SHIFT: u8 = 4;
+pub const WIN: u8 = 8;
+
+/// A key other than a modifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    /// Windows virtual-key code
+    pub vk: u32,
+    /// X11 keysym
+    pub keysym: u32,
+}
+
+// name, virtual-key code, keysym
+const NAMED_KEYS: &[(&str, u32, u32)] = &[
+    ("tab", 0x09, 0xff09),
+    ("esc", 0x1b, 0xff1b),
+    ("escape", 0x1b, 0xff1b),
+    ("enter", 0x0d, 0xff0d),
+    ("return", 0x0d, 0xff0d),
+    ("space", 0x20, 0x0020),
+    ("backspace", 0x08, 0xff08),
+    ("delete", 0x2e, 0xffff),
+    ("del", 0x2e, 0xffff),
+    ("insert", 0x2d, 0xff63),
+    ("home", 0x24, 0xff50),
+    ("end", 0x23, 0xff57),
+    ("pageup", 0x21, 0xff55),
+    ("pagedown", 0x22, 0xff56),
+    ("left", 0x25, 0xff51),
+    ("up", 0x26, 0xff52),
+    ("right", 0x27, 0xff53),
+    ("down", 0x28, 0xff54),
+    ("printscreen", 0x2c, 0xff61),
+];
+
+fn parse_key(name: &str) -> Option<Key> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(&(_, vk, keysym)) = NAMED_KEYS.iter().find(|(n, _, _)| *n == lower) {
+        return Some(Key { vk, keysym });
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        // F1-F24: VK_F1 = 0x70, XK_F1 = 0xffbe
+        return (1..=24).contains(&n).then(|| Key { vk: 0x6f + n, keysym: 0xffbd + n });
+    }
+    let mut chars = lower.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_lowercase() => Some(Key { vk: c.to_ascii_uppercase() as u32, keysym: c as u32 }),
+        (Some(c), None) if c.is_ascii_digit() => Some(Key { vk: c as u32, keysym: c as u32 }),
+        _ => None,
+    }
+}
+
+fn parse_modifier(name: &str) -> Option<u8> {
+    match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Some(CTRL),
+        "alt" => Some(ALT),
+        "shift" => Some(SHIFT),
+        "win" | "super" | "meta" => Some(WIN),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    pub modifiers: u8,
+    /// `None` for a lone modifier ("Win"): the modifier key itself
+    pub key: Option<Key>,
+}
+
+impl FromStr for Shortcut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = 0;
+        let mut key = None;
+        for part in s.split('+').map(str::trim) {
+            if part.is_empty() {
+                return Err(format!("Invalid shortcut '{}'", s));
+            }
+            if let Some(modifier) = parse_modifier(part) {
+                modifiers |= modifier;
+            } else if key.is_some() {
+                return Err(format!("Shortcut '{}' has more than one key", s));
+            } else {
+                key = Some(parse_key(part).ok_or_else(|| format!("Unknown key '{}' in shortcut '{}'", part, s))?);
+            }
+        }
+        if key.is_none() && modifiers.count_ones() != 1 {
+            return Err(format!("Shortcut '{}' needs a key", s));
+        }
+        Ok(Shortcut { modifiers, key })
+    }
+}
+
+impl Shortcut {
+    /// Virtual-key codes of a lone modifier (generic, left and right)
+    fn modifier_vks(self) -> &'static [u32] {
+        match self.modifiers {
+            CTRL => &[0x11, 0xa2, 0xa3],
+            ALT => &[0x12, 0xa4, 0xa5],
+            SHIFT => &[0x10, 0xa0, 0xa1],
+            WIN => &[0x5b, 0x5c],
+            _ => &[],
+        }
+    }
+
+    /// Whether a key event with modifiers `held` should be swallowed. Lone
+    /// modifiers are blocked on release too, so e.g. the Start menu never opens.
+    pub fn blocks_vk(self, vk: u32, held: u8, key_down: bool) -> bool {
+        match self.key {
+            Some(key) => key_down && key.vk == vk && held & self.modifiers == self.modifiers,
+            None => self.modifier_vks().contains(&vk),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ShortcutPolicy {
+    /// Nobody logged in
+    pub locked: Vec<String>,
+    /// Logged in, Primus in front
+    pub session: Vec<String>,
+    /// A game launched through Primus is running
+    pub in_game: Vec<String>,
+}
+
+impl Default for ShortcutPolicy {
+    fn default() -> Self {
+        let mut strict: Vec<String> = ["Win", "Ctrl+Esc", "Ctrl+Shift+Esc", "Alt+Tab", "Alt+Esc", "Alt+F4", "Alt+Enter", "Alt+Space"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        strict.extend((1..=24).filter(|n| *n != 4).map(|n| format!("Alt+F{}", n)));
+        Self {
+            locked: strict.clone(),
+            session: strict,
+            in_game: vec!["Win".to_string()],
+        }
+    }
+}
+
+impl ShortcutPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        for shortcut in self.locked.iter().chain(&self.session).chain(&self.in_game) {
+            shortcut.parse::<Shortcut>()?;
+        }
+        Ok(())
+    }
+
+    /// Shortcuts to block in `mode`; nothing while a dialog is open or in
+    /// maintenance
+    pub fn for_mode(&self, mode: KioskMode) -> Vec<Shortcut> {
+        let list = match mode {
+            KioskMode::Locked => &self.locked,
+            KioskMode::Session => &self.session,
+            KioskMode::AppRunning => &self.in_game,
+            KioskMode::DialogAllowed | KioskMode::Maintenance => return Vec::new(),
+        };
+        list.iter().filter_map(|s| s.parse().ok()).collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Windows: low-level keyboard hook
+
+#[cfg(target_os = "windows")]
+mod backend {
+    use std::sync::{mpsc, RwLock};
+
+    use winapi::ctypes::c_int;
+    use winapi::shared::minwindef::{LPARAM, LRESULT, WPARAM};
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::processthreadsapi::GetCurrentThreadId;
+    use winapi::um::winuser::{
+        CallNextHookEx, DispatchMessageW, GetAsyncKeyState, GetMessageW, PostThreadMessageW, SetWindowsHookExW,
+        TranslateMessage, UnhookWindowsHookEx, KBDLLHOOKSTRUCT, LLKHF_ALTDOWN, MSG, VK_CONTROL, VK_LWIN, VK_RWIN,
+        VK_SHIFT, WH_KEYBOARD_LL, WM_KEYDOWN, WM_QUIT, WM_SYSKEYDOWN,
+    };
+
+    use super::{Shortcut, ALT, CTRL, SHIFT, WIN};
+
+    // Read by the hook procedure, which gets no context pointer
+    static RULES: RwLock<Vec<Shortcut>> = RwLock::new(Vec::new());
+
+    fn held_modifiers(flags: u32) -> u8 {
+        let down = |vk: c_int| unsafe { GetAsyncKeyState(vk) } < 0;
+        let mut held = 0;
+        if down(VK_CONTROL) {
+            held |= CTRL;
+        }
+        if flags & LLKHF_ALTDOWN != 0 {
+            held |= ALT;
+        }
+        if down(VK_SHIFT) {
+            held |= SHIFT;
+        }
+        if down(VK_LWIN) || down(VK_RWIN) {
+            held |= WIN;
+        }
+        held
+    }
+
+    extern "system" fn keyboard_hook_proc(code: c_int, w_param: WPARAM, l_param: LPARAM) -> LRESULT {
+        if code >= 0 {
+            let kbd = unsafe { &*(l_param as *const KBDLLHOOKSTRUCT) };
+            let key_down = w_param == WM_KEYDOWN as usize || w_param == WM_SYSKEYDOWN as usize;
+            // Never wait inside the hook; a rule update in progress lets the key through
+            if let Ok(rules) = RULES.try_read() {
+                if !rules.is_empty() {
+                    let held = held_modifiers(kbd.flags);
+                    if rules.iter().any(|s| s.blocks_vk(kbd.vkCode, held, key_down)) {
+                        return 1;
+                    }
+                }
+            }
+        }
+        // The hook handle argument is ignored by Windows
+        unsafe { CallNextHookEx(std::ptr::null_mut(), code, w_param, l_param) }
+    }
+
+    /// Low-level hooks are called on the installing thread's message loop
+    fn spawn_hook_thread() -> Result<u32, String> {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || unsafe {
+            let hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), GetModuleHandleW(std::ptr::null()), 0);
+            if hook.is_null() {
+                let _ = tx.send(Err(format!("Failed to install keyboard hook: {}", std::io::Error::last_os_error())));
+                return;
+            }
+            let _ = tx.send(Ok(GetCurrentThreadId()));
+            let mut msg: MSG = std::mem::zeroed();
+            while GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+            UnhookWindowsHookEx(hook);
+        });
+        rx.recv().map_err(|_| "Keyboard hook thread exited".to_string())?
+    }
+
+    #[derive(Default)]
+    pub struct Backend {
+        hook_thread: Option<u32>,
+    }
+
+    impl Backend {
+        pub fn apply(&mut self, rules: &[Shortcut]) -> Result<(), String> {
+            *RULES.write().unwrap() = rules.to_vec();
+            if self.hook_thread.is_none() {
+                self.hook_thread = Some(spawn_hook_thread()?);
+            }
+            Ok(())
+        }
+
+        pub fn is_installed(&self) -> bool {
+            self.hook_thread.is_some()
+        }
+
+        pub fn stop(&mut self) {
+            RULES.write().unwrap().clear();
+            if let Some(thread) = self.hook_thread.take() {
+                unsafe {
+                    PostThreadMessageW(thread, WM_QUIT, 0, 0);
+                }
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Linux: X11 passive grabs
+
+#[cfg(not(target_os = "windows"))]
+mod backend {
+    use std::sync::Arc;
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt, GrabMode, ModMask, Window};
+    use x11rb::rust_connection::RustConnection;
+
+    use super::{Shortcut, ALT, CTRL, SHIFT, WIN};
+
+    // Super_L, Super_R, Control_L/R, Alt_L/R, Shift_L/R
+    const MODIFIER_KEYSYMS: &[(u8, &[u32])] = &[
+        (WIN, &[0xffeb, 0xffec]),
+        (CTRL, &[0xffe3, 0xffe4]),
+        (ALT, &[0xffe9, 0xffea]),
+        (SHIFT, &[0xffe1, 0xffe2]),
+    ];
+
+    fn mod_mask(modifiers: u8) -> ModMask {
+        let mut mask = ModMask::from(0u16);
+        if modifiers & CTRL != 0 {
+            mask = mask | ModMask::CONTROL;
+        }
+        if modifiers & ALT != 0 {
+            mask = mask | ModMask::M1;
+        }
+        if modifiers & SHIFT != 0 {
+            mask = mask | ModMask::SHIFT;
+        }
+        if modifiers & WIN != 0 {
+            mask = mask | ModMask::M4;
+        }
+        mask
+    }
+
+    struct Display {
+        conn: Arc<RustConnection>,
+        root: Window,
+    }
+
+    #[derive(Default)]
+    pub struct Backend {
+        display: Option<Display>,
+        /// (keycode, modifiers) currently grabbed
+        grabbed: Vec<(u8, ModMask)>,
+    }
+
+    impl Backend {
+        fn connect(&mut self) -> Result<&Display, String> {
+            if self.display.is_none() {
+                let (conn, screen) = x11rb::connect(None).map_err(|e| format!("No X11 display for shortcut blocking: {}", e))?;
+                let conn = Arc::new(conn);
+                let root = conn.setup().roots[screen].root;
+                // Grabbed keys are delivered to us; drain and drop them
+                let events = conn.clone();
+                std::thread::spawn(move || while events.wait_for_event().is_ok() {});
+                self.display = Some(Display { conn, root });
+            }
+            Ok(self.display.as_ref().unwrap())
+        }
+
+        fn keycodes(conn: &RustConnection, keysym: u32) -> Result<Vec<u8>, String> {
+            let setup = conn.setup();
+            let (min, max) = (setup.min_keycode, setup.max_keycode);
+            let mapping = conn
+                .get_keyboard_mapping(min, max - min + 1)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            let per = mapping.keysyms_per_keycode.max(1) as usize;
+            Ok(mapping
+                .keysyms
+                .chunks(per)
+                .enumerate()
+                .filter(|(_, syms)| syms.contains(&keysym))
+                .map(|(i, _)| min + i as u8)
+                .collect())
+        }
+
+        fn ungrab_all(&mut self) {
+            if let Some(display) = &self.display {
+                for (keycode, mask) in self.grabbed.drain(..) {
+                    let _ = display.conn.ungrab_key(keycode, display.root, mask);
+                }
+                let _ = display.conn.flush();
+            }
+        }
+
+        pub fn apply(&mut self, rules: &[Shortcut]) -> Result<(), String> {
+            self.ungrab_all();
+            if rules.is_empty() {
+                return Ok(());
+            }
+            let display = self.connect()?;
+            let (conn, root) = (display.conn.clone(), display.root);
+
+            let mut grabs: Vec<(u8, ModMask)> = Vec::new();
+            for rule in rules {
+                match rule.key {
+                    Some(key) => {
+                        // Same combination with Caps Lock and/or Num Lock on
+                        let base = mod_mask(rule.modifiers);
+                        for keycode in Self::keycodes(&conn, key.keysym)? {
+                            for lock in [ModMask::from(0u16), ModMask::LOCK, ModMask::M2, ModMask::LOCK | ModMask::M2] {
+                                grabs.push((keycode, base | lock));
+                            }
+                        }
+                    }
+                    None => {
+                        let keysyms = MODIFIER_KEYSYMS.iter().find(|(m, _)| *m == rule.modifiers).map(|(_, k)| *k).unwrap_or(&[]);
+                        for keysym in keysyms {
+                            for keycode in Self::keycodes(&conn, *keysym)? {
+                                grabs.push((keycode, ModMask::ANY));
+                            }
+                        }
+                    }
+                }
+            }
+
+            for (keycode, mask) in grabs {
+                let grabbed = conn
+                    .grab_key(false, root, mask, keycode, GrabMode::ASYNC, GrabMode::ASYNC)
+                    .map_err(|e| e.to_string())
+                    .and_then(|cookie| cookie.check().map_err(|e| e.to_string()));
+                match grabbed {
+                    Ok(()) => self.grabbed.push((keycode, mask)),
+                    // Usually another client (the window manager) owns it
+                    Err(e) => log_warn!("[Shortcuts] Could not grab keycode {} ({:?}): {}", keycode, mask, e),
+                }
+            }
+            conn.flush().map_err(|e| e.to_string())
+        }
+
+        pub fn is_installed(&self) -> bool {
+            !self.grabbed.is_empty()
+        }
+
+        pub fn stop(&mut self) {
+            self.ungrab_all();
+        }
+    }
+}
+
+/// Owns the platform blocker; rules are swapped whenever the kiosk mode or
+/// the policy changes
+#[derive(Default)]
+pub struct ShortcutBlocker {
+    backend: backend::Backend,
+    active: Vec<Shortcut>,
+}
+
+impl ShortcutBlocker {
+    pub fn apply(&mut self, rules: Vec<Shortcut>) -> Result<(), String> {
+        if rules == self.active {
+            return Ok(());
+        }
+        self.backend.apply(&rules)?;
+        log_info!("[Shortcuts] Blocking {} shortcuts", rules.len());
+        self.active = rules;
+        Ok(())
+    }
+
+    /// Hook running / keys grabbed
+    pub fn is_installed(&self) -> bool {
+        self.backend.is_installed()
+    }
+
+    pub fn stop(&mut self) {
+        self.backend.stop();
+        self.active.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shortcut(s: &str) -> Shortcut {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let parsed = shortcut("Ctrl+Shift+Esc");
+        assert_eq!(parsed.modifiers, CTRL | SHIFT);
+        assert_eq!(parsed.key, parse_key("esc"));
+        assert_eq!(shortcut(" alt + TAB ").key, Some(Key { vk: 0x09, keysym: 0xff09 }));
+        assert_eq!(shortcut("Super").modifiers, WIN);
+        assert_eq!(shortcut("Super").key, None);
+    }
+
+    #[test]
+    fn parses_key_names() {
+        assert_eq!(parse_key("F1"), Some(Key { vk: 0x70, keysym: 0xffbe }));
+        assert_eq!(parse_key("f24"), Some(Key { vk: 0x87, keysym: 0xffd5 }));
+        assert_eq!(parse_key("a"), Some(Key { vk: 'A' as u32, keysym: 'a' as u32 }));
+        assert_eq!(parse_key("7"), Some(Key { vk: '7' as u32, keysym: '7' as u32 }));
+        assert_eq!(parse_key("Escape"), parse_key("esc"));
+        assert_eq!(parse_key("F0"), None);
+        assert_eq!(parse_key("F25"), None);
+        assert_eq!(parse_key("ab"), None);
+        assert_eq!(parse_key("é"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_shortcuts() {
+        for s in ["Ctrl+A+B", "Ctrl", "Ctrl+Alt", "", "Ctrl+", "+A", "Ctrl+Nope"] {
+            assert!(s.parse::<Shortcut>().is_err(), "accepted '{}'", s);
+        }
+    }
+
+    #[test]
+    fn lone_modifier_blocks_its_keys_on_press_and_release() {
+        let win = shortcut("Win");
+        for vk in [0x5b, 0x5c] {
+            assert!(win.blocks_vk(vk, WIN, true));
+            assert!(win.blocks_vk(vk, 0, false));
+        }
+        assert!(!win.blocks_vk(0x11, CTRL, true));
+    }
+
+    #[test]
+    fn combination_blocks_on_key_down_with_extra_modifiers() {
+        let alt_tab = shortcut("Alt+Tab");
+        assert!(alt_tab.blocks_vk(0x09, ALT, true));
+        assert!(alt_tab.blocks_vk(0x09, ALT | SHIFT, true));
+        assert!(alt_tab.blocks_vk(0x09, ALT | CTRL | WIN, true));
+        assert!(!alt_tab.blocks_vk(0x09, ALT, false));
+        assert!(!alt_tab.blocks_vk(0x09, CTRL, true));
+        assert!(!alt_tab.blocks_vk(0x1b, ALT, true));
+    }
+
+    #[test]
+    fn rules_per_mode() {
+        let policy = ShortcutPolicy::default();
+        assert!(policy.validate().is_ok());
+        assert!(policy.for_mode(KioskMode::Locked).contains(&shortcut("Alt+Tab")));
+        assert!(policy.for_mode(KioskMode::Session).contains(&shortcut("Alt+F4")));
+        assert_eq!(policy.for_mode(KioskMode::AppRunning), vec![shortcut("Win")]);
+        assert!(policy.for_mode(KioskMode::DialogAllowed).is_empty());
+        assert!(policy.for_mode(KioskMode::Maintenance).is_empty());
+    }
+
+    #[test]
+    fn validate_rejects_any_bad_entry() {
+        let policy = ShortcutPolicy { in_game: vec!["Win".to_string(), "Ctrl+A+B".to_string()], ..ShortcutPolicy::default() };
+        assert!(policy.validate().is_err());
+    }
+}
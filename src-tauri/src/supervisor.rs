@@ -0,0 +1,329 @@
+// Supervision of games launched through Primus.
+//
+// Every launch becomes a session. The supervisor owns the `Child` handle and
+// follows the whole process tree, so launchers that spawn the real game and
+// exit (or games started through a `steam://` URI, which are matched by their
+// install directory) are tracked until the last process is gone. Sessions are
+// reported through `game-started` / `game-exited` events.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sysinfo::{Pid, PidExt, ProcessExt, ProcessRefreshKind, System, SystemExt};
+
+use crate::agent::AgentEvents;
+use crate::launch::Hook;
+
+/// `SessionInfo` of a newly launched game
+pub const EVENT_GAME_STARTED: &str = "game-started";
+/// `SessionInfo` plus `exit_code`, `duration_secs` and `ended_at`
+pub const EVENT_GAME_EXITED: &str = "game-exited";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a launcher URI may take before the game process shows up
+const URI_LAUNCH_GRACE: Duration = Duration::from_secs(120);
+
+#[derive(Clone, Debug, Default)]
+pub struct LaunchSpec {
+    pub game_id: Option<String>,
+    pub name: String,
+    /// Executable or launcher URI
+    pub target: String,
+    /// Processes running from here belong to the session (URI launches)
+    pub install_dir: Option<PathBuf>,
+    /// Run once the session has ended
+    pub post_exit: Option<Hook>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub game_id: Option<String>,
+    pub name: String,
+    pub target: String,
+    /// PID of the process Primus spawned, `None` for launcher URIs
+    pub pid: Option<u32>,
+    /// All live processes currently attributed to the session
+    pub pids: Vec<u32>,
+    pub started_at: DateTime<Utc>,
+}
+
+struct Session {
+    info: SessionInfo,
+    started: Instant,
+}
+
+/// Called with all live sessions whenever one starts, ends or its process
+/// set changes (empty when nothing is running any more).
+pub type SessionsChanged = Box<dyn Fn(&[SessionInfo]) + Send + Sync>;
+
+pub struct Supervisor {
+    events: Arc<dyn AgentEvents>,
+    on_change: SessionsChanged,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Supervisor {
+    pub fn new(events: Arc<dyn AgentEvents>, on_change: SessionsChanged) -> Self {
+        Self {
+            events,
+            on_change,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Spawn `cmd` and supervise it until its whole process tree has exited
+    pub fn launch(self: &Arc<Self>, spec: LaunchSpec, mut cmd: Command) -> Result<SessionInfo, String> {
+        let child = cmd.spawn().map_err(|e| format!("Failed to launch {}: {}", spec.target, e))?;
+        let info = self.register(spec.clone(), Some(child.id()));
+        let supervisor = self.clone();
+        let session_id = info.session_id.clone();
+        std::thread::spawn(move || supervisor.watch(session_id, Some(child), spec));
+        Ok(info)
+    }
+
+    /// Track a game started through a launcher URI by its install directory
+    pub fn track_uri(self: &Arc<Self>, spec: LaunchSpec) -> SessionInfo {
+        let info = self.register(spec.clone(), None);
+        let supervisor = self.clone();
+        let session_id = info.session_id.clone();
+        std::thread::spawn(move || supervisor.watch(session_id, None, spec));
+        info
+    }
+
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.sessions.lock().unwrap().values().map(|s| s.info.clone()).collect()
+    }
+
+    /// Whether `pid` belongs to a game launched through Primus
+    pub fn owns(&self, pid: u32) -> bool {
+        self.sessions.lock().unwrap().values().any(|s| s.info.pids.contains(&pid))
+    }
+
+    fn notify_changed(&self) {
+        let sessions = self.sessions();
+        (self.on_change)(&sessions);
+    }
+
+    fn register(&self, spec: LaunchSpec, pid: Option<u32>) -> SessionInfo {
+        let info = SessionInfo {
+            session_id: uuid::Uuid::new_v4().simple().to_string(),
+            game_id: spec.game_id,
+            name: spec.name,
+            target: spec.target,
+            pid,
+            pids: pid.into_iter().collect(),
+            started_at: Utc::now(),
+        };
+        self.sessions.lock().unwrap().insert(info.session_id.clone(), Session { info: info.clone(), started: Instant::now() });
+        log_info!("[Supervisor] Started {} ({:?})", info.name, pid);
+        self.events.emit(EVENT_GAME_STARTED, serde_json::to_value(&info).unwrap_or_default());
+        self.notify_changed();
+        info
+    }
+
+    fn watch(&self, session_id: String, mut child: Option<Child>, spec: LaunchSpec) {
+        let install_dir = spec.install_dir;
+        let root_pid = child.as_ref().map(|c| c.id());
+        let mut exit_code: Option<i32> = None;
+        let mut root_done = child.is_none();
+        let mut seen_any = child.is_some();
+        // pid -> process start time, to survive PID reuse
+        let mut tracked: HashMap<u32, u64> = HashMap::new();
+        let mut sys = System::new();
+        let started = Instant::now();
+
+        loop {
+            if let Some(c) = child.as_mut() {
+                match c.try_wait() {
+                    Ok(Some(status)) => {
+                        exit_code = status.code();
+                        root_done = true;
+                        child = None;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        log_warn!("[Supervisor] Failed to poll {:?}: {}", root_pid, e);
+                        root_done = true;
+                        child = None;
+                    }
+                }
+            }
+
+            sys.refresh_processes_specifics(ProcessRefreshKind::new());
+            self.collect_tree(&sys, root_pid.filter(|_| !root_done), install_dir.as_deref(), &mut tracked);
+            if !tracked.is_empty() {
+                seen_any = true;
+            }
+
+            let mut pids: Vec<u32> = root_pid.filter(|_| !root_done).into_iter().collect();
+            pids.extend(tracked.keys().filter(|p| Some(**p) != root_pid));
+            pids.sort_unstable();
+            let changed = match self.sessions.lock().unwrap().get_mut(&session_id) {
+                Some(session) if session.info.pids != pids => {
+                    session.info.pids = pids;
+                    true
+                }
+                _ => false,
+            };
+            if changed {
+                self.notify_changed();
+            }
+
+            if root_done && tracked.is_empty() && (seen_any || started.elapsed() > URI_LAUNCH_GRACE) {
+                break;
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        self.finish(&session_id, exit_code, seen_any);
+
+        if let Some(hook) = spec.post_exit {
+            if let Err(e) = hook.run("Post-exit") {
+                log_warn!("[Supervisor] {}", e);
+            }
+        }
+    }
+
+    /// Refresh the set of processes belonging to a session: the root, every
+    /// descendant of an already tracked process, and anything running from
+    /// the install directory.
+    fn collect_tree(&self, sys: &System, root: Option<u32>, install_dir: Option<&Path>, tracked: &mut HashMap<u32, u64>) {
+        let processes = sys.processes();
+        tracked.retain(|pid, start| {
+            processes.get(&Pid::from_u32(*pid)).map(|p| p.start_time() == *start).unwrap_or(false)
+        });
+        if let Some(root) = root {
+            if let Some(p) = processes.get(&Pid::from_u32(root)) {
+                tracked.insert(root, p.start_time());
+            }
+        }
+        if let Some(dir) = install_dir.filter(|d| !d.as_os_str().is_empty()) {
+            for (pid, p) in processes {
+                if p.exe().starts_with(dir) {
+                    tracked.insert(pid.as_u32(), p.start_time());
+                }
+            }
+        }
+        // Descendants; orphans keep their (dead) parent's PID on Windows, and
+        // are caught while their parent is still tracked on Linux
+        loop {
+            let before = tracked.len();
+            for (pid, p) in processes {
+                if let Some(parent) = p.parent() {
+                    if tracked.contains_key(&parent.as_u32()) && !tracked.contains_key(&pid.as_u32()) {
+                        tracked.insert(pid.as_u32(), p.start_time());
+                    }
+                }
+            }
+            if tracked.len() == before {
+                break;
+            }
+        }
+    }
+
+    fn finish(&self, session_id: &str, exit_code: Option<i32>, detected: bool) {
+        let session = match self.sessions.lock().unwrap().remove(session_id) {
+            Some(session) => session,
+            None => return,
+        };
+        let duration = session.started.elapsed().as_secs();
+        log_info!("[Supervisor] {} exited (code {:?}) after {}s", session.info.name, exit_code, duration);
+
+        let mut payload = serde_json::to_value(&session.info).unwrap_or_default();
+        if let Some(obj) = payload.as_object_mut() {
+            obj.insert("exit_code".to_string(), json!(exit_code));
+            obj.insert("duration_secs".to_string(), json!(duration));
+            obj.insert("ended_at".to_string(), json!(Utc::now()));
+            obj.insert("detected".to_string(), json!(detected));
+        }
+        self.events.emit(EVENT_GAME_EXITED, payload);
+        self.notify_changed();
+    }
+}
+
+#[tauri::command]
+pub async fn get_game_sessions(supervisor: tauri::State<'_, Arc<Supervisor>>) -> Result<Vec<SessionInfo>, String> {
+    Ok(supervisor.sessions())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[derive(Default)]
+    struct RecordingEvents(Mutex<Vec<(String, Value)>>);
+
+    impl AgentEvents for RecordingEvents {
+        fn emit(&self, event: &str, payload: Value) {
+            self.0.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    impl RecordingEvents {
+        fn named(&self, event: &str) -> Vec<Value> {
+            self.0.lock().unwrap().iter().filter(|(e, _)| e == event).map(|(_, p)| p.clone()).collect()
+        }
+    }
+
+    fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done() {
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn launch_tracks_a_process_until_it_exits() {
+        let marker = std::env::temp_dir().join(format!("primus-supervisor-test-{}", uuid::Uuid::new_v4().simple()));
+        let events = Arc::new(RecordingEvents::default());
+        let changes: Arc<Mutex<Vec<usize>>> = Arc::default();
+        let recorded = changes.clone();
+        let supervisor = Arc::new(Supervisor::new(
+            events.clone(),
+            Box::new(move |sessions| recorded.lock().unwrap().push(sessions.len())),
+        ));
+
+        let spec = LaunchSpec {
+            game_id: Some("test:sleep".to_string()),
+            name: "Sleep".to_string(),
+            target: "sleep".to_string(),
+            install_dir: None,
+            post_exit: Some(Hook {
+                program: "/bin/sh".to_string(),
+                args: vec!["-c".to_string(), format!("touch '{}'", marker.display())],
+                timeout_secs: 5,
+            }),
+        };
+        let mut cmd = Command::new("sleep");
+        cmd.arg("0.1");
+        let info = supervisor.launch(spec, cmd).unwrap();
+        let pid = info.pid.unwrap();
+
+        assert!(supervisor.owns(pid));
+        assert_eq!(changes.lock().unwrap().first(), Some(&1));
+        assert_eq!(events.named(EVENT_GAME_STARTED).len(), 1);
+
+        wait_for("game-exited", || !events.named(EVENT_GAME_EXITED).is_empty());
+        let exited = events.named(EVENT_GAME_EXITED).remove(0);
+        assert_eq!(exited["session_id"], info.session_id.as_str());
+        assert_eq!(exited["exit_code"], 0);
+        assert!(exited["duration_secs"].as_u64().is_some());
+        assert_eq!(exited["detected"], true);
+
+        assert!(!supervisor.owns(pid));
+        assert!(supervisor.sessions().is_empty());
+        assert_eq!(changes.lock().unwrap().last(), Some(&0));
+
+        wait_for("post-exit hook", || marker.exists());
+        let _ = std::fs::remove_file(&marker);
+    }
+}
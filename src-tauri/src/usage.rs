@@ -0,0 +1,526 @@
+// Play-time ledger.
+//
+// One record per supervised game session (game, user, start/stop, foreground
+// time). Records live in an append-only journal, `usage.jsonl` in the app
+// config dir:
+//
+//     {"op":"record","record":{...}}     latest snapshot of a record wins
+//     {"op":"uploaded","ids":[...]}      acknowledged by the backend
+//
+// Open sessions are checkpointed periodically, so after a crash they are
+// closed at their last checkpoint instead of being lost. Closed records are
+// uploaded in batches with signed requests and retried until the backend
+// accepts them; the journal is compacted after every successful upload.
+
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::device_client::DeviceClient;
+use crate::supervisor::SessionInfo;
+
+const JOURNAL_FILE: &str = "usage.jsonl";
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(30);
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(60);
+const UPLOAD_BATCH: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UsageRecord {
+    /// Supervisor session id
+    pub id: String,
+    pub game_id: Option<String>,
+    pub game_name: String,
+    pub user: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+    /// Seconds one of the session's processes owned the foreground window
+    pub foreground_secs: u64,
+    /// Closed from a checkpoint after a crash rather than a real exit
+    #[serde(default)]
+    pub recovered: bool,
+}
+
+impl UsageRecord {
+    pub fn duration_secs(&self) -> i64 {
+        (self.ended_at.unwrap_or(self.updated_at) - self.started_at).num_seconds().max(0)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum JournalEntry {
+    Record { record: UsageRecord },
+    Uploaded { ids: Vec<String> },
+}
+
+#[derive(Serialize)]
+pub struct UsageSummary {
+    pub user: Option<String>,
+    pub open: Vec<UsageRecord>,
+    /// Closed records waiting for upload
+    pub pending: Vec<UsageRecord>,
+}
+
+#[derive(Default)]
+struct LedgerState {
+    user: Option<String>,
+    open: HashMap<String, UsageRecord>,
+    /// PIDs of each open session, for foreground attribution
+    pids: HashMap<String, Vec<u32>>,
+    pending: Vec<UsageRecord>,
+}
+
+pub struct UsageLedger {
+    config_dir: PathBuf,
+    state: Mutex<LedgerState>,
+    /// Serializes journal writes and compaction
+    journal: Mutex<()>,
+}
+
+impl UsageLedger {
+    /// Load the journal, closing sessions left open by a crash
+    pub fn open(config_dir: PathBuf) -> Self {
+        let ledger = Self {
+            config_dir,
+            state: Mutex::new(LedgerState::default()),
+            journal: Mutex::new(()),
+        };
+
+        let mut records: HashMap<String, UsageRecord> = HashMap::new();
+        if let Ok(data) = fs::read_to_string(ledger.path()) {
+            for line in data.lines().filter(|l| !l.trim().is_empty()) {
+                // A torn last line from a crash is skipped
+                match serde_json::from_str::<JournalEntry>(line) {
+                    Ok(JournalEntry::Record { record }) => {
+                        records.insert(record.id.clone(), record);
+                    }
+                    Ok(JournalEntry::Uploaded { ids }) => {
+                        for id in ids {
+                            records.remove(&id);
+                        }
+                    }
+                    Err(e) => log_warn!("[Usage] Skipping corrupt journal line: {}", e),
+                }
+            }
+        }
+
+        let mut pending: Vec<UsageRecord> = records.into_values().collect();
+        for record in pending.iter_mut().filter(|r| r.ended_at.is_none()) {
+            record.ended_at = Some(record.updated_at);
+            record.recovered = true;
+            log_info!("[Usage] Recovered unfinished session {} ({})", record.id, record.game_name);
+        }
+        pending.sort_by_key(|r| r.started_at);
+        ledger.state.lock().unwrap().pending = pending;
+        if let Err(e) = ledger.compact() {
+            log_warn!("[Usage] Failed to compact journal: {}", e);
+        }
+        ledger
+    }
+
+    fn path(&self) -> PathBuf {
+        self.config_dir.join(JOURNAL_FILE)
+    }
+
+    fn append(&self, entries: &[JournalEntry]) {
+        let _guard = self.journal.lock().unwrap();
+        let result = (|| -> Result<(), String> {
+            fs::create_dir_all(&self.config_dir).map_err(|e| e.to_string())?;
+            let mut file = OpenOptions::new().create(true).append(true).open(self.path()).map_err(|e| e.to_string())?;
+            let mut data = String::new();
+            for entry in entries {
+                data.push_str(&serde_json::to_string(entry).map_err(|e| e.to_string())?);
+                data.push('\n');
+            }
+            file.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+            file.sync_data().map_err(|e| e.to_string())
+        })();
+        if let Err(e) = result {
+            log_error!("[Usage] Failed to write journal: {}", e);
+        }
+    }
+
+    /// Rewrite the journal with only the records still needed
+    fn compact(&self) -> Result<(), String> {
+        let _guard = self.journal.lock().unwrap();
+        let data = {
+            let state = self.state.lock().unwrap();
+            let mut data = String::new();
+            for record in state.pending.iter().chain(state.open.values()) {
+                let entry = JournalEntry::Record { record: record.clone() };
+                data.push_str(&serde_json::to_string(&entry).map_err(|e| e.to_string())?);
+                data.push('\n');
+            }
+            data
+        };
+        fs::create_dir_all(&self.config_dir).map_err(|e| e.to_string())?;
+        let tmp = self.path().with_extension("jsonl.tmp");
+        {
+            let mut file = fs::File::create(&tmp).map_err(|e| e.to_string())?;
+            file.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+            file.sync_all().map_err(|e| e.to_string())?;
+        }
+        fs::rename(&tmp, self.path()).map_err(|e| e.to_string())
+    }
+
+    /// Reconcile with the supervisor's live sessions: open records for new
+    /// sessions and close records whose session ended.
+    pub fn sync(&self, sessions: &[SessionInfo]) {
+        let now = Utc::now();
+        let mut entries = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let user = state.user.clone();
+            for session in sessions {
+                state.pids.insert(session.session_id.clone(), session.pids.clone());
+                if !state.open.contains_key(&session.session_id) {
+                    let record = UsageRecord {
+                        id: session.session_id.clone(),
+                        game_id: session.game_id.clone(),
+                        game_name: session.name.clone(),
+                        user: user.clone(),
+                        started_at: session.started_at,
+                        ended_at: None,
+                        updated_at: now,
+                        foreground_secs: 0,
+                        recovered: false,
+                    };
+                    entries.push(JournalEntry::Record { record: record.clone() });
+                    state.open.insert(record.id.clone(), record);
+                }
+            }
+
+            let ended: Vec<String> = state.open.keys()
+                .filter(|id| !sessions.iter().any(|s| &s.session_id == *id))
+                .cloned()
+                .collect();
+            for id in ended {
+                state.pids.remove(&id);
+                if let Some(mut record) = state.open.remove(&id) {
+                    record.ended_at = Some(now);
+                    record.updated_at = now;
+                    log_info!("[Usage] {} played {}s ({}s in foreground)", record.game_name, record.duration_secs(), record.foreground_secs);
+                    entries.push(JournalEntry::Record { record: record.clone() });
+                    state.pending.push(record);
+                }
+            }
+        }
+        if !entries.is_empty() {
+            self.append(&entries);
+        }
+    }
+
+    /// Attribute sessions started from now on to `user` (`None` on logout)
+    pub fn set_user(&self, user: Option<String>) {
+        self.state.lock().unwrap().user = user.filter(|u| !u.trim().is_empty());
+    }
+
+    pub fn summary(&self) -> UsageSummary {
+        let state = self.state.lock().unwrap();
+        UsageSummary {
+            user: state.user.clone(),
+            open: state.open.values().cloned().collect(),
+            pending: state.pending.clone(),
+        }
+    }
+
+    /// Credit foreground time and checkpoint open sessions
+    fn sample(&self, elapsed: Duration, checkpoint: bool) {
+        // Nothing to attribute; skip the foreground window lookup
+        if self.state.lock().unwrap().open.is_empty() {
+            return;
+        }
+        let foreground = foreground_pid();
+        let now = Utc::now();
+        let mut entries = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let LedgerState { open, pids, .. } = &mut *state;
+            for (id, record) in open.iter_mut() {
+                if let (Some(fg), Some(session_pids)) = (foreground, pids.get(id)) {
+                    if session_pids.contains(&fg) {
+                        record.foreground_secs += elapsed.as_secs();
+                    }
+                }
+                record.updated_at = now;
+                if checkpoint {
+                    entries.push(JournalEntry::Record { record: record.clone() });
+                }
+            }
+        }
+        if !entries.is_empty() {
+            self.append(&entries);
+        }
+    }
+
+    /// Upload one batch of closed records. Returns how many were accepted.
+    async fn upload_batch(&self, client: &DeviceClient) -> Result<usize, String> {
+        let batch: Vec<UsageRecord> = self.state.lock().unwrap().pending.iter().take(UPLOAD_BATCH).cloned().collect();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        let records: Vec<_> = batch.iter().map(|r| json!({
+            "session_id": r.id,
+            "game_id": r.game_id,
+            "game_name": r.game_name,
+            "user": r.user,
+            "started_at": r.started_at,
+            "ended_at": r.ended_at,
+            "duration_secs": r.duration_secs(),
+            "foreground_secs": r.foreground_secs,
+            "recovered": r.recovered,
+        })).collect();
+        client.post("/clientpc/usage", &json!({ "records": records })).await?;
+
+        let ids: Vec<String> = batch.into_iter().map(|r| r.id).collect();
+        self.append(&[JournalEntry::Uploaded { ids: ids.clone() }]);
+        self.state.lock().unwrap().pending.retain(|r| !ids.contains(&r.id));
+        Ok(ids.len())
+    }
+
+    /// Start foreground sampling and the background uploader
+    pub fn spawn(self: &Arc<Self>) {
+        let ledger = self.clone();
+        std::thread::spawn(move || {
+            let mut last = Instant::now();
+            let mut last_checkpoint = Instant::now();
+            loop {
+                std::thread::sleep(SAMPLE_INTERVAL);
+                let checkpoint = last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL;
+                ledger.sample(last.elapsed(), checkpoint);
+                last = Instant::now();
+                if checkpoint {
+                    last_checkpoint = last;
+                }
+            }
+        });
+
+        let ledger = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(UPLOAD_INTERVAL).await;
+                if ledger.state.lock().unwrap().pending.is_empty() {
+                    continue;
+                }
+                // Credentials may only appear after enrollment
+                let client = match DeviceClient::from_config_dir(&ledger.config_dir) {
+                    Ok(client) => client,
+                    Err(_) => continue,
+                };
+                let mut uploaded = 0;
+                loop {
+                    match ledger.upload_batch(&client).await {
+                        Ok(0) => break,
+                        Ok(n) => uploaded += n,
+                        Err(e) => {
+                            log_warn!("[Usage] Upload failed, will retry: {}", e);
+                            break;
+                        }
+                    }
+                }
+                if uploaded > 0 {
+                    log_info!("[Usage] Uploaded {} usage records", uploaded);
+                    if let Err(e) = ledger.compact() {
+                        log_warn!("[Usage] Failed to compact journal: {}", e);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// PID owning the foreground window
+#[cfg(target_os = "windows")]
+fn foreground_pid() -> Option<u32> {
+    use winapi::um::winuser::{GetForegroundWindow, GetWindowThreadProcessId};
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        (pid != 0).then_some(pid)
+    }
+}
+
+/// PID owning the active window, via EWMH properties (X11/XWayland)
+#[cfg(not(target_os = "windows"))]
+fn foreground_pid() -> Option<u32> {
+    use std::process::Command;
+    let xprop = |args: &[&str]| -> Option<String> {
+        let output = Command::new("xprop").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    // `_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007`
+    let active = xprop(&["-root", "_NET_ACTIVE_WINDOW"])?;
+    let window = active.split_whitespace().last()?.trim_end_matches(',').to_string();
+    if window == "0x0" {
+        return None;
+    }
+    // `_NET_WM_PID(CARDINAL) = 12345`
+    let pid = xprop(&["-id", &window, "_NET_WM_PID"])?;
+    pid.split('=').nth(1)?.trim().parse().ok()
+}
+
+#[tauri::command]
+pub async fn set_usage_user(ledger: tauri::State<'_, Arc<UsageLedger>>, user: Option<String>) -> Result<(), String> {
+    ledger.set_user(user);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_usage_summary(ledger: tauri::State<'_, Arc<UsageLedger>>) -> Result<UsageSummary, String> {
+    Ok(ledger.summary())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("primus-usage-test-{}", uuid::Uuid::new_v4().simple()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn record(id: &str, minutes_ago: i64, ended: bool) -> UsageRecord {
+        let started_at = Utc::now() - chrono::Duration::minutes(minutes_ago);
+        let updated_at = started_at + chrono::Duration::minutes(1);
+        UsageRecord {
+            id: id.to_string(),
+            game_id: None,
+            game_name: format!("Game {}", id),
+            user: None,
+            started_at,
+            ended_at: if ended { Some(updated_at) } else { None },
+            updated_at,
+            foreground_secs: 0,
+            recovered: false,
+        }
+    }
+
+    fn line(entry: &JournalEntry) -> String {
+        serde_json::to_string(entry).unwrap() + "\n"
+    }
+
+    fn journal_lines(dir: &std::path::Path) -> usize {
+        fs::read_to_string(dir.join(JOURNAL_FILE)).unwrap().lines().count()
+    }
+
+    fn session(id: &str) -> SessionInfo {
+        SessionInfo {
+            session_id: id.to_string(),
+            game_id: Some("steam:730".to_string()),
+            name: "Counter-Strike 2".to_string(),
+            target: "steam://rungameid/730".to_string(),
+            pid: None,
+            pids: vec![],
+            started_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn replay_skips_a_torn_last_line() {
+        let dir = temp_dir();
+        let data = line(&JournalEntry::Record { record: record("a", 10, true) }) + r#"{"op":"record","reco"#;
+        fs::write(dir.join(JOURNAL_FILE), data).unwrap();
+
+        let ledger = UsageLedger::open(dir.clone());
+        let pending = ledger.summary().pending;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, "a");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn replay_drops_uploaded_records() {
+        let dir = temp_dir();
+        let data = line(&JournalEntry::Record { record: record("a", 10, true) })
+            + &line(&JournalEntry::Record { record: record("b", 5, true) })
+            + &line(&JournalEntry::Uploaded { ids: vec!["a".to_string()] });
+        fs::write(dir.join(JOURNAL_FILE), data).unwrap();
+
+        let ledger = UsageLedger::open(dir.clone());
+        let ids: Vec<String> = ledger.summary().pending.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["b".to_string()]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn replay_recovers_unfinished_records_at_their_checkpoint() {
+        let dir = temp_dir();
+        let open = record("a", 10, false);
+        let data = line(&JournalEntry::Record { record: open.clone() });
+        fs::write(dir.join(JOURNAL_FILE), data).unwrap();
+
+        let ledger = UsageLedger::open(dir.clone());
+        let pending = ledger.summary().pending;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].ended_at, Some(open.updated_at));
+        assert!(pending[0].recovered);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn compaction_keeps_only_the_latest_pending_records() {
+        let dir = temp_dir();
+        let mut a = record("a", 10, false);
+        let first = line(&JournalEntry::Record { record: a.clone() });
+        a.ended_at = Some(a.updated_at);
+        let data = first
+            + &line(&JournalEntry::Record { record: a })
+            + &line(&JournalEntry::Record { record: record("b", 5, true) })
+            + &line(&JournalEntry::Record { record: record("c", 3, true) })
+            + &line(&JournalEntry::Uploaded { ids: vec!["c".to_string()] });
+        fs::write(dir.join(JOURNAL_FILE), data).unwrap();
+
+        UsageLedger::open(dir.clone());
+        assert_eq!(journal_lines(&dir), 2);
+        let reopened = UsageLedger::open(dir.clone());
+        let ids: Vec<String> = reopened.summary().pending.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["a".to_string(), "b".to_string()]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn sync_opens_and_closes_records() {
+        let dir = temp_dir();
+        let ledger = UsageLedger::open(dir.clone());
+        ledger.set_user(Some("alice".to_string()));
+
+        ledger.sync(&[session("s1")]);
+        let summary = ledger.summary();
+        assert_eq!(summary.open.len(), 1);
+        assert_eq!(summary.open[0].user.as_deref(), Some("alice"));
+        assert!(summary.pending.is_empty());
+
+        // Unchanged sessions don't write anything
+        ledger.sync(&[session("s1")]);
+        assert_eq!(journal_lines(&dir), 1);
+
+        ledger.sync(&[]);
+        let summary = ledger.summary();
+        assert!(summary.open.is_empty());
+        assert_eq!(summary.pending.len(), 1);
+        assert!(summary.pending[0].ended_at.is_some());
+        assert!(!summary.pending[0].recovered);
+
+        // The closed record survives a restart without being marked recovered
+        let reopened = UsageLedger::open(dir.clone());
+        let pending = reopened.summary().pending;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, "s1");
+        assert!(!pending[0].recovered);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -7,6 +7,7 @@ import axios from "axios";
 
 // Import Tauri API for kiosk functionality
 import { invoke } from './utils/invoke';
+import { listen } from '@tauri-apps/api/event';
 
 // Import SetupScreen for device registration
 import SetupScreen from './components/screens/SetupScreen';
@@ -86,6 +87,14 @@ export default function App() {
         }
     }, [jwt]);
 
+    // Attribute play time recorded by the Rust usage ledger to this user, and
+    // move the kiosk between its locked and in-session states
+    useEffect(() => {
+        const user = currentUser ? String(currentUser.id ?? currentUser.email ?? '') : null;
+        invoke('set_usage_user', { user: user || null }).catch(() => {});
+        invoke('set_kiosk_session', { active: !!currentUser }).catch(() => {});
+    }, [currentUser]);
+
     // Fetch user balance data
     useEffect(() => {
         if (!currentUser || !pcId) return;
@@ -139,13 +148,6 @@ export default function App() {
                     console.warn('Backend not reachable:', connectionError.message);
                 }
 
-                try {
-                    await invoke('register_pc_with_backend');
-                    console.log('PC registered with backend');
-                } catch (regError) {
-                    console.warn('PC registration failed:', regError);
-                }
-
                 try {
                     await invoke('setup_complete_kiosk');
                     await invoke('enable_kiosk_shortcuts');
@@ -168,8 +170,9 @@ export default function App() {
 
     // Heartbeat and command polling
     useEffect(() => {
-        let heartbeatTimer;
-        let commandTimer;
+        let unlistenHeartbeat;
+        let unlistenCommands;
+        let unlistenOverlay;
 
         const registerAndHeartbeat = async () => {
             try {
@@ -183,70 +186,31 @@ export default function App() {
                     console.warn('[Primus] Could not load device credentials:', credErr);
                 }
 
-                const sendHeartbeat = async () => {
-                    try {
-                        const res = await invoke("send_heartbeat");
-                        console.log('[Primus] Heartbeat sent via Tauri:', res);
-                        const isLocked = res?.status === 'locked';
-                        setLocked(isLocked);
-                    } catch (tauriErr) {
-                        if (pcId) {
-                            try {
-                                const res = await axios.post(
-                                    `${getApiBase()}/api/clientpc/heartbeat/${pcId}`,
-                                    null,
-                                    {
-                                        headers: {
-                                            ...authHeaders(),
-                                            ...csrfHeaders(),
-                                            'X-PC-ID': String(pcId)
-                                        }
-                                    }
-                                );
-                                setLocked(res?.data?.status === 'locked');
-                            } catch (httpErr) {
-                                console.warn('[Primus] HTTP heartbeat failed:', httpErr);
-                            }
-                        }
+                // Heartbeats are sent by the Rust command agent; the UI only
+                // follows the lock state it reports
+                unlistenHeartbeat = await listen('agent-heartbeat', (e) => {
+                    setLocked(e.payload?.status === 'locked');
+                });
+
+                // Commands are pulled and executed by the Rust command agent;
+                // only UI-side reactions are handled here
+                unlistenCommands = await listen('agent-command', (e) => {
+                    const { event, payload } = e.payload || {};
+                    if (event === 'message' && payload) {
+                        const text = payload.text || (typeof payload === 'string' ? payload : 'Message');
+                        invoke('show_notification', { title: 'Message from staff', body: text, urgency: 'normal' })
+                            .catch(() => showToast(text));
+                    }
+                    if (event === 'logout') {
+                        localStorage.removeItem('primus_jwt');
+                        window.location.reload();
                     }
-                };
-
-                await sendHeartbeat();
-                heartbeatTimer = setInterval(sendHeartbeat, 20000); // 20 second heartbeat
-
-                const pollCommands = async () => {
-                    try {
-                        if (!pcId) return;
-                        const res = await axios.post(
-                            `${getApiBase()}/api/command/pull`,
-                            new URLSearchParams({ pc_id: String(pcId) }),
-                            { headers: { ...authHeaders(), 'Content-Type': 'application/x-www-form-urlencoded', ...csrfHeaders() } }
-                        );
-                        if (res.data && res.data.command) {
-                            const cmd = res.data.command;
-                            if (cmd === 'message' && res.data.params) {
-                                try {
-                                    const p = JSON.parse(res.data.params);
-                                    showToast(p.text || 'Message');
-                                } catch {
-                                    showToast(res.data.params);
-                                }
-                            }
-                            if (cmd === 'logout') {
-                                localStorage.removeItem('primus_jwt');
-                                window.location.reload();
-                            }
-                            if (cmd === 'shutdown') {
-                                try { await invoke('system_shutdown'); } catch { }
-                            }
-                            if (cmd === 'restart') {
-                                try { await invoke('system_restart'); } catch { }
-                            }
-                        }
-                    } catch { }
-                };
-
-                commandTimer = setInterval(pollCommands, 5000);
+                });
+                // Native notifications unavailable: show them in-app instead
+                unlistenOverlay = await listen('notification-overlay', (e) => {
+                    const { title, body } = e.payload || {};
+                    showToast(body ? `${title}: ${body}` : title);
+                });
             } catch (e) {
                 console.warn('[Primus] Heartbeat setup failed:', e);
             }
@@ -255,8 +219,9 @@ export default function App() {
         registerAndHeartbeat();
 
         return () => {
-            if (heartbeatTimer) clearInterval(heartbeatTimer);
-            if (commandTimer) clearInterval(commandTimer);
+            if (unlistenHeartbeat) unlistenHeartbeat();
+            if (unlistenCommands) unlistenCommands();
+            if (unlistenOverlay) unlistenOverlay();
         };
     }, [currentUser, pcId]);
 
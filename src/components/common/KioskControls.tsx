@@ -3,30 +3,71 @@ import { invoke } from '../../utils/invoke';
 import { Settings } from 'lucide-react';
 import toast from 'react-hot-toast';
 
+type RegData = { type: 'sz'; data: string } | { type: 'dword'; data: number };
+
+interface PlanStep {
+  key: string;
+  name: string;
+  action: 'set' | 'delete' | 'unchanged';
+  current: RegData | null;
+  target: RegData | null;
+}
+
+/** Result of `setup_complete_kiosk` / `teardown_kiosk` */
+interface SetupReport {
+  dry_run: boolean;
+  steps: PlanStep[];
+  restart_required: boolean;
+}
+
+const showValue = (value: RegData | null) => (value === null ? '(absent)' : String(value.data));
+
+const changedCount = (report: SetupReport) => report.steps.filter((s) => s.action !== 'unchanged').length;
+
 const KioskControls: React.FC = () => {
   const [isLoading, setIsLoading] = useState(false);
+  const [report, setReport] = useState<SetupReport | null>(null);
 
-  const enableCompleteKiosk = async () => {
+  const run = async (command: 'setup_complete_kiosk' | 'teardown_kiosk', dryRun: boolean) => {
     setIsLoading(true);
     try {
-      await invoke<string>('setup_complete_kiosk');
-      toast.success('Kiosk mode enabled! Restart required.');
-    } catch (error: any) {
-      toast.error('Failed to enable kiosk mode. Please run as Administrator.');
+      const result = await invoke<SetupReport>(command, { dryRun });
+      setReport(result);
+      return result;
     } finally {
       setIsLoading(false);
     }
   };
 
+  const previewKiosk = async () => {
+    try {
+      const result = await run('setup_complete_kiosk', true);
+      toast(`${changedCount(result)} settings would change`);
+    } catch (error: any) {
+      toast.error(`Failed to plan kiosk setup: ${error}`);
+    }
+  };
+
+  const enableCompleteKiosk = async () => {
+    try {
+      const result = await run('setup_complete_kiosk', false);
+      if (changedCount(result) === 0) {
+        toast.success('Kiosk mode already enabled');
+      } else {
+        toast.success(result.restart_required ? 'Kiosk mode enabled! Restart required.' : 'Kiosk mode enabled!');
+      }
+    } catch (error: any) {
+      toast.error(`Failed to enable kiosk mode: ${error}`);
+    }
+  };
+
   const disableKiosk = async () => {
-    setIsLoading(true);
     try {
-      await invoke<string>('disable_kiosk_mode');
-      toast.success('Kiosk mode disabled! Restart required.');
+      const result = await run('teardown_kiosk', false);
+      const restored = `${changedCount(result)} settings restored`;
+      toast.success(result.restart_required ? `Kiosk mode disabled, ${restored}. Restart required.` : `Kiosk mode disabled, ${restored}.`);
     } catch (error: any) {
-      toast.error('Failed to disable kiosk mode. Please run as Administrator.');
-    } finally {
-      setIsLoading(false);
+      toast.error(`Failed to disable kiosk mode: ${error}`);
     }
   };
 
@@ -37,6 +78,14 @@ const KioskControls: React.FC = () => {
           <Settings className="w-5 h-5 text-primary-400" />
           <span className="text-sm font-medium">Kiosk Mode</span>
 
+          <button
+            onClick={previewKiosk}
+            disabled={isLoading}
+            className="px-3 py-1 bg-secondary-600 hover:bg-secondary-700 text-white text-xs rounded disabled:bg-gray-600"
+          >
+            Preview
+          </button>
+
           <button
             onClick={enableCompleteKiosk}
             disabled={isLoading}
@@ -53,6 +102,30 @@ const KioskControls: React.FC = () => {
             Disable
           </button>
         </div>
+
+        {report && (
+          <div className="mt-3 text-xs text-secondary-300">
+            <div className="flex items-center justify-between mb-1">
+              <span className="font-medium">
+                {report.dry_run ? 'Planned changes' : 'Applied changes'}
+                {report.restart_required && ' (restart required)'}
+              </span>
+              <button onClick={() => setReport(null)} className="text-secondary-400 hover:text-white">
+                Close
+              </button>
+            </div>
+            <ul className="space-y-0.5 max-h-40 overflow-y-auto font-mono">
+              {report.steps.map((step) => (
+                <li key={`${step.key}\\${step.name}`} className={step.action === 'unchanged' ? 'text-secondary-500' : ''}>
+                  {step.action === 'unchanged' && `keep ${step.name} = ${showValue(step.current)}`}
+                  {step.action === 'set' && `set ${step.name}: ${showValue(step.current)} → ${showValue(step.target)}`}
+                  {step.action === 'delete' && `delete ${step.name} (was ${showValue(step.current)})`}
+                </li>
+              ))}
+              {report.steps.length === 0 && <li>Nothing to change</li>}
+            </ul>
+          </div>
+        )}
       </div>
     </div>
   );
@@ -2,6 +2,7 @@ import React, { useEffect, useState } from 'react';
 import axios from 'axios';
 import { getApiBase, authHeaders } from './utils/api';
 import { invoke } from './utils/invoke';
+import { convertFileSrc } from '@tauri-apps/api/tauri';
 
 // --- Helper Components for Icons ---
 const SearchIcon = ({ className }) => (
@@ -180,7 +181,12 @@ export default function Games({ currentUser }) {
     const launchDetectedGame = async (game) => {
         try {
             console.log('Launching game:', game.name, 'from', game.exe_path);
-            await invoke('launch_game', { exePath: game.exe_path });
+            await invoke('launch_game', {
+                exePath: game.exe_path,
+                gameId: game.id || null,
+                name: game.name,
+                installPath: game.install_path || null
+            });
             console.log(`✅ ${game.name} launched successfully`);
 
             // Show success message
@@ -322,7 +328,9 @@ export default function Games({ currentUser }) {
                                     >
                                         <div className="text-center">
                                             <div className="w-12 h-12 bg-primary/20 rounded-lg flex items-center justify-center mx-auto mb-3 text-2xl">
-                                                🎮
+                                                {game.icon_path ? (
+                                                    <img src={convertFileSrc(game.icon_path)} alt="" className="w-10 h-10 object-contain" />
+                                                ) : '🎮'}
                                             </div>
                                             <h4 className="text-white font-semibold mb-2 truncate" title={game.name}>{game.name}</h4>
                                             <p className="text-gray-400 text-xs mb-3 truncate" title={game.install_path}>{game.install_path}</p>
@@ -1,7 +1,8 @@
 import { useState, useEffect } from 'react';
 import { Search, Monitor, Gamepad, Crosshair, Sword, Users, Zap, ChevronRight, HardDrive, Plus, FolderOpen, Trash2 } from 'lucide-react';
-import { invoke } from '@tauri-apps/api/tauri';
+import { invoke, convertFileSrc } from '@tauri-apps/api/tauri';
 import { open } from '@tauri-apps/api/dialog';
+import { listen } from '@tauri-apps/api/event';
 import GameCard from '../components/GameCard';
 import { apiService } from '../services/apiClient';
 import { showToast } from '../utils/api';
@@ -36,24 +37,42 @@ const GamesPage = () => {
     const [newGameName, setNewGameName] = useState('');
     const [newGamePath, setNewGamePath] = useState('');
 
-    // Load local games from localStorage
+    // Local games live in the Rust game catalog; older clients kept them in
+    // localStorage, so import those once
+    const toLocalGame = (g) => ({
+        ...g,
+        title: g.name,
+        is_local: true,
+        genre: g.categories?.[0] || 'Local Game',
+        tags: g.categories?.length ? g.categories : ['Local']
+    });
+
     useEffect(() => {
-        try {
-            const stored = localStorage.getItem(LOCAL_GAMES_KEY);
-            if (stored) {
-                setLocalGames(JSON.parse(stored));
+        let unlisten;
+        const loadCatalog = async () => {
+            try {
+                const stored = JSON.parse(localStorage.getItem(LOCAL_GAMES_KEY) || '[]');
+                if (stored.length > 0) {
+                    await invoke('import_catalog_games', {
+                        games: stored.map(g => ({ name: g.name || g.title, exe_path: g.exe_path }))
+                    });
+                    localStorage.removeItem(LOCAL_GAMES_KEY);
+                }
+                const catalog = await invoke('list_catalog_games');
+                setLocalGames(catalog.map(toLocalGame));
+                unlisten = await listen('catalog-changed', (e) => {
+                    setLocalGames((e.payload || []).map(toLocalGame));
+                });
+            } catch (e) {
+                console.error('Failed to load local games:', e);
             }
-        } catch (e) {
-            console.error('Failed to load local games:', e);
-        }
+        };
+        loadCatalog();
+        return () => {
+            if (unlisten) unlisten();
+        };
     }, []);
 
-    // Save local games to localStorage
-    const saveLocalGames = (games) => {
-        setLocalGames(games);
-        localStorage.setItem(LOCAL_GAMES_KEY, JSON.stringify(games));
-    };
-
     // Initial Fetch
     useEffect(() => {
         fetchGames();
@@ -131,27 +150,24 @@ const GamesPage = () => {
     };
 
     // Add local game
-    const handleAddGame = () => {
+    const handleAddGame = async () => {
         if (!newGameName.trim() || !newGamePath.trim()) {
             showToast('Please enter both name and path');
             return;
         }
 
-        const newGame = {
-            id: `local_${Date.now()}`,
-            name: newGameName,
-            title: newGameName,
-            exe_path: newGamePath,
-            is_local: true,
-            genre: 'Local Game',
-            tags: ['Local']
-        };
-
-        saveLocalGames([...localGames, newGame]);
-        setNewGameName('');
-        setNewGamePath('');
-        setShowAddGame(false);
-        showToast(`Added "${newGameName}" to local games`);
+        try {
+            const game = await invoke('add_catalog_game', {
+                game: { name: newGameName, exe_path: newGamePath }
+            });
+            setLocalGames(prev => [...prev.filter(g => g.id !== game.id), toLocalGame(game)]);
+            setNewGameName('');
+            setNewGamePath('');
+            setShowAddGame(false);
+            showToast(`Added "${game.name}" to local games`);
+        } catch (e) {
+            showToast(`Failed to add game: ${e}`);
+        }
     };
 
     // Browse for executable
@@ -173,17 +189,33 @@ const GamesPage = () => {
     };
 
     // Remove local game
-    const handleRemoveLocalGame = (gameId) => {
-        const updated = localGames.filter(g => g.id !== gameId);
-        saveLocalGames(updated);
-        showToast('Game removed');
+    const handleRemoveLocalGame = async (gameId) => {
+        try {
+            await invoke('remove_catalog_game', { id: gameId });
+            setLocalGames(prev => prev.filter(g => g.id !== gameId));
+            showToast('Game removed');
+        } catch (e) {
+            showToast(`Failed to remove game: ${e}`);
+        }
+    };
+
+    // Catalog id launch profiles are keyed by; café games from the backend
+    // are synced into the catalog as `cafe:<id>`
+    const catalogId = (game) => {
+        if (game.is_local) return game.id;
+        return game.id != null ? `cafe:${game.id}` : null;
     };
 
     // Launch local game
     const handleLaunchGame = async (game) => {
         if (game.exe_path) {
             try {
-                await invoke('launch_game', { path: game.exe_path });
+                await invoke('launch_game', {
+                    exePath: game.exe_path,
+                    gameId: catalogId(game),
+                    name: game.title || game.name,
+                    installPath: game.install_path || null
+                });
                 showToast(`Launching ${game.title || game.name}...`);
             } catch (e) {
                 console.error('Failed to launch game:', e);
@@ -214,7 +246,7 @@ const GamesPage = () => {
     const mapGame = (g) => ({
         id: g.id,
         title: g.title || g.name,
-        image: g.image || g.cover_url || g.icon_url || 'https://picsum.photos/400/600',
+        image: g.image || g.cover_url || g.icon_url || (g.icon_path && convertFileSrc(g.icon_path)) || 'https://picsum.photos/400/600',
         genre: g.genre || 'Game',
         badge: g.is_popular ? 'Popular' : undefined,
         tags: g.tags || []
@@ -255,6 +287,9 @@ const GamesPage = () => {
                                         borderRadius: 'var(--radius-sm)',
                                         fontSize: '0.875rem'
                                     }}>
+                                        {game.icon_path && (
+                                            <img src={convertFileSrc(game.icon_path)} alt="" style={{ width: 20, height: 20, marginRight: 'var(--spacing-sm)' }} />
+                                        )}
                                         <span style={{ flex: 1, overflow: 'hidden', textOverflow: 'ellipsis', whiteSpace: 'nowrap' }}>
                                             {game.name}
                                         </span>
@@ -1,3 +1,5 @@
+import { invoke } from "./invoke";
+
 const ENV_BASE = import.meta.env.VITE_API_BASE || import.meta.env.VITE_API_BASE_URL || null;
 
 // Production API URL - used as default for Tauri builds
@@ -46,7 +48,11 @@ export function getApiBase() {
 
 export function setApiBase(url) {
   if (url && typeof url === "string") {
-    localStorage.setItem("primus_api_base", url.replace(/\/$/, ""));
+    const base = url.replace(/\/$/, "");
+    localStorage.setItem("primus_api_base", base);
+    // Keep the Rust client config (command agent, enrollment) on the same backend
+    invoke("update_config", { patch: { backend_url: base } })
+      .catch((e) => console.warn("[Primus] Failed to update client config:", e));
   }
 }
 